@@ -0,0 +1,77 @@
+//! Builds a Polyglot opening book from a PGN database.
+//!
+//! Usage: `cargo run --release --example book_builder -- <games.pgn> <output> [options]`
+//!
+//! Options:
+//!
+//! * `--ply <n>`: the number of plies of each game to add, 20 by default.
+//! * `--min-games <n>`: the number of games a move must be played in, 1 by default.
+//! * `--min-elo <n>`: the rating both players must have for a game to be added.
+//! * `--csv` or `--json`: writes the moves of the book for inspection, rather than a `.bin`.
+
+extern crate pleco;
+
+use std::env;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::process;
+
+use pleco::book::builder::BookBuilder;
+
+enum Format {
+    Polyglot,
+    Csv,
+    Json,
+}
+
+fn usage() -> ! {
+    eprintln!("usage: book_builder <games.pgn> <output> [--ply n] [--min-games n] [--min-elo n] [--csv | --json]");
+    process::exit(1);
+}
+
+fn parse_num(arg: Option<String>) -> u32 {
+    arg.and_then(|n| n.parse().ok()).unwrap_or_else(|| usage())
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let (input, output) = match (args.next(), args.next()) {
+        (Some(input), Some(output)) => (input, output),
+        _ => usage(),
+    };
+
+    let mut builder = BookBuilder::new();
+    let mut format = Format::Polyglot;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ply" => { builder.max_ply(parse_num(args.next()) as usize); },
+            "--min-games" => { builder.min_games(parse_num(args.next())); },
+            "--min-elo" => { builder.min_rating(parse_num(args.next())); },
+            "--csv" => format = Format::Csv,
+            "--json" => format = Format::Json,
+            _ => usage(),
+        }
+    }
+
+    let pgn = File::open(&input).unwrap_or_else(|e| {
+        eprintln!("unable to open {}: {}", input, e);
+        process::exit(1);
+    });
+    let games = builder.add_pgn(BufReader::new(pgn));
+    println!("added {} games", games);
+
+    let out = BufWriter::new(File::create(&output).unwrap_or_else(|e| {
+        eprintln!("unable to create {}: {}", output, e);
+        process::exit(1);
+    }));
+    let written = match format {
+        Format::Polyglot => builder.write_polyglot(out),
+        Format::Csv => builder.write_csv(out),
+        Format::Json => builder.write_json(out),
+    };
+    if let Err(e) = written {
+        eprintln!("unable to write {}: {}", output, e);
+        process::exit(1);
+    }
+    println!("wrote {} moves to {}", builder.records().len(), output);
+}
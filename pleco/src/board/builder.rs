@@ -0,0 +1,299 @@
+//! Contains [`BoardBuilder`], for constructing a [`Board`] piece by piece.
+//!
+//! [`BoardBuilder`]: struct.BoardBuilder.html
+//! [`Board`]: ../struct.Board.html
+
+use std::fmt;
+
+use core::sq::{SQ, NO_SQ};
+use core::{Player, PieceType, Rank, CastleType};
+
+use super::{Board, BoardError, FenBuildError, fen};
+use super::castle_rights::Castling;
+use super::piece_locations::PieceLocations;
+
+/// Represents possible Errors encountered while building a `Board` from a `BoardBuilder`.
+pub enum BoardBuildError {
+    Board(BoardError),
+    Fen(FenBuildError),
+}
+
+impl From<BoardError> for BoardBuildError {
+    fn from(err: BoardError) -> BoardBuildError {
+        BoardBuildError::Board(err)
+    }
+}
+
+impl From<FenBuildError> for BoardBuildError {
+    fn from(err: FenBuildError) -> BoardBuildError {
+        BoardBuildError::Fen(err)
+    }
+}
+
+impl fmt::Debug for BoardBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BoardBuildError::Board(ref err) => write!(f, "invalid board, {:?}", err),
+            BoardBuildError::Fen(ref err) => write!(f, "invalid position, {:?}", err),
+        }
+    }
+}
+
+/// Builds a `Board` from an arbitrary position, without needing to write out a FEN string.
+///
+/// A new builder starts from an empty board with White to move. Pieces can be placed and
+/// removed, and the side to move, castling rights, en-passant square and move counters set,
+/// before calling `BoardBuilder::build()`.
+///
+/// Castling rights are resolved as the `KQkq` castling field of a FEN string would be, using
+/// the outermost rook on each side of the king.
+///
+/// # Examples
+///
+/// ```
+/// use pleco::{Player, PieceType, SQ};
+/// use pleco::board::builder::BoardBuilder;
+/// use pleco::board::castle_rights::Castling;
+///
+/// let board = BoardBuilder::new()
+///     .place(SQ::E1, Player::White, PieceType::K)
+///     .place(SQ::H1, Player::White, PieceType::R)
+///     .place(SQ::E8, Player::Black, PieceType::K)
+///     .castling(Castling::WHITE_K)
+///     .full_moves(20)
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(board.get_fen(), "4k3/8/8/8/8/8/8/4K2R w K - 0 20");
+///
+/// // A Board must have exactly one king per player.
+/// assert!(BoardBuilder::new().place(SQ::E1, Player::White, PieceType::K).build().is_err());
+/// ```
+#[derive(Clone)]
+pub struct BoardBuilder {
+    pieces: PieceLocations,
+    turn: Player,
+    castling: Castling,
+    ep_square: SQ,
+    rule_50: i16,
+    full_moves: u16,
+    chess960: bool,
+}
+
+impl Default for BoardBuilder {
+    fn default() -> Self {
+        BoardBuilder::new()
+    }
+}
+
+impl BoardBuilder {
+    /// Creates a builder for an empty board, with White to move, no castling rights, and
+    /// at the first full move.
+    pub fn new() -> BoardBuilder {
+        BoardBuilder {
+            pieces: PieceLocations::blank(),
+            turn: Player::White,
+            castling: Castling::empty(),
+            ep_square: NO_SQ,
+            rule_50: 0,
+            full_moves: 1,
+            chess960: false,
+        }
+    }
+
+    /// Creates a builder starting from the current position of a `Board`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::{Board, Player, SQ};
+    /// use pleco::board::builder::BoardBuilder;
+    ///
+    /// let board = BoardBuilder::from_board(&Board::default())
+    ///     .remove(SQ::D1)
+    ///     .turn(Player::Black)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(board.get_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR b KQkq - 0 1");
+    /// ```
+    pub fn from_board(board: &Board) -> BoardBuilder {
+        BoardBuilder {
+            pieces: board.piece_locations.clone(),
+            turn: board.turn,
+            castling: board.state.castling,
+            ep_square: board.ep_square(),
+            rule_50: board.rule_50(),
+            full_moves: board.moves_played() / 2 + 1,
+            chess960: board.is_chess960(),
+        }
+    }
+
+    /// Places a piece on a square, replacing any piece already there.
+    pub fn place(&mut self, sq: SQ, player: Player, piece: PieceType) -> &mut Self {
+        self.pieces.place(sq, player, piece);
+        self
+    }
+
+    /// Removes the piece on a square, if there is one.
+    pub fn remove(&mut self, sq: SQ) -> &mut Self {
+        self.pieces.remove(sq);
+        self
+    }
+
+    /// Removes every piece from the board.
+    pub fn clear(&mut self) -> &mut Self {
+        self.pieces = PieceLocations::blank();
+        self
+    }
+
+    /// Returns the player and piece on a square, if any.
+    pub fn piece_at(&self, sq: SQ) -> Option<(Player, PieceType)> {
+        self.pieces.player_piece_at(sq)
+    }
+
+    /// Sets the player to move.
+    pub fn turn(&mut self, player: Player) -> &mut Self {
+        self.turn = player;
+        self
+    }
+
+    /// Sets the castling rights of both players.
+    pub fn castling(&mut self, castling: Castling) -> &mut Self {
+        self.castling = castling;
+        self
+    }
+
+    /// Sets the en-passant square, being the square behind a pawn that has just moved two
+    /// squares. `NO_SQ` clears it.
+    pub fn ep_square(&mut self, sq: SQ) -> &mut Self {
+        self.ep_square = sq;
+        self
+    }
+
+    /// Sets the number of half-moves since the last capture or pawn move.
+    pub fn rule_50(&mut self, half_moves: i16) -> &mut Self {
+        self.rule_50 = half_moves;
+        self
+    }
+
+    /// Sets the full move number, starting at 1 and incremented after each move by Black.
+    pub fn full_moves(&mut self, full_moves: u16) -> &mut Self {
+        self.full_moves = full_moves;
+        self
+    }
+
+    /// Sets if the Board is a Chess960 game.
+    pub fn chess960(&mut self, chess960: bool) -> &mut Self {
+        self.chess960 = chess960;
+        self
+    }
+
+    /// Builds the Board, checking that the position is legal.
+    ///
+    /// The position is checked by `Board::is_okay()` and `fen::is_valid_fen()`. Castling rights
+    /// also need the king on its first rank and a rook to castle with.
+    pub fn build(&self) -> Result<Board, BoardBuildError> {
+        let mut half_moves: u16 = self.full_moves.saturating_sub(1) * 2;
+        if self.turn == Player::Black {
+            half_moves += 1;
+        }
+
+        let mut board = Board::from_piece_locations(self.pieces.clone(), self.turn, half_moves);
+        board.is_okay()?;
+        board.set_state(&self.castling.pretty_string(), self.ep_square, self.rule_50)?;
+        board.chess960 |= self.chess960;
+
+        for player in &[Player::White, Player::Black] {
+            for side in &[CastleType::KingSide, CastleType::QueenSide] {
+                if !board.can_castle(*player, *side) {
+                    continue;
+                }
+                let rook_sq: SQ = board.castling_rook_sq[*player as usize][*side as usize];
+                if player.relative_rank_of_sq(board.king_sq(*player)) != Rank::R1
+                    || board.piece_locations.player_piece_at(rook_sq) != Some((*player, PieceType::R)) {
+                    return Err(FenBuildError::UnrecognizedCastling {castling: self.castling.pretty_string()}.into());
+                }
+            }
+        }
+
+        Ok(fen::is_valid_fen(board)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_positions() {
+        for fen in fen::ALL_FENS.iter() {
+            let board = Board::new_from_fen(fen).unwrap();
+            let built = BoardBuilder::from_board(&board).build().unwrap();
+            assert_eq!(built.get_fen(), *fen);
+            assert_eq!(built.zobrist(), board.zobrist());
+        }
+
+        let board = BoardBuilder::new()
+            .place(SQ::E1, Player::White, PieceType::K)
+            .place(SQ::E8, Player::Black, PieceType::K)
+            .place(SQ::D4, Player::White, PieceType::P)
+            .place(SQ::E4, Player::Black, PieceType::P)
+            .place(SQ::A8, Player::Black, PieceType::R)
+            .turn(Player::Black)
+            .ep_square(SQ::D3)
+            .castling(Castling::BLACK_Q)
+            .rule_50(0)
+            .full_moves(31)
+            .build()
+            .unwrap();
+        assert_eq!(board.get_fen(), "r3k3/8/8/8/3Pp3/8/8/4K3 b q d3 0 31");
+        assert_eq!(board.generate_moves().len(), Board::new_from_fen(&board.get_fen()).unwrap().generate_moves().len());
+    }
+
+    #[test]
+    fn build_errors() {
+        let mut builder = BoardBuilder::new();
+        builder.place(SQ::E1, Player::White, PieceType::K);
+        match builder.build() {
+            Err(BoardBuildError::Board(BoardError::IncorrectKingNum {player: Player::Black, num: 0})) => {},
+            Err(e) => panic!("{:?}", e),
+            Ok(_) => panic!(),
+        }
+
+        // Black's king cannot be in check with White to move.
+        builder.place(SQ::E8, Player::Black, PieceType::K).place(SQ::E4, Player::White, PieceType::R);
+        match builder.build() {
+            Err(BoardBuildError::Board(BoardError::OpponentInCheck {player: Player::Black})) => {},
+            Err(e) => panic!("{:?}", e),
+            Ok(_) => panic!(),
+        }
+        builder.turn(Player::Black);
+        assert!(builder.build().is_ok());
+
+        // No rook to castle with.
+        builder.castling(Castling::WHITE_K);
+        match builder.build() {
+            Err(BoardBuildError::Fen(FenBuildError::UnrecognizedCastling {..})) => {},
+            Err(e) => panic!("{:?}", e),
+            Ok(_) => panic!(),
+        }
+        builder.castling(Castling::empty());
+
+        builder.place(SQ::A1, Player::White, PieceType::P);
+        match builder.build() {
+            Err(BoardBuildError::Fen(FenBuildError::PawnOnLastRow)) => {},
+            Err(e) => panic!("{:?}", e),
+            Ok(_) => panic!(),
+        }
+        builder.remove(SQ::A1).ep_square(SQ::C3);
+        match builder.build() {
+            Err(BoardBuildError::Fen(FenBuildError::EPSquareInvalid {..})) => {},
+            Err(e) => panic!("{:?}", e),
+            Ok(_) => panic!(),
+        }
+        builder.ep_square(NO_SQ);
+        assert_eq!(builder.build().unwrap().get_fen(), "4k3/8/8/8/4R3/8/8/4K3 b - - 0 1");
+        assert_eq!(builder.clear().piece_at(SQ::E1), None);
+    }
+}
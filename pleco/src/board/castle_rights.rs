@@ -118,7 +118,16 @@ impl Castling {
     #[inline]
     pub fn player_can_castle(&self, player: Player) -> Castling {
         Castling {
-            bits: self.bits & (Castling::WHITE_ALL.bits << (2 * player as u16))
+            bits: self.bits & (Castling::WHITE_ALL.bits >> (2 * player as u16))
+        }
+    }
+
+    /// Returns the castling rights of both players, without the bits marking if a player
+    /// has castled.
+    #[inline]
+    pub fn rights(&self) -> Castling {
+        Castling {
+            bits: self.bits & (Castling::WHITE_ALL.bits | Castling::BLACK_ALL.bits)
         }
     }
 
@@ -143,12 +152,40 @@ impl Castling {
 
     #[inline]
     pub fn update_castling(&mut self, to: SQ, from: SQ) -> u8 {
-        let mask_change: u8 = to.castle_rights_mask() | from.castle_rights_mask();
+        self.update_castling_mask(to.castle_rights_mask() | from.castle_rights_mask())
+    }
+
+    /// Removes any castling rights contained in `mask_change`, returning the bits of the
+    /// rights that were removed.
+    ///
+    /// Used when the squares affecting castling rights are not the standard ones, as in
+    /// Chess960.
+    #[inline]
+    pub fn update_castling_mask(&mut self, mask_change: u8) -> u8 {
         let to_return: u8 = self.bits & mask_change;
         self.bits &= !mask_change;
         to_return
     }
 
+    /// Returns the single castling right for a given player and side.
+    #[inline]
+    pub fn from_player_side(player: Player, side: CastleType) -> Castling {
+        match player {
+            Player::White => {
+                match side {
+                    CastleType::KingSide => Castling::WHITE_K,
+                    CastleType::QueenSide => Castling::WHITE_Q,
+                }
+            }
+            Player::Black => {
+                match side {
+                    CastleType::KingSide => Castling::BLACK_K,
+                    CastleType::QueenSide => Castling::BLACK_Q,
+                }
+            }
+        }
+    }
+
     /// Adds the Right to castle based on an `char`.
     ///
     /// `K` -> Add White King-side Castling bit.
@@ -217,4 +254,26 @@ mod tests {
         let c_const = Castling::all_castling();
         assert_eq!(c, c_const);
     }
+
+    #[test]
+    pub fn player_side_rights() {
+        let mut c = Castling::empty();
+        c |= Castling::from_player_side(Player::White, CastleType::KingSide);
+        c |= Castling::from_player_side(Player::Black, CastleType::QueenSide);
+        assert!(c.castle_rights(Player::White, CastleType::KingSide));
+        assert!(c.castle_rights(Player::Black, CastleType::QueenSide));
+        assert!(!c.castle_rights(Player::White, CastleType::QueenSide));
+        assert_eq!(c.update_castling_mask(C_WHITE_K_MASK | C_WHITE_Q_MASK), C_WHITE_K_MASK);
+        assert_eq!(c, Castling::BLACK_Q);
+    }
+
+    #[test]
+    pub fn player_can_castle() {
+        let mut c = Castling::all_castling();
+        c.set_castling(Player::Black);
+        assert_eq!(c.player_can_castle(Player::White), Castling::WHITE_ALL);
+        assert_eq!(c.player_can_castle(Player::Black), Castling::BLACK_ALL);
+        c.remove_player_castling(Player::Black);
+        assert!(c.player_can_castle(Player::Black).is_empty());
+    }
 }
\ No newline at end of file
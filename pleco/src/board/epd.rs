@@ -0,0 +1,511 @@
+//! Module for reading and writing positions in Extended Position Description (EPD).
+//!
+//! An EPD line holds the first four fields of a FEN string, followed by any number of
+//! operations, each an opcode and its operands ending in a semicolon. Test suites such as
+//! WAC and STS use EPD to give the best moves of each position, while perft suites use it to
+//! give the expected node counts at each depth.
+//!
+//! Moves given as operands, such as those of `bm` and `pv`, are written in Standard Algebraic
+//! Notation, and are resolved against the position when parsed.
+//!
+//! # Examples
+//!
+//! ```
+//! use pleco::board::epd::{Epd, EpdOperation};
+//!
+//! let epd = Epd::parse("2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";").unwrap();
+//! assert_eq!(epd.id(), Some("WAC.001"));
+//! assert_eq!(epd.best_moves()[0].stringify(), "g3g6");
+//! assert_eq!(epd.to_string(), "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";");
+//! ```
+
+use std::fmt;
+
+use core::piece_move::BitMove;
+
+use super::{Board, FenBuildError};
+use super::san::SanError;
+
+/// Represents possible Errors encountered while parsing an EPD line.
+pub enum EpdError {
+    NotEnoughFields {fields: usize},
+    Fen(FenBuildError),
+    UnterminatedString,
+    MissingOperand {opcode: String},
+    Operand {opcode: String, operand: String},
+    San {opcode: String, error: SanError},
+    IllegalMove {opcode: String, bit_move: BitMove},
+}
+
+impl From<FenBuildError> for EpdError {
+    fn from(err: FenBuildError) -> EpdError {
+        EpdError::Fen(err)
+    }
+}
+
+impl fmt::Debug for EpdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EpdError::NotEnoughFields{fields} => writeln!(f, "invalid number of position fields: {}, expected 4", fields),
+            EpdError::Fen(ref err) => write!(f, "invalid position, {:?}", err),
+            EpdError::UnterminatedString => writeln!(f, "unterminated string operand"),
+            EpdError::MissingOperand{ref opcode} => writeln!(f, "missing operand for opcode: {}", opcode),
+            EpdError::Operand{ref opcode, ref operand} => writeln!(f, "invalid operand for opcode {}: {}", opcode, operand),
+            EpdError::San{ref opcode, ref error} => write!(f, "invalid move for opcode {}, {:?}", opcode, error),
+            EpdError::IllegalMove{ref opcode, bit_move} => writeln!(f, "illegal move for opcode {}: {}", opcode, bit_move),
+        }
+    }
+}
+
+/// A single operation of an EPD line.
+#[derive(Clone, PartialEq, Debug)]
+pub enum EpdOperation {
+    /// `bm`, the best moves of the position.
+    BestMoves(Vec<BitMove>),
+    /// `am`, the moves to avoid in the position.
+    AvoidMoves(Vec<BitMove>),
+    /// `id`, the name of the position.
+    Id(String),
+    /// `c0` through `c9`, comments on the position.
+    Comment(u8, String),
+    /// `acd`, the depth the position was analyzed to.
+    AnalysisDepth(u32),
+    /// `ce`, the evaluation of the position in centipawns, from the side to move.
+    CentipawnEval(i32),
+    /// `pv`, the principal variation, starting from the position.
+    PrincipalVariation(Vec<BitMove>),
+    /// `D1` through `Dn`, the perft node count at the given depth.
+    Perft(u8, u64),
+    /// Any other operation, with its opcode and unquoted operands.
+    Other(String, Vec<String>),
+}
+
+/// A position and its operations, as read from or written to an EPD line.
+///
+/// The half-move clock and full-move number are read from the `hmvc` and `fmvn` operations,
+/// if given, and are written back out if they differ from the defaults of 0 and 1.
+pub struct Epd {
+    board: Board,
+    operations: Vec<EpdOperation>,
+}
+
+impl fmt::Display for Epd {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fen: String = self.board.get_fen();
+        let fields: Vec<&str> = fen.split_whitespace().take(4).collect();
+        write!(f, "{}", fields.join(" "))?;
+
+        for op in self.operations.iter() {
+            match *op {
+                EpdOperation::BestMoves(ref moves) => write_moves(f, "bm", &self.board, moves, false)?,
+                EpdOperation::AvoidMoves(ref moves) => write_moves(f, "am", &self.board, moves, false)?,
+                EpdOperation::Id(ref id) => write!(f, " id {};", quote(id))?,
+                EpdOperation::Comment(n, ref comment) => write!(f, " c{} {};", n, quote(comment))?,
+                EpdOperation::AnalysisDepth(depth) => write!(f, " acd {};", depth)?,
+                EpdOperation::CentipawnEval(eval) => write!(f, " ce {};", eval)?,
+                EpdOperation::PrincipalVariation(ref moves) => write_moves(f, "pv", &self.board, moves, true)?,
+                EpdOperation::Perft(depth, nodes) => write!(f, " D{} {};", depth, nodes)?,
+                EpdOperation::Other(ref opcode, ref operands) => {
+                    write!(f, " {}", opcode)?;
+                    for operand in operands.iter() {
+                        if operand.is_empty() || operand.contains(|c: char| c.is_whitespace() || c == ';' || c == '"') {
+                            write!(f, " {}", quote(operand))?;
+                        } else {
+                            write!(f, " {}", operand)?;
+                        }
+                    }
+                    write!(f, ";")?;
+                }
+            }
+        }
+
+        if self.board.rule_50() != 0 {
+            write!(f, " hmvc {};", self.board.rule_50())?;
+        }
+        if self.board.moves_played() >= 2 {
+            write!(f, " fmvn {};", self.board.moves_played() / 2 + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Epd {
+    /// Creates an `Epd` of a position, with no operations.
+    pub fn new(board: Board) -> Epd {
+        Epd {
+            board,
+            operations: Vec::new(),
+        }
+    }
+
+    /// Parses a single line of EPD.
+    ///
+    /// Operands may be quoted strings, which can contain spaces and semicolons. The final
+    /// semicolon of a line may be left out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::board::epd::{Epd, EpdOperation};
+    ///
+    /// let epd = Epd::parse("4k3/8/8/8/8/8/8/4K2R w K - D1 15; D2 66; c0 \"castling; rook\";").unwrap();
+    /// assert_eq!(epd.perft(2), Some(66));
+    /// assert_eq!(epd.comment(0), Some("castling; rook"));
+    /// assert_eq!(epd.board().get_fen(), "4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+    /// ```
+    pub fn parse(line: &str) -> Result<Epd, EpdError> {
+        let mut rest: &str = line.trim();
+        let mut fields: Vec<&str> = Vec::with_capacity(4);
+        while fields.len() < 4 && !rest.is_empty() {
+            let end: usize = rest.find(char::is_whitespace).unwrap_or_else(|| rest.len());
+            fields.push(&rest[..end]);
+            rest = rest[end..].trim_left();
+        }
+        if fields.len() != 4 {
+            return Err(EpdError::NotEnoughFields {fields: fields.len()});
+        }
+
+        let raw_ops: Vec<(String, Vec<String>)> = split_operations(rest)?;
+
+        let mut hmvc: String = "0".to_string();
+        let mut fmvn: String = "1".to_string();
+        for &(ref opcode, ref operands) in raw_ops.iter() {
+            if opcode == "hmvc" || opcode == "fmvn" {
+                let operand: &str = single_operand(opcode, operands)?;
+                let counter: u16 = parse_operand(opcode, operand)?;
+                if opcode == "hmvc" && counter <= i16::max_value() as u16 {
+                    hmvc = operand.to_string();
+                } else if opcode == "fmvn" && counter >= 1 {
+                    fmvn = operand.to_string();
+                } else {
+                    return Err(EpdError::Operand {opcode: opcode.clone(), operand: operand.to_string()});
+                }
+            }
+        }
+
+        let fen: String = format!("{} {} {}", fields.join(" "), hmvc, fmvn);
+        let mut epd = Epd::new(Board::new_from_fen(&fen)?);
+
+        for (opcode, operands) in raw_ops.into_iter() {
+            if let Some(op) = epd.parse_operation(&opcode, operands)? {
+                epd.operations.push(op);
+            }
+        }
+        Ok(epd)
+    }
+
+    /// Adds an operation, after checking that any moves it holds are legal for the position.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::Board;
+    /// use pleco::board::epd::{Epd, EpdOperation};
+    ///
+    /// let board = Board::default();
+    /// let e4 = board.parse_san("e4").unwrap();
+    /// let epd = Epd::new(board)
+    ///     .with_operation(EpdOperation::BestMoves(vec![e4])).unwrap()
+    ///     .with_operation(EpdOperation::Id("start".to_string())).unwrap();
+    /// assert_eq!(epd.to_string(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - bm e4; id \"start\";");
+    /// ```
+    pub fn with_operation(mut self, op: EpdOperation) -> Result<Epd, EpdError> {
+        match op {
+            EpdOperation::BestMoves(ref moves) => check_moves(&self.board, "bm", moves, false)?,
+            EpdOperation::AvoidMoves(ref moves) => check_moves(&self.board, "am", moves, false)?,
+            EpdOperation::PrincipalVariation(ref moves) => check_moves(&self.board, "pv", moves, true)?,
+            _ => {}
+        }
+        self.operations.push(op);
+        Ok(self)
+    }
+
+    /// Returns the position.
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Returns all operations, in the order they were given.
+    pub fn operations(&self) -> &[EpdOperation] {
+        &self.operations
+    }
+
+    /// Returns the best moves of the position, from the `bm` opcode.
+    pub fn best_moves(&self) -> &[BitMove] {
+        self.operations.iter()
+            .filter_map(|op| match *op { EpdOperation::BestMoves(ref m) => Some(m.as_slice()), _ => None })
+            .next()
+            .unwrap_or(&[])
+    }
+
+    /// Returns the moves to avoid in the position, from the `am` opcode.
+    pub fn avoid_moves(&self) -> &[BitMove] {
+        self.operations.iter()
+            .filter_map(|op| match *op { EpdOperation::AvoidMoves(ref m) => Some(m.as_slice()), _ => None })
+            .next()
+            .unwrap_or(&[])
+    }
+
+    /// Returns the principal variation, from the `pv` opcode.
+    pub fn pv(&self) -> &[BitMove] {
+        self.operations.iter()
+            .filter_map(|op| match *op { EpdOperation::PrincipalVariation(ref m) => Some(m.as_slice()), _ => None })
+            .next()
+            .unwrap_or(&[])
+    }
+
+    /// Returns the name of the position, from the `id` opcode.
+    pub fn id(&self) -> Option<&str> {
+        self.operations.iter()
+            .filter_map(|op| match *op { EpdOperation::Id(ref id) => Some(id.as_str()), _ => None })
+            .next()
+    }
+
+    /// Returns a comment on the position, from the `c0` through `c9` opcodes.
+    pub fn comment(&self, n: u8) -> Option<&str> {
+        self.operations.iter()
+            .filter_map(|op| match *op { EpdOperation::Comment(i, ref c) if i == n => Some(c.as_str()), _ => None })
+            .next()
+    }
+
+    /// Returns the depth the position was analyzed to, from the `acd` opcode.
+    pub fn analysis_depth(&self) -> Option<u32> {
+        self.operations.iter()
+            .filter_map(|op| match *op { EpdOperation::AnalysisDepth(d) => Some(d), _ => None })
+            .next()
+    }
+
+    /// Returns the evaluation of the position in centipawns, from the `ce` opcode.
+    pub fn centipawn_eval(&self) -> Option<i32> {
+        self.operations.iter()
+            .filter_map(|op| match *op { EpdOperation::CentipawnEval(e) => Some(e), _ => None })
+            .next()
+    }
+
+    /// Returns the perft node count at a depth, from the `D1` through `Dn` opcodes.
+    pub fn perft(&self, depth: u8) -> Option<u64> {
+        self.operations.iter()
+            .filter_map(|op| match *op { EpdOperation::Perft(d, n) if d == depth => Some(n), _ => None })
+            .next()
+    }
+
+    /// Returns the operands of any other opcode.
+    pub fn operation(&self, opcode: &str) -> Option<&[String]> {
+        self.operations.iter()
+            .filter_map(|op| match *op {
+                EpdOperation::Other(ref o, ref operands) if o == opcode => Some(operands.as_slice()),
+                _ => None
+            })
+            .next()
+    }
+
+    /// Parses a single operation, other than the move counters.
+    fn parse_operation(&self, opcode: &str, operands: Vec<String>) -> Result<Option<EpdOperation>, EpdError> {
+        let op: EpdOperation = match opcode {
+            "hmvc" | "fmvn" => return Ok(None),
+            "bm" => EpdOperation::BestMoves(self.parse_moves(opcode, &operands, false)?),
+            "am" => EpdOperation::AvoidMoves(self.parse_moves(opcode, &operands, false)?),
+            "pv" => EpdOperation::PrincipalVariation(self.parse_moves(opcode, &operands, true)?),
+            "id" => EpdOperation::Id(join_operands(opcode, &operands)?),
+            "acd" => EpdOperation::AnalysisDepth(parse_operand(opcode, single_operand(opcode, &operands)?)?),
+            "ce" => EpdOperation::CentipawnEval(parse_operand(opcode, single_operand(opcode, &operands)?)?),
+            _ => {
+                // Comments are c0 through c9, and perft counts are D1 through Dn
+                let mut chars = opcode.chars();
+                let prefix: Option<char> = chars.next();
+                let index: Option<u8> = chars.as_str().parse::<u8>().ok();
+                match (prefix, index) {
+                    (Some('c'), Some(n)) if n <= 9 => EpdOperation::Comment(n, join_operands(opcode, &operands)?),
+                    (Some('D'), Some(n)) if n >= 1 => {
+                        EpdOperation::Perft(n, parse_operand(opcode, single_operand(opcode, &operands)?)?)
+                    },
+                    _ => EpdOperation::Other(opcode.to_string(), operands),
+                }
+            }
+        };
+        Ok(Some(op))
+    }
+
+    /// Parses the SAN operands of an opcode. A sequence of moves is played out one after
+    /// another, while otherwise each move is read from the position.
+    fn parse_moves(&self, opcode: &str, operands: &[String], sequence: bool) -> Result<Vec<BitMove>, EpdError> {
+        if operands.is_empty() {
+            return Err(EpdError::MissingOperand {opcode: opcode.to_string()});
+        }
+        let mut board: Board = self.board.shallow_clone();
+        let mut moves: Vec<BitMove> = Vec::with_capacity(operands.len());
+        for san in operands.iter() {
+            let mov: BitMove = board.parse_san(san)
+                .map_err(|error| EpdError::San {opcode: opcode.to_string(), error})?;
+            if sequence {
+                board.apply_move(mov);
+            }
+            moves.push(mov);
+        }
+        Ok(moves)
+    }
+}
+
+/// Splits the operations of an EPD line into their opcodes and unquoted operands.
+fn split_operations(ops: &str) -> Result<Vec<(String, Vec<String>)>, EpdError> {
+    let mut operations: Vec<(String, Vec<String>)> = Vec::new();
+    let mut tokens: Vec<String> = Vec::new();
+    let mut chars = ops.chars();
+
+    loop {
+        let c: Option<char> = chars.next();
+        match c {
+            Some('"') => {
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => s.push(chars.next().ok_or(EpdError::UnterminatedString)?),
+                        Some(c) => s.push(c),
+                        None => return Err(EpdError::UnterminatedString),
+                    }
+                }
+                tokens.push(s);
+            }
+            Some(c) if c.is_whitespace() => {}
+            Some(c) if c != ';' => {
+                let mut s = c.to_string();
+                let mut next: Option<char> = None;
+                while let Some(c) = chars.next() {
+                    if c.is_whitespace() || c == ';' {
+                        next = Some(c);
+                        break;
+                    }
+                    s.push(c);
+                }
+                tokens.push(s);
+                if next != Some(';') {
+                    continue;
+                }
+                let opcode: String = tokens.remove(0);
+                operations.push((opcode, tokens.split_off(0)));
+            }
+            _ => {
+                // The end of an operation
+                if !tokens.is_empty() {
+                    let opcode: String = tokens.remove(0);
+                    operations.push((opcode, tokens.split_off(0)));
+                }
+                if c.is_none() {
+                    return Ok(operations);
+                }
+            }
+        }
+    }
+}
+
+fn single_operand<'a>(opcode: &str, operands: &'a [String]) -> Result<&'a str, EpdError> {
+    match operands.len() {
+        0 => Err(EpdError::MissingOperand {opcode: opcode.to_string()}),
+        1 => Ok(&operands[0]),
+        _ => Err(EpdError::Operand {opcode: opcode.to_string(), operand: operands.join(" ")}),
+    }
+}
+
+fn join_operands(opcode: &str, operands: &[String]) -> Result<String, EpdError> {
+    if operands.is_empty() {
+        return Err(EpdError::MissingOperand {opcode: opcode.to_string()});
+    }
+    Ok(operands.join(" "))
+}
+
+fn parse_operand<T: ::std::str::FromStr>(opcode: &str, operand: &str) -> Result<T, EpdError> {
+    operand.parse::<T>()
+        .map_err(|_| EpdError::Operand {opcode: opcode.to_string(), operand: operand.to_string()})
+}
+
+fn check_moves(board: &Board, opcode: &str, moves: &[BitMove], sequence: bool) -> Result<(), EpdError> {
+    let mut board: Board = board.shallow_clone();
+    for mov in moves.iter() {
+        if !board.generate_moves().contains(mov) {
+            return Err(EpdError::IllegalMove {opcode: opcode.to_string(), bit_move: *mov});
+        }
+        if sequence {
+            board.apply_move(*mov);
+        }
+    }
+    Ok(())
+}
+
+fn write_moves(f: &mut fmt::Formatter, opcode: &str, board: &Board, moves: &[BitMove], sequence: bool) -> fmt::Result {
+    write!(f, " {}", opcode)?;
+    let mut board: Board = board.shallow_clone();
+    for mov in moves.iter() {
+        write!(f, " {}", board.move_to_san(*mov))?;
+        if sequence {
+            board.apply_move(*mov);
+        }
+    }
+    write!(f, ";")
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_operations() {
+        let epd = Epd::parse("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 bm exd6 O-O; am b8=Q; \
+                              pv b8=Q+ Kd7 Qxa8; acd 12; ce -35; c3 \"a \\\"quoted\\\" comment\"; \
+                              id WAC 2; D1 42; custom 1 \"two words\"; hmvc 4; fmvn 30").unwrap();
+        let uci = |moves: &[BitMove]| moves.iter().map(|m| m.stringify()).collect::<Vec<_>>();
+        assert_eq!(uci(epd.best_moves()), vec!["e5d6", "e1g1"]);
+        assert_eq!(uci(epd.avoid_moves()), vec!["b7b8q"]);
+        assert_eq!(uci(epd.pv()), vec!["b7b8q", "e8d7", "b8a8"]);
+        assert_eq!(epd.analysis_depth(), Some(12));
+        assert_eq!(epd.centipawn_eval(), Some(-35));
+        assert_eq!(epd.comment(3), Some("a \"quoted\" comment"));
+        assert_eq!(epd.comment(0), None);
+        assert_eq!(epd.id(), Some("WAC 2"));
+        assert_eq!(epd.perft(1), Some(42));
+        assert_eq!(epd.perft(2), None);
+        assert_eq!(epd.operation("custom"), Some(&["1".to_string(), "two words".to_string()][..]));
+        assert_eq!(epd.board().get_fen(), "r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 4 30");
+
+        assert_eq!(epd.to_string(),
+                   "r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 bm exd6 O-O; am b8=Q+; \
+                    pv b8=Q+ Kd7 Qxa8; acd 12; ce -35; c3 \"a \\\"quoted\\\" comment\"; \
+                    id \"WAC 2\"; D1 42; custom 1 \"two words\"; hmvc 4; fmvn 30;");
+        let again = Epd::parse(&epd.to_string()).unwrap();
+        assert_eq!(again.operations(), epd.operations());
+        assert_eq!(again.to_string(), epd.to_string());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
+        assert!(Epd::parse(start).unwrap().operations().is_empty());
+        match Epd::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq") {
+            Err(EpdError::NotEnoughFields {fields: 3}) => {},
+            _ => panic!(),
+        }
+        let error = |ops: &str| Epd::parse(&format!("{} {}", start, ops)).err().unwrap();
+        match error("bm e5;") { EpdError::San {..} => {}, e => panic!("{:?}", e) }
+        match error("pv e4 e4;") { EpdError::San {..} => {}, e => panic!("{:?}", e) }
+        match error("bm;") { EpdError::MissingOperand {..} => {}, e => panic!("{:?}", e) }
+        match error("id \"open;") { EpdError::UnterminatedString => {}, e => panic!("{:?}", e) }
+        match error("acd deep;") { EpdError::Operand {..} => {}, e => panic!("{:?}", e) }
+        match error("D1 20 21;") { EpdError::Operand {..} => {}, e => panic!("{:?}", e) }
+        match error("fmvn 0;") { EpdError::Operand {..} => {}, e => panic!("{:?}", e) }
+        match Epd::parse("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - bm e4;") {
+            Err(EpdError::Fen(_)) => {},
+            _ => panic!(),
+        }
+
+        let pawn_move = Board::default().parse_san("e4").unwrap();
+        let epd = Epd::new(Board::default());
+        match epd.with_operation(EpdOperation::PrincipalVariation(vec![pawn_move, pawn_move])) {
+            Err(EpdError::IllegalMove {..}) => {},
+            _ => panic!(),
+        }
+    }
+}
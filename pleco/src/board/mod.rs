@@ -16,7 +16,7 @@ use std::cmp::{PartialEq,max,min};
 
 use rand;
 
-use core::piece_move::{BitMove, MoveType};
+use core::piece_move::{BitMove, MoveType, PreMoveInfo, MoveFlag};
 use core::move_list::MoveList;
 use core::mono_traits::*;
 use core::masks::*;
@@ -30,6 +30,7 @@ use helper::prelude::*;
 use tools::prng::PRNG;
 use tools::Searcher;
 use bot_prelude::{IterativeSearcher,JamboreeSearcher};
+use book;
 
 use self::castle_rights::Castling;
 use self::piece_locations::PieceLocations;
@@ -42,13 +43,17 @@ pub mod piece_locations;
 pub mod board_state;
 pub mod fen;
 pub mod perft;
-mod pgn;
+pub mod san;
+pub mod pgn;
+pub mod builder;
+pub mod epd;
 
 /// Represents possible Errors encountered while building a `Board` from a fen string.
 pub enum FenBuildError {
     NotEnoughSections {sections: usize},
     IncorrectRankAmounts {ranks: usize},
     UnrecognizedTurn {turn: String},
+    UnrecognizedCastling {castling: String},
     EPSquareUnreadable {ep: String},
     EPSquareInvalid {ep: String},
     SquareSmallerRank {rank: usize, square: String},
@@ -73,6 +78,7 @@ impl fmt::Debug for FenBuildError {
             FenBuildError::NotEnoughSections{sections} => writeln!(f, "invalid number of fen sections: {}, expected 6", sections),
             FenBuildError::IncorrectRankAmounts{ranks} => writeln!(f, "invalid number of ranks: {}, expected 8", ranks),
             FenBuildError::UnrecognizedTurn {ref turn} => writeln!(f, "invalid turn: {}, expected 'w' or 'b'", turn),
+            FenBuildError::UnrecognizedCastling {ref castling} => writeln!(f, "invalid castling rights: {}", castling),
             FenBuildError::EPSquareUnreadable{ref ep} => writeln!(f, "unreadable En-passant square: {}", ep),
             FenBuildError::EPSquareInvalid{ref ep} => writeln!(f, "invalid En-passant square: {}", ep),
             FenBuildError::SquareSmallerRank{rank, ref square} => writeln!(f, "square number too small for rank, rank: {} square: {},", rank, square),
@@ -87,6 +93,111 @@ impl fmt::Debug for FenBuildError {
     }
 }
 
+/// Represents the reasons a move cannot be applied to a `Board`.
+#[derive(Copy, Clone, PartialEq)]
+pub enum IllegalMove {
+    NoPiece {sq: SQ},
+    WrongSide {sq: SQ},
+    InvalidMove {bit_move: BitMove},
+    LeavesKingInCheck {bit_move: BitMove},
+}
+
+impl fmt::Debug for IllegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IllegalMove::NoPiece{sq} => writeln!(f, "no piece on the source square: {}", sq),
+            IllegalMove::WrongSide{sq} => writeln!(f, "piece on {} belongs to the side not to move", sq),
+            IllegalMove::InvalidMove{bit_move} => writeln!(f, "the piece cannot make the move: {}", bit_move),
+            IllegalMove::LeavesKingInCheck{bit_move} => writeln!(f, "move leaves the king in check: {}", bit_move),
+        }
+    }
+}
+
+/// Represents possible Errors encountered while parsing a move in UCI notation.
+#[derive(Clone, PartialEq)]
+pub enum MoveParseError {
+    Malformed {uci: String},
+    Illegal(IllegalMove),
+}
+
+impl From<IllegalMove> for MoveParseError {
+    fn from(err: IllegalMove) -> MoveParseError {
+        MoveParseError::Illegal(err)
+    }
+}
+
+impl fmt::Debug for MoveParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MoveParseError::Malformed{ref uci} => writeln!(f, "malformed UCI move: {}", uci),
+            MoveParseError::Illegal(ref err) => write!(f, "illegal move, {:?}", err),
+        }
+    }
+}
+
+/// Represents the reasons the last move of a `Board` cannot be undone.
+#[derive(Copy, Clone, PartialEq)]
+pub enum UndoMoveError {
+    NoPreviousMove,
+    NullMove,
+}
+
+impl fmt::Debug for UndoMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UndoMoveError::NoPreviousMove => writeln!(f, "no move has been applied since the board was created or cloned"),
+            UndoMoveError::NullMove => writeln!(f, "the last move is a null move"),
+        }
+    }
+}
+
+/// The reason a game of chess has ended.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Termination {
+    /// The side to move is in check and has no legal moves.
+    Checkmate,
+    /// The side to move is not in check and has no legal moves.
+    Stalemate,
+    /// Neither player has enough material left to deliver checkmate.
+    InsufficientMaterial,
+    /// The same position has occurred three times.
+    ThreefoldRepetition,
+    /// One hundred half-moves have been played without a capture or pawn move.
+    FiftyMoveRule,
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match *self {
+            Termination::Checkmate => "checkmate",
+            Termination::Stalemate => "stalemate",
+            Termination::InsufficientMaterial => "insufficient material",
+            Termination::ThreefoldRepetition => "threefold repetition",
+            Termination::FiftyMoveRule => "fifty-move rule",
+        };
+        f.pad(s)
+    }
+}
+
+/// The outcome of a finished game, as returned by [`Board::outcome`].
+///
+/// [`Board::outcome`]: struct.Board.html#method.outcome
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct GameOutcome {
+    /// The winning player, or `None` if the game is a draw.
+    pub winner: Option<Player>,
+    /// The reason the game ended.
+    pub termination: Termination,
+}
+
+impl GameOutcome {
+    /// Returns if the game ended in a draw.
+    #[inline]
+    pub fn is_draw(&self) -> bool {
+        self.winner.is_none()
+    }
+}
+
 
 /// Represents a Chessboard through a `Board`.
 ///
@@ -148,6 +259,13 @@ pub struct Board {
     // or recomputing BoardStates.
     state: Arc<BoardState>,
 
+    // Castling layout of the game, fixed once the Board is created. Only differs from the
+    // standard squares for Chess960 positions.
+    castling_rook_sq: [[SQ; CASTLING_SIDES]; PLAYER_CNT], // Starting rook square per player per side
+    castling_path: [[BitBoard; CASTLING_SIDES]; PLAYER_CNT], // Squares that must be empty to castle
+    castling_mask: [u8; SQ_CNT], // Castling rights lost when a piece moves to / from a square
+    chess960: bool, // Is this a game of Chess960
+
     /// Reference to the pre-computed lookup tables.
     #[doc(hidden)]
     pub magic_helper: Helper
@@ -203,10 +321,21 @@ impl Board {
             piece_counts: [[8, 2, 2, 2, 1, 1], [8, 2, 2, 2, 1, 1]],
             piece_locations: PieceLocations::default(),
             state: Arc::new(BoardState::default()),
+            castling_rook_sq: [[NO_SQ; CASTLING_SIDES]; PLAYER_CNT],
+            castling_path: [[BitBoard(0); CASTLING_SIDES]; PLAYER_CNT],
+            castling_mask: [0; SQ_CNT],
+            chess960: false,
             magic_helper: Helper::new(),
         };
         // Create the Zobrist hash & set the Piece Locations structure
         b.set_piece_states();
+        for player in &ALL_PLAYERS {
+            let k_sq: SQ = b.king_sq(*player);
+            for side in &[CastleType::KingSide, CastleType::QueenSide] {
+                let r_sq = SQ(CASTLING_ROOK_START[*player as usize][*side as usize]);
+                b.set_castling_right(*player, *side, k_sq, r_sq);
+            }
+        }
         b.set_zob_hash();
         b.set_material_key();
         b
@@ -251,6 +380,10 @@ impl Board {
             piece_counts: self.piece_counts,
             piece_locations: self.piece_locations.clone(),
             state: Arc::clone(&self.state),
+            castling_rook_sq: self.castling_rook_sq,
+            castling_path: self.castling_path,
+            castling_mask: self.castling_mask,
+            chess960: self.chess960,
             magic_helper: self.magic_helper,
         }
     }
@@ -293,6 +426,10 @@ impl Board {
             piece_counts: self.piece_counts,
             piece_locations: self.piece_locations.clone(),
             state: Arc::clone(&self.state),
+            castling_rook_sq: self.castling_rook_sq,
+            castling_path: self.castling_path,
+            castling_mask: self.castling_mask,
+            chess960: self.chess960,
             magic_helper: self.magic_helper,
         }
     }
@@ -423,12 +560,212 @@ impl Board {
         }
     }
 
+    /// Helper method for registering a castling right, given the square of the rook it concerns.
+    ///
+    /// Sets the rook square, castling path, and the castling masks of both the king's and rook's
+    /// starting squares.
+    ///
+    /// # Safety
+    ///
+    /// Assumes that `k_from` and `r_from` are on the player's first rank.
+    fn set_castling_right(&mut self, player: Player, side: CastleType, k_from: SQ, r_from: SQ) {
+        let (k_to, r_to) = match side {
+            CastleType::KingSide => (player.relative_square(SQ::G1), player.relative_square(SQ::F1)),
+            CastleType::QueenSide => (player.relative_square(SQ::C1), player.relative_square(SQ::D1)),
+        };
+
+        let right: u8 = Castling::from_player_side(player, side).bits();
+        self.castling_mask[k_from.0 as usize] |= right;
+        self.castling_mask[r_from.0 as usize] |= right;
+        self.castling_rook_sq[player as usize][side as usize] = r_from;
+        self.castling_path[player as usize][side as usize] =
+            (between_bb(r_from, r_to) | between_bb(k_from, k_to) | r_to.to_bb() | k_to.to_bb())
+                & !(k_from.to_bb() | r_from.to_bb());
+    }
+
+    /// Helper method for reading the castling field of a FEN string.
+    ///
+    /// Accepts both X-FEN (`KQkq`, with a file letter for any rook that isn't the outermost
+    /// one) and Shredder-FEN (the files of the castling rooks, such as `HAha`). If the castling
+    /// rights imply a non-standard placement of the king or rooks, the Board is marked as Chess960.
+    ///
+    /// # Safety
+    ///
+    /// Assumes that the Board has its BitBoards set.
+    fn set_castling_from_fen(&mut self, castling: &str) -> Result<Castling, FenBuildError> {
+        let mut rights = Castling::empty();
+        for c in castling.chars() {
+            if c == '-' {
+                continue;
+            }
+            let player: Player = if c.is_uppercase() { Player::White } else { Player::Black };
+            let back_rank: Rank = player.relative_rank(Rank::R1);
+
+            // A king off of its first rank has no real castling rights, but these are still
+            // accepted for `K` and `Q` by falling back to the standard squares.
+            let k_sq: Option<SQ> = if self.count_piece(player, PieceType::K) == 1
+                && self.king_sq(player).rank() == back_rank {
+                Some(self.king_sq(player))
+            } else {
+                None
+            };
+            let rooks: BitBoard = self.piece_bb(player, PieceType::R)
+                & BitBoard(RANK_BB[back_rank as usize]);
+
+            let (side, k_from, r_from) = match (c.to_ascii_uppercase(), k_sq) {
+                ('K', Some(k)) => {
+                    let east: BitBoard = rooks & !(k.to_bb() | (k.to_bb() - 1));
+                    let r = if east.is_empty() {
+                        player.relative_square(SQ::H1)
+                    } else {
+                        east.msb().to_sq()
+                    };
+                    (CastleType::KingSide, k, r)
+                }
+                ('Q', Some(k)) => {
+                    let west: BitBoard = rooks & (k.to_bb() - 1);
+                    let r = if west.is_empty() {
+                        player.relative_square(SQ::A1)
+                    } else {
+                        west.lsb().to_sq()
+                    };
+                    (CastleType::QueenSide, k, r)
+                }
+                ('K', None) => (CastleType::KingSide, player.relative_square(SQ::E1),
+                                player.relative_square(SQ::H1)),
+                ('Q', None) => (CastleType::QueenSide, player.relative_square(SQ::E1),
+                                player.relative_square(SQ::A1)),
+                (f @ 'A'...'H', Some(k)) => {
+                    let r = SQ::make(ALL_FILES[(f as u8 - b'A') as usize], back_rank);
+                    if (rooks & r.to_bb()).is_empty() {
+                        return Err(FenBuildError::UnrecognizedCastling {castling: castling.to_string()});
+                    }
+                    self.chess960 = true;
+                    if r > k {
+                        (CastleType::KingSide, k, r)
+                    } else {
+                        (CastleType::QueenSide, k, r)
+                    }
+                }
+                _ => {
+                    return Err(FenBuildError::UnrecognizedCastling {castling: castling.to_string()});
+                }
+            };
+
+            if k_from.file() != File::E || (r_from.file() != File::A && r_from.file() != File::H) {
+                self.chess960 = true;
+            }
+            rights |= Castling::from_player_side(player, side);
+            self.set_castling_right(player, side, k_from, r_from);
+        }
+        Ok(rights)
+    }
+
+    /// Helper method for creating the castling field of a FEN string for a Chess960 game, in
+    /// the X-FEN format.
+    fn chess960_castling_string(&self) -> String {
+        let mut s = String::default();
+        for player in &ALL_PLAYERS {
+            for side in &[CastleType::KingSide, CastleType::QueenSide] {
+                if !self.can_castle(*player, *side) {
+                    continue;
+                }
+                let r_sq: SQ = self.castling_rook_sq[*player as usize][*side as usize];
+                let beyond: BitBoard = match *side {
+                    CastleType::KingSide => !(r_sq.to_bb() | (r_sq.to_bb() - 1)),
+                    CastleType::QueenSide => r_sq.to_bb() - 1,
+                };
+                let c: char = if (self.piece_bb(*player, PieceType::R) & r_sq.rank_bb() & beyond).is_empty() {
+                    if *side == CastleType::KingSide { 'K' } else { 'Q' }
+                } else {
+                    FILE_DISPLAYS[r_sq.file_idx_of_sq() as usize].to_ascii_uppercase()
+                };
+                s.push(match *player {
+                    Player::White => c,
+                    Player::Black => c.to_ascii_lowercase(),
+                });
+            }
+        }
+        if s.is_empty() {
+            s.push('-');
+        }
+        s
+    }
+
+
+    /// Helper method for creating a Board with the given pieces, side to move and number of
+    /// half moves played.
+    ///
+    /// The BoardState is left at its default, and must be set with `Board::set_state()`.
+    fn from_piece_locations(piece_loc: PieceLocations, turn: Player, half_moves: u16) -> Board {
+        let mut b = Board {
+            turn: turn,
+            bit_boards: [[BitBoard(0); PIECE_TYPE_CNT]; PLAYER_CNT],
+            occ: [BitBoard(0), BitBoard(0)],
+            occ_all: BitBoard(0),
+            half_moves: half_moves,
+            depth: 0,
+            piece_counts: [[0; PIECE_TYPE_CNT]; PLAYER_CNT],
+            piece_locations: piece_loc,
+            state: Arc::new(BoardState::default()),
+            castling_rook_sq: [[NO_SQ; CASTLING_SIDES]; PLAYER_CNT],
+            castling_path: [[BitBoard(0); CASTLING_SIDES]; PLAYER_CNT],
+            castling_mask: [0; SQ_CNT],
+            chess960: false,
+            magic_helper: Helper::new(),
+        };
+        b.set_bitboards();
+        b
+    }
+
+    /// Helper method for creating the BoardState of a Board with its pieces already placed,
+    /// given the castling field of a FEN string, the en-passant square and the rule 50 count.
+    ///
+    /// # Safety
+    ///
+    /// Assumes each player has exactly one king.
+    fn set_state(&mut self, castling: &str, ep_sq: SQ, rule_50: i16) -> Result<(), FenBuildError> {
+        let mut board_s = UniqueArc::new(BoardState {
+            castling: Castling::empty(),
+            rule_50: rule_50,
+            ply: 0,
+            ep_square: ep_sq,
+            psq: Score::ZERO,
+            zobrast: 0,
+            pawn_key: 0,
+            material_key: 0,
+            nonpawn_material: [0; PLAYER_CNT],
+            captured_piece: None,
+            checkers_bb: BitBoard(0),
+            blockers_king: [BitBoard(0); PLAYER_CNT],
+            pinners_king: [BitBoard(0); PLAYER_CNT],
+            check_sqs: [BitBoard(0); PIECE_TYPE_CNT],
+            prev_move: BitMove::null(),
+            prev: None,
+        });
+
+        { // Set Castling and Check info
+            let state: &mut BoardState = &mut *board_s;
+            state.castling = self.set_castling_from_fen(castling)?;
+            state.checkers_bb = self.attackers_to(self.king_sq(self.turn), self.get_occupied())
+                & self.get_occupied_player(self.turn.other_player());
+            self.set_check_info(state);
+        }
+        self.state = board_s.shareable();
+        self.set_zob_hash();
+        self.set_material_key();
+        Ok(())
+    }
 
     /// Constructs a board from a FEN String.
     ///
     /// FEN stands for Forsyth-Edwards Notation, and is a way of representing a board through a
     /// string of characters. More information can be found on the [ChessWiki](https://chessprogramming.wikispaces.com/Forsyth-Edwards+Notation).
     ///
+    /// The castling field may also be given in the X-FEN or Shredder-FEN formats. If the castling
+    /// rights require a king or rook off of its standard square, the Board is treated as a
+    /// Chess960 game.
+    ///
     /// # Examples
     ///
     /// ```
@@ -436,6 +773,9 @@ impl Board {
     ///
     /// let board = Board::new_from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
     /// assert_eq!(board.count_all_pieces(),32);
+    ///
+    /// let chess960 = Board::new_from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+    /// assert!(chess960.is_chess960());
     /// ```
     ///
     /// # Panics
@@ -463,7 +803,7 @@ impl Board {
             return Err(FenBuildError::IncorrectRankAmounts{ranks: b_rep.len()});
         }
 
-        let (piece_loc, piece_cnt) = PieceLocations::from_partial_fen(b_rep.as_slice())?;
+        let (piece_loc, _) = PieceLocations::from_partial_fen(b_rep.as_slice())?;
 
         // Side to Move
         let turn_char: char = det_split[1].chars()
@@ -476,12 +816,6 @@ impl Board {
             _ => {return Err(FenBuildError::UnrecognizedTurn{turn: det_split[1].to_string()});},
         };
 
-        // Castle Bytes
-        let mut castle_bytes = Castling::empty();
-        for char in det_split[2].chars() {
-            castle_bytes.add_castling_char(char);
-        }
-
         let mut ep_sq: SQ = SQ(0);
         for (i, character) in det_split[3].chars().enumerate() {
             if i > 1 { return Err(FenBuildError::EPSquareUnreadable{ep: det_split[3].to_string()}); }
@@ -525,51 +859,172 @@ impl Board {
             total_moves += 1
         };
 
-        // Create the Board States
-        let mut board_s = UniqueArc::new(BoardState {
-            castling: castle_bytes,
-            rule_50: rule_50,
-            ply: 0,
-            ep_square: ep_sq,
-            psq: Score::ZERO,
-            zobrast: 0,
-            pawn_key: 0,
-            material_key: 0,
-            nonpawn_material: [0; PLAYER_CNT],
-            captured_piece: None,
-            checkers_bb: BitBoard(0),
-            blockers_king: [BitBoard(0); PLAYER_CNT],
-            pinners_king: [BitBoard(0); PLAYER_CNT],
-            check_sqs: [BitBoard(0); PIECE_TYPE_CNT],
-            prev_move: BitMove::null(),
-            prev: None,
-        });
+        let mut b = Board::from_piece_locations(piece_loc, turn, total_moves);
+        b.set_state(det_split[2], ep_sq, rule_50)?;
+        fen::is_valid_fen(b)
+    }
 
-        // Create the Board
-        let mut b = Board {
-            turn: turn,
-            bit_boards: [[BitBoard(0); PIECE_TYPE_CNT]; PLAYER_CNT],
-            occ: [BitBoard(0), BitBoard(0)],
-            occ_all: BitBoard(0),
-            half_moves: total_moves,
-            depth: 0,
-            piece_counts: piece_cnt,
-            piece_locations: piece_loc,
-            state: Arc::new(BoardState::default()),
-            magic_helper: Helper::new(),
+    /// Constructs a Chess960 board from a FEN String.
+    ///
+    /// Unlike `Board::new_from_fen`, the returned Board is always treated as a Chess960 game, even
+    /// if the king and rooks happen to be on their standard squares. This affects the notation of
+    /// castling moves, which are written as the king capturing its own rook.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::Board;
+    ///
+    /// let board = Board::new_from_fen_chess960("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+    /// assert!(board.is_chess960());
+    /// ```
+    pub fn new_from_fen_chess960(fen: &str) -> Result<Board,FenBuildError> {
+        let mut board = Board::new_from_fen(fen)?;
+        board.chess960 = true;
+        Ok(board)
+    }
+
+    /// Constructs the Chess960 starting position with the given index, from 0 to 959.
+    ///
+    /// Positions are numbered by the Scharnagl scheme, where position 518 is the standard
+    /// starting position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than 960.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::Board;
+    ///
+    /// let board = Board::new_chess960(518);
+    /// assert_eq!(board.get_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    /// assert!(board.is_chess960());
+    /// ```
+    pub fn new_chess960(index: u16) -> Board {
+        assert!(index < 960);
+        let mut back_rank: [Option<char>; FILE_CNT] = [None; FILE_CNT];
+        let mut n = index as usize;
+
+        back_rank[(n % 4) * 2 + 1] = Some('B');
+        n /= 4;
+        back_rank[(n % 4) * 2] = Some('B');
+        n /= 4;
+
+        // The remaining pieces are placed on the nth empty file
+        let nth_empty = |rank: &[Option<char>; FILE_CNT], mut i: usize| -> usize {
+            for (file, piece) in rank.iter().enumerate() {
+                if piece.is_none() {
+                    if i == 0 {
+                        return file;
+                    }
+                    i -= 1;
+                }
+            }
+            unreachable!()
         };
 
-        // Set the BitBoards
-        b.set_bitboards();
-        { // Set Check info
-            let state: &mut BoardState = &mut *board_s;
-            b.set_check_info(state);
+        let queen = nth_empty(&back_rank, n % 6);
+        back_rank[queen] = Some('Q');
+        n /= 6;
+
+        let (knight_1, knight_2) = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2),
+                                    (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)][n];
+        let knight_2 = nth_empty(&back_rank, knight_2);
+        let knight_1 = nth_empty(&back_rank, knight_1);
+        back_rank[knight_2] = Some('N');
+        back_rank[knight_1] = Some('N');
+
+        for piece in ['R', 'K', 'R'].iter() {
+            let file = nth_empty(&back_rank, 0);
+            back_rank[file] = Some(*piece);
         }
-        b.state = board_s.shareable();
-        b.set_zob_hash();
-        b.set_material_key();
 
-        fen::is_valid_fen(b)
+        let white: String = back_rank.iter().map(|p| p.unwrap()).collect();
+        let fen = format!("{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1",
+                          white.to_lowercase(), white);
+        Board::new_from_fen_chess960(&fen).unwrap()
+    }
+
+    /// Returns the Board with its ranks mirrored and the colors of every piece swapped, so that
+    /// White's pieces become Black's and vice versa. The castling rights, en-passant square and
+    /// side to move are swapped as well.
+    ///
+    /// The returned Board has no previous moves, with its `BoardState` regenerated from scratch.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::Board;
+    ///
+    /// let board = Board::new_from_fen("r3k3/8/8/8/3Pp3/8/8/4K2R b Kq d3 0 31").unwrap();
+    /// let flipped = board.flip_colors();
+    /// assert_eq!(flipped.get_fen(), "4k2r/8/8/3pP3/8/8/8/R3K3 w Qk d6 0 31");
+    /// assert_eq!(flipped.flip_colors().zobrist(), board.zobrist());
+    /// ```
+    pub fn flip_colors(&self) -> Board {
+        let mut piece_loc = PieceLocations::blank();
+        for sq in 0..SQ_CNT as u8 {
+            if let Some((player, piece)) = self.piece_locations.player_piece_at(SQ(sq)) {
+                piece_loc.place(SQ(sq).flip(), player.other_player(), piece);
+            }
+        }
+
+        let castling: String = if self.chess960 {
+            self.chess960_castling_string()
+        } else {
+            self.state.castling.pretty_string()
+        }.chars()
+            .map(|c| if c.is_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() })
+            .collect();
+
+        let ep_sq: SQ = if self.ep_square() == NO_SQ { NO_SQ } else { self.ep_square().flip() };
+        self.transformed(piece_loc, self.turn.other_player(), &castling, ep_sq)
+    }
+
+    /// Returns the Board with its files mirrored, so that pieces on the a-file move to the
+    /// h-file and vice versa.
+    ///
+    /// The returned Board has no previous moves, with its `BoardState` regenerated from scratch.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either player can still castle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::Board;
+    ///
+    /// let board = Board::new_from_fen("4k3/8/8/8/3Pp3/8/8/1K5R b - d3 0 31").unwrap();
+    /// let mirrored = board.mirror_horizontal();
+    /// assert_eq!(mirrored.get_fen(), "3k4/8/8/8/3pP3/8/8/R5K1 b - e3 0 31");
+    /// ```
+    pub fn mirror_horizontal(&self) -> Board {
+        assert!(self.state.castling.no_castling());
+        let mut piece_loc = PieceLocations::blank();
+        for sq in 0..SQ_CNT as u8 {
+            if let Some((player, piece)) = self.piece_locations.player_piece_at(SQ(sq)) {
+                piece_loc.place(SQ(sq ^ 0b111), player, piece);
+            }
+        }
+
+        let ep_sq: SQ = if self.ep_square() == NO_SQ { NO_SQ } else { SQ(self.ep_square().0 ^ 0b111) };
+        self.transformed(piece_loc, self.turn, "-", ep_sq)
+    }
+
+    /// Helper method for creating a transformed copy of the Board, keeping the move counters.
+    fn transformed(&self, piece_loc: PieceLocations, turn: Player, castling: &str, ep_sq: SQ) -> Board {
+        let mut half_moves: u16 = (self.half_moves / 2) * 2;
+        if turn == Player::Black {
+            half_moves += 1;
+        }
+        let mut board = Board::from_piece_locations(piece_loc, turn, half_moves);
+        board.set_state(castling, ep_sq, self.state.rule_50)
+            .expect("castling rights of a valid board");
+        board.chess960 |= self.chess960;
+        board
     }
 
     /// Creates a FEN String of the Given Board.
@@ -627,7 +1082,11 @@ impl Board {
         s.push(' ');
 
         // Castling State
-        s.push_str(&(self.state.castling.pretty_string()));
+        if self.chess960 {
+            s.push_str(&self.chess960_castling_string());
+        } else {
+            s.push_str(&(self.state.castling.pretty_string()));
+        }
         s.push(' ');
 
         // EP Square
@@ -787,9 +1246,11 @@ impl Board {
             }
 
             // Update castling rights
-            if !new_state.castling.is_empty() && (to.castle_rights_mask() | from.castle_rights_mask()) != 0 {
-                let castle_zob_index = new_state.castling.update_castling(to,from);
-                zob ^= z_castle(castle_zob_index);
+            let castle_mask: u8 = self.castling_mask[to.0 as usize] | self.castling_mask[from.0 as usize];
+            if !new_state.castling.is_empty() && castle_mask != 0 {
+                zob ^= z_castle(new_state.castling.rights().bits());
+                new_state.castling.update_castling_mask(castle_mask);
+                zob ^= z_castle(new_state.castling.rights().bits());
             }
 
             // Actually move the piece
@@ -809,7 +1270,7 @@ impl Board {
                     self.remove_piece_c(piece, to, us);
                     self.put_piece_c(promo_piece, to, us);
                     zob ^= z_square(to, us, promo_piece) ^
-                        z_square(from, us, PieceType::P);
+                        z_square(to, us, PieceType::P);
                     pawn_key ^= z_square(to, us, PieceType::P);
 
                     let promo_count = self.count_piece(us, promo_piece);
@@ -861,18 +1322,198 @@ impl Board {
     /// let mut board = Board::default();
     /// let success = board.apply_uci_move("e2e4");
     ///
-    /// assert!(success);
+    /// assert!(success);
+    /// ```
+    pub fn apply_uci_move(&mut self, uci_move: &str) -> bool {
+        match self.parse_uci_move(uci_move) {
+            Ok(mov) => {
+                self.apply_move(mov);
+                true
+            },
+            Err(_) => false
+        }
+    }
+
+    /// Applies a move to the Board if it is legal, or returns why it is not. Unlike
+    /// `Board::apply_move()`, any `BitMove` may be passed in, and the board is left unchanged
+    /// if the move is illegal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::{Board, BitMove, SQ};
+    /// use pleco::board::IllegalMove;
+    ///
+    /// let mut board = Board::default();
+    /// let e2e4 = board.parse_uci_move("e2e4").unwrap();
+    /// assert!(board.try_apply_move(e2e4).is_ok());
+    ///
+    /// let e3e4 = BitMove::make_quiet(SQ::E3, SQ::E4);
+    /// assert_eq!(board.try_apply_move(e3e4), Err(IllegalMove::NoPiece {sq: SQ::E3}));
+    /// ```
+    pub fn try_apply_move(&mut self, bit_move: BitMove) -> Result<(), IllegalMove> {
+        self.check_move(bit_move)?;
+        self.apply_move(bit_move);
+        Ok(())
+    }
+
+    /// Undoes the previously applied move, or returns why it cannot be. Unlike
+    /// `Board::undo_move()`, this does not panic if there is no move to undo, such as after a
+    /// `Board::shallow_clone()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::Board;
+    /// use pleco::board::UndoMoveError;
+    ///
+    /// let mut board = Board::default();
+    /// assert!(board.apply_uci_move("e2e4"));
+    ///
+    /// let mut board_clone = board.shallow_clone();
+    /// assert_eq!(board_clone.try_undo_move(), Err(UndoMoveError::NoPreviousMove));
+    /// assert_eq!(board.try_undo_move(), Ok(()));
+    /// ```
+    pub fn try_undo_move(&mut self) -> Result<(), UndoMoveError> {
+        if self.depth == 0 || self.state.prev.is_none() {
+            return Err(UndoMoveError::NoPreviousMove);
+        }
+        if self.state.prev_move.is_null() {
+            return Err(UndoMoveError::NullMove);
+        }
+        self.undo_move();
+        Ok(())
+    }
+
+    /// Parses a move in UCI notation, such as `e2e4` or `e7e8q`, returning the matching legal
+    /// move for the current position, or why there is none.
+    ///
+    /// Castling may be written either as the King moving two squares, or as the King moving to
+    /// the square of the rook it castles with. Only the latter is accepted in Chess960 games.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::{Board, SQ};
+    /// use pleco::board::{IllegalMove, MoveParseError};
+    ///
+    /// let board = Board::default();
+    /// assert_eq!(board.parse_uci_move("g1f3").unwrap().stringify(), "g1f3");
+    /// assert!(board.parse_uci_move("g1f9").is_err());
+    /// assert_eq!(board.parse_uci_move("e7e5"),
+    ///            Err(MoveParseError::Illegal(IllegalMove::WrongSide {sq: SQ::E7})));
+    /// ```
+    pub fn parse_uci_move(&self, uci_move: &str) -> Result<BitMove, MoveParseError> {
+        let malformed = || MoveParseError::Malformed {uci: uci_move.to_string()};
+        let bytes: &[u8] = uci_move.as_bytes();
+        if bytes.len() != 4 && bytes.len() != 5 {
+            return Err(malformed());
+        }
+        let src: SQ = parse_uci_square(&bytes[0..2]).ok_or_else(&malformed)?;
+        let dst: SQ = parse_uci_square(&bytes[2..4]).ok_or_else(&malformed)?;
+        let promo: Option<PieceType> = match bytes.get(4) {
+            None => None,
+            Some(&b'q') => Some(PieceType::Q),
+            Some(&b'r') => Some(PieceType::R),
+            Some(&b'b') => Some(PieceType::B),
+            Some(&b'n') => Some(PieceType::N),
+            Some(_) => return Err(malformed())
+        };
+
+        let us: Player = self.turn;
+        match self.piece_locations.player_piece_at(src) {
+            None => return Err(IllegalMove::NoPiece {sq: src}.into()),
+            Some((player, _)) if player != us => return Err(IllegalMove::WrongSide {sq: src}.into()),
+            Some((_, piece)) => {
+                let bit_move: BitMove = self.encode_move(piece, src, dst, promo);
+                self.check_move(bit_move)?;
+                Ok(bit_move)
+            }
+        }
+    }
+
+    /// Encodes a move with the flags it would have if it were generated for this position.
+    fn encode_move(&self, piece: PieceType, src: SQ, dst: SQ, promo: Option<PieceType>) -> BitMove {
+        let us: Player = self.turn;
+        let capture: bool = (self.get_occupied_player(us.other_player()) & dst.to_bb()).is_not_empty();
+
+        if piece == PieceType::K && promo.is_none() {
+            // Castling is written as the King taking its own rook, or outside of Chess960, as
+            // the King moving two squares.
+            let rook_sq: SQ = if !self.chess960 && src == us.relative_square(SQ::E1)
+                && (dst == us.relative_square(SQ::G1) || dst == us.relative_square(SQ::C1)) {
+                self.castling_rook_square(if dst.file() == File::G {
+                    CastleType::KingSide
+                } else {
+                    CastleType::QueenSide
+                })
+            } else {
+                dst
+            };
+            if self.piece_locations.player_piece_at(rook_sq) == Some((us, PieceType::R)) {
+                return BitMove::init(PreMoveInfo {
+                    src,
+                    dst: rook_sq,
+                    flags: MoveFlag::Castle {king_side: rook_sq.file() > src.file()}
+                });
+            }
+        }
+
+        let flags: MoveFlag = if let Some(prom) = promo {
+            MoveFlag::Promotion {capture, prom}
+        } else if piece == PieceType::P && dst == self.ep_square() {
+            MoveFlag::Capture {ep_capture: true}
+        } else if capture {
+            MoveFlag::Capture {ep_capture: false}
+        } else if piece == PieceType::P && (dst.0 as i8 - src.0 as i8).abs() == 16 {
+            MoveFlag::DoublePawnPush
+        } else {
+            MoveFlag::QuietMove
+        };
+        BitMove::init(PreMoveInfo {src, dst, flags})
+    }
+
+    /// Returns why a move is illegal for the current position, if it is.
+    fn check_move(&self, bit_move: BitMove) -> Result<(), IllegalMove> {
+        let src: SQ = bit_move.get_src();
+        match self.piece_locations.player_piece_at(src) {
+            None => return Err(IllegalMove::NoPiece {sq: src}),
+            Some((player, _)) if player != self.turn => return Err(IllegalMove::WrongSide {sq: src}),
+            _ => {}
+        }
+        if !self.pseudo_legal_ignoring_check(bit_move) {
+            return Err(IllegalMove::InvalidMove {bit_move});
+        }
+        if !self.evades_check(bit_move) || !self.legal_move(bit_move) {
+            return Err(IllegalMove::LeavesKingInCheck {bit_move});
+        }
+        Ok(())
+    }
+
+    /// Returns the UCI notation of a move for the current Board.
+    ///
+    /// This is the same as `BitMove::stringify()`, except for Chess960 games, where castling is
+    /// written as the king moving to the square of the rook it castles with.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::Board;
+    ///
+    /// let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQK2R w KQkq - 0 1";
+    /// let board = Board::new_from_fen(fen).unwrap();
+    /// let castle = *board.generate_moves().iter().find(|m| m.is_castle()).unwrap();
+    /// assert_eq!(board.move_to_uci(castle), "e1g1");
+    ///
+    /// let chess960 = Board::new_from_fen_chess960(fen).unwrap();
+    /// assert_eq!(chess960.move_to_uci(castle), "e1h1");
     /// ```
-    pub fn apply_uci_move(&mut self, uci_move: &str) -> bool {
-        let all_moves: MoveList = self.generate_moves();
-        let bit_move: Option<BitMove> = all_moves.iter()
-                                                 .find(|m| m.stringify() == uci_move)
-                                                 .cloned();
-        if let Some(mov) = bit_move {
-            self.apply_move(mov);
-            return true;
+    pub fn move_to_uci(&self, bit_move: BitMove) -> String {
+        if self.chess960 && bit_move.is_castle() {
+            bit_move.get_src().to_string() + &bit_move.get_dest().to_string()
+        } else {
+            bit_move.stringify()
         }
-        false
     }
 
     /// Un-does the previously applied move, allowing the Board to return to it's most recently held state.
@@ -953,17 +1594,12 @@ impl Board {
     /// Apply a "Null Move" to the board, essentially swapping the current turn of
     /// the board without moving any pieces.
     ///
-    /// # Safety
-    ///
-    /// This method should only be used for special evaluation purposes, as it does not give an
-    /// accurate or legal state of the chess board.
+    /// The en-passant square is cleared, and the zobrist key, checkers, pinners and check squares
+    /// are updated for the new side to move. A null move is only possible when the side to move
+    /// is not in check, so `false` is returned and the board is left unchanged if it is.
     ///
-    /// Unsafe as it allows for Null Moves to be applied in states of check, which is never a valid
-    /// state of a chess game.
-    ///
-    /// # Panics
-    ///
-    /// Panics if the Board is currently in check.
+    /// Null moves are used for search techniques such as null-move pruning, and do not give a
+    /// legal state of the chess board. Repetitions are never counted across a null move.
     ///
     /// # Examples
     ///
@@ -973,12 +1609,15 @@ impl Board {
     /// let mut chessboard = Board::default();
     /// let board_clone = chessboard.shallow_clone();
     ///
-    /// unsafe { chessboard.apply_null_move(); }
+    /// assert!(chessboard.apply_null_move());
     ///
     /// assert_ne!(chessboard.depth(), board_clone.depth());
+    /// assert_ne!(chessboard.turn(), board_clone.turn());
     /// ```
-    pub unsafe fn apply_null_move(&mut self) {
-        assert!(self.checkers().is_empty());
+    pub fn apply_null_move(&mut self) -> bool {
+        if self.in_check() {
+            return false;
+        }
 
         let mut zob: u64 = self.state.zobrast ^ z_side();
 
@@ -1002,6 +1641,8 @@ impl Board {
 
             new_state.zobrast = zob;
             self.turn = self.turn.other_player();
+            new_state.checkers_bb = self.attackers_to(self.king_sq(self.turn), self.occ_all)
+                & self.get_occupied_player(self.turn.other_player());
             self.set_check_info(new_state);
         }
         self.state = next_arc_state.shareable();
@@ -1011,14 +1652,15 @@ impl Board {
         } else {
             assert!(self.is_ok_quick());
         }
+        true
     }
 
     /// Undo a "Null Move" to the Board, returning to the previous state.
     ///
-    /// # Safety
+    /// # Panics
     ///
-    /// This method should only be used if it can be guaranteed that the last played move from
-    /// the current state is a Null-Move, eg `Board::apply_null_move()`. Otherwise, a panic will occur.
+    /// Panics if the last played move from the current state is not a Null-Move, eg
+    /// `Board::apply_null_move()`.
     ///
     /// # Examples
     ///
@@ -1028,19 +1670,22 @@ impl Board {
     /// let mut chessboard = Board::default();
     /// let board_clone = chessboard.shallow_clone();
     ///
-    /// unsafe { chessboard.apply_null_move(); }
+    /// chessboard.apply_null_move();
     ///
     /// assert_ne!(chessboard.ply(), board_clone.ply());
     ///
-    /// unsafe { chessboard.undo_null_move(); }
+    /// chessboard.undo_null_move();
     ///
     /// assert_eq!(chessboard.moves_played(), board_clone.moves_played());
+    /// assert_eq!(chessboard.depth(), board_clone.depth());
     /// assert_eq!(chessboard.get_fen(), board_clone.get_fen());
     /// ```
-    pub unsafe fn undo_null_move(&mut self) {
+    pub fn undo_null_move(&mut self) {
+        assert!(self.state.prev.is_some());
         assert!(self.state.prev_move.is_null());
         self.turn = self.turn.other_player();
         self.state = self.state.get_prev().unwrap();
+        self.depth -= 1;
     }
 
     /// Get a List of legal `BitMove`s for the player whose turn it is to move.
@@ -1269,8 +1914,12 @@ impl Board {
             *to_r_orig = player.relative_square( SQ(2));
             *r_dst = player.relative_square( SQ(3));
         }
-        self.move_piece_c(PieceType::K, k_src, *to_r_orig, player);
-        self.move_piece_c(PieceType::R, *r_src, *r_dst, player);
+        // In Chess960 the king and rook may land on each other's starting squares,
+        // so remove both before placing either.
+        self.remove_piece_c(PieceType::K, k_src, player);
+        self.remove_piece_c(PieceType::R, *r_src, player);
+        self.put_piece_c(PieceType::K, *to_r_orig, player);
+        self.put_piece_c(PieceType::R, *r_dst, player);
     }
 
     /// Helper function to remove a Castling for a given player.
@@ -1289,8 +1938,10 @@ impl Board {
             player.relative_square(SQ(3))
         };
 
-        self.move_piece_c(PieceType::K, k_dst, k_src, player);
-        self.move_piece_c(PieceType::R, r_dst, r_src, player);
+        self.remove_piece_c(PieceType::K, k_dst, player);
+        self.remove_piece_c(PieceType::R, r_dst, player);
+        self.put_piece_c(PieceType::K, k_src, player);
+        self.put_piece_c(PieceType::R, r_src, player);
     }
 
     /// Helper function to that outputs the Blockers of a given square
@@ -1348,6 +1999,8 @@ impl Board {
             Player::White => {}
         };
 
+        zob ^= z_castle(self.state.castling.rights().bits());
+
         let state =  Arc::get_mut(&mut self.state).unwrap();
 
         state.zobrast = zob;
@@ -1380,6 +2033,22 @@ impl Board {
         self.state.zobrast
     }
 
+    /// Returns the Polyglot key of the board, used to look up positions in a Polyglot opening
+    /// book.
+    ///
+    /// Unlike `Board::zobrist()`, this is computed from scratch on each call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::Board;
+    ///
+    /// assert_eq!(Board::default().polyglot_key(), 0x463b_9618_1691_fc9c);
+    /// ```
+    pub fn polyglot_key(&self) -> u64 {
+        book::polyglot_key(self)
+    }
+
     /// Return the pawn key of the board.
     ///
     /// This is a semi-unique key for any configuration of pawns on the board.
@@ -1701,14 +2370,20 @@ impl Board {
     /// having the rook and king be in the correct square.
     #[inline]
     pub fn castle_impeded(&self, castle_type: CastleType) -> bool {
-        let path: BitBoard = BitBoard(CASTLING_PATH[self.turn as usize][castle_type as usize]);
+        let path: BitBoard = self.castling_path[self.turn as usize][castle_type as usize];
         (path & self.occ_all).is_not_empty()
     }
 
     /// Square of the Rook that is involved with the current player's castle.
     #[inline]
     pub fn castling_rook_square(&self, castle_type: CastleType) -> SQ {
-        SQ(CASTLING_ROOK_START[self.turn as usize][castle_type as usize])
+        self.castling_rook_sq[self.turn as usize][castle_type as usize]
+    }
+
+    /// Returns if the Board is a game of Chess960.
+    #[inline(always)]
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
     }
 
     /// Return the last move played, if any.
@@ -1768,6 +2443,145 @@ impl Board {
         !self.in_check() && (self.generate_moves().is_empty() || self.state.rule_50 >= 50)
     }
 
+    /// Returns if the current position has occurred at least `count` times, including the
+    /// current occurrence.
+    ///
+    /// Only positions since the last capture or pawn move (the rule 50 window) with the same
+    /// side to move are compared, by their zobrist keys. `Board::is_repetition(3)` checks for
+    /// a threefold repetition.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::Board;
+    ///
+    /// let mut chessboard = Board::default();
+    /// for mov in ["g1f3", "g8f6", "f3g1", "f6g8"].iter() {
+    ///     chessboard.apply_uci_move(mov);
+    /// }
+    /// assert!(chessboard.is_repetition(2));
+    /// assert!(!chessboard.is_repetition(3));
+    /// ```
+    pub fn is_repetition(&self, count: u8) -> bool {
+        if count <= 1 {
+            return true;
+        }
+        let key: u64 = self.state.zobrast;
+        let rule_50: i16 = self.state.rule_50;
+        let mut occurrences: u8 = 1;
+        let mut plies_back: i16 = 2;
+
+        let mut state: Option<&Arc<BoardState>> = prev_played_state(&self.state)
+            .and_then(|s| prev_played_state(s));
+
+        while let Some(prev_state) = state {
+            if plies_back > rule_50 {
+                break;
+            }
+            if prev_state.zobrast == key {
+                occurrences += 1;
+                if occurrences >= count {
+                    return true;
+                }
+            }
+            plies_back += 2;
+            state = prev_played_state(prev_state)
+                .and_then(|s| prev_played_state(s));
+        }
+        false
+    }
+
+    /// Returns if the current position is drawn by the fifty-move rule, meaning one hundred
+    /// half-moves have been played without a capture or a pawn move.
+    ///
+    /// A checkmate delivered on the final half-move takes precedence over the draw.
+    ///
+    /// This method can be computationally expensive if the side to move is in check.
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.state.rule_50 >= 100 && (!self.in_check() || !self.generate_moves().is_empty())
+    }
+
+    /// Returns if the current position is drawn by either threefold repetition or the
+    /// fifty-move rule.
+    ///
+    /// This method does not account for stalemate or insufficient material.
+    pub fn is_draw(&self) -> bool {
+        self.is_fifty_move_draw() || self.is_repetition(3)
+    }
+
+    /// Returns if neither player has enough material left to checkmate the other.
+    ///
+    /// This covers a lone king against a lone king, a king and a single minor piece against a
+    /// lone king, and positions where the only pieces besides the kings are bishops that all
+    /// stand on squares of the same colour.
+    pub fn is_insufficient_material(&self) -> bool {
+        if self.count_piece(Player::White, PieceType::P) + self.count_piece(Player::Black, PieceType::P) > 0
+            || self.piece_two_bb_both_players(PieceType::R, PieceType::Q).is_not_empty() {
+            return false;
+        }
+
+        let knights: u8 = self.count_piece(Player::White, PieceType::N)
+            + self.count_piece(Player::Black, PieceType::N);
+        let bishops: BitBoard = self.piece_bb_both_players(PieceType::B);
+
+        if knights + bishops.count_bits() <= 1 {
+            return true;
+        }
+
+        knights == 0 && ((bishops & BitBoard::DARK_SQUARES).is_empty()
+            || (bishops & BitBoard::LIGHT_SQUARES).is_empty())
+    }
+
+    /// Returns the outcome of the game if it has ended, or `None` if the game is still
+    /// in progress.
+    ///
+    /// The game is over by checkmate, stalemate, insufficient material, the fifty-move rule,
+    /// or threefold repetition, checked in that order.
+    ///
+    /// This method can be computationally expensive, do not use outside of Engines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::Board;
+    /// use pleco::Player;
+    /// use pleco::board::Termination;
+    ///
+    /// let mut chessboard = Board::default();
+    /// assert!(chessboard.outcome().is_none());
+    ///
+    /// for mov in ["f2f3", "e7e5", "g2g4", "d8h4"].iter() {
+    ///     chessboard.apply_uci_move(mov);
+    /// }
+    /// let outcome = chessboard.outcome().unwrap();
+    /// assert_eq!(outcome.winner, Some(Player::Black));
+    /// assert_eq!(outcome.termination, Termination::Checkmate);
+    /// ```
+    pub fn outcome(&self) -> Option<GameOutcome> {
+        let termination = if self.generate_moves().is_empty() {
+            if self.in_check() {
+                return Some(GameOutcome {
+                    winner: Some(self.turn.other_player()),
+                    termination: Termination::Checkmate,
+                });
+            }
+            Termination::Stalemate
+        } else if self.is_insufficient_material() {
+            Termination::InsufficientMaterial
+        } else if self.state.rule_50 >= 100 {
+            Termination::FiftyMoveRule
+        } else if self.is_repetition(3) {
+            Termination::ThreefoldRepetition
+        } else {
+            return None;
+        };
+
+        Some(GameOutcome {
+            winner: None,
+            termination,
+        })
+    }
+
     /// Return the `BitBoard` of all checks on the current player's king. If the current side
     /// to move is not in check, the `BitBoard` will be empty.
     #[inline(always)]
@@ -1849,7 +2663,7 @@ impl Board {
 
             return (rook_moves(occupied, k_sq) &
                 self.sliding_piece_bb(them)).is_empty() &&
-                (queen_moves(occupied, k_sq) & self.diagonal_piece_bb(them)).is_empty();
+                (bishop_moves(occupied, k_sq) & self.diagonal_piece_bb(them)).is_empty();
         }
 
         // If Moving the king, check if the square moved to is not being attacked
@@ -1869,10 +2683,143 @@ impl Board {
             aligned(src, dst, self.king_sq(self.turn))
     }
 
-    #[doc(hidden)]
-    pub fn pseudo_legal_move(&self, _m: BitMove) -> bool {
-        unimplemented!()
-        // TODO: create pseduo-legal-move
+    /// Tests if a move is pseudo-legal for the current position, meaning it could have been
+    /// generated for the side to move, without verifying that it doesn't leave the King in
+    /// check through a pin.
+    ///
+    /// Unlike the other move testing methods, this accepts any `BitMove`, including moves from
+    /// another position. Combined with `Board::legal_move()`, this validates a move without
+    /// generating the full list of moves, such as a move retrieved from a transposition table.
+    ///
+    /// The moving piece, destination square, move flags, and any squares in between are checked.
+    /// If the side to move is in check, the move must also be a King move to a safe square,
+    /// or capture or block a single checking piece.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::{Board, BitMove, SQ};
+    ///
+    /// let board = Board::default();
+    /// let moves = board.generate_moves();
+    /// assert!(moves.iter().all(|m| board.pseudo_legal_move(*m) && board.legal_move(*m)));
+    ///
+    /// // A pawn cannot move three squares.
+    /// assert!(!board.pseudo_legal_move(BitMove::make_quiet(SQ::E2, SQ::E5)));
+    /// assert!(!board.pseudo_legal_move(BitMove::null()));
+    /// ```
+    pub fn pseudo_legal_move(&self, m: BitMove) -> bool {
+        self.pseudo_legal_ignoring_check(m) && self.evades_check(m)
+    }
+
+    /// Tests if a move is pseudo-legal, without considering if the side to move is in check.
+    fn pseudo_legal_ignoring_check(&self, m: BitMove) -> bool {
+        let src: SQ = m.get_src();
+        let dst: SQ = m.get_dest();
+        if src == dst {
+            return false;
+        }
+        let us: Player = self.turn;
+        let them: Player = us.other_player();
+        let piece: PieceType = match self.piece_locations.player_piece_at(src) {
+            Some((player, piece)) if player == us => piece,
+            _ => return false
+        };
+
+        if m.is_castle() {
+            return piece == PieceType::K && self.pseudo_legal_castle(m);
+        }
+
+        let flag: u16 = m.get_raw() >> 12;
+        let dst_bb: BitBoard = dst.to_bb();
+        // The two flags left unused by the move encoding.
+        if flag == 6 || flag == 7 || (self.get_occupied_player(us) & dst_bb).is_not_empty() {
+            return false;
+        }
+        if m.is_en_passant() {
+            if piece != PieceType::P || dst != self.ep_square() {
+                return false;
+            }
+        } else if m.is_capture() != (self.get_occupied_player(them) & dst_bb).is_not_empty() {
+            return false;
+        }
+
+        if piece == PieceType::P {
+            if m.is_promo() != (us.relative_rank_of_sq(dst) == Rank::R8) {
+                return false;
+            }
+            let push: SQ = SQ((src.0 as i8 + us.pawn_push()) as u8);
+            let valid = if m.is_capture() {
+                (pawn_attacks_from(src, us) & dst_bb).is_not_empty()
+            } else if flag == BitMove::FLAG_DOUBLE_PAWN {
+                us.relative_rank_of_sq(src) == Rank::R2
+                    && dst.0 as i8 == push.0 as i8 + us.pawn_push()
+                    && self.piece_at_sq(push).is_none()
+            } else {
+                dst == push
+            };
+            if !valid {
+                return false;
+            }
+        } else if m.is_promo() || flag == BitMove::FLAG_DOUBLE_PAWN || m.is_en_passant()
+            || (self.attacks_from(piece, src, us) & dst_bb).is_empty() {
+            return false;
+        }
+        true
+    }
+
+    /// Tests if a pseudo-legal move is an evasion when the side to move is in check.
+    fn evades_check(&self, m: BitMove) -> bool {
+        let checkers: BitBoard = self.checkers();
+        if checkers.is_not_empty() && !m.is_castle() {
+            let src: SQ = m.get_src();
+            let dst: SQ = m.get_dest();
+            let dst_bb: BitBoard = dst.to_bb();
+            if self.piece_at_sq(src) == Some(PieceType::K) {
+                // The king cannot hide from a slider behind its own square.
+                let occupied: BitBoard = self.occ_all ^ src.to_bb();
+                let them: BitBoard = self.get_occupied_player(self.turn.other_player());
+                return (self.attackers_to(dst, occupied) & them).is_empty();
+            }
+            if checkers.more_than_one() {
+                return false;
+            }
+            let evasion: BitBoard = between_bb(checkers.to_sq(), self.king_sq(self.turn)) | checkers;
+            let captures_checker: bool = m.is_en_passant()
+                && (checkers & SQ(dst.0 ^ 8).to_bb()).is_not_empty();
+            if (evasion & dst_bb).is_empty() && !captures_checker {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Tests if a castling move is pseudo-legal, including that the King is not castling
+    /// out of, through, or into check.
+    fn pseudo_legal_castle(&self, m: BitMove) -> bool {
+        let us: Player = self.turn;
+        let side: CastleType = if m.is_king_castle() {
+            CastleType::KingSide
+        } else {
+            CastleType::QueenSide
+        };
+        let r_from: SQ = m.get_dest();
+        if self.in_check() || !self.can_castle(us, side) || self.castle_impeded(side)
+            || r_from != self.castling_rook_square(side)
+            || self.piece_locations.player_piece_at(r_from) != Some((us, PieceType::R)) {
+            return false;
+        }
+
+        let k_to: SQ = us.relative_square(
+            if side == CastleType::KingSide { SQ::G1 } else { SQ::C1 });
+        let occupied: BitBoard = self.occ_all ^ r_from.to_bb();
+        let mut king_path: BitBoard = between_bb(m.get_src(), k_to) | k_to.to_bb();
+        while let Some(s) = king_path.pop_some_lsb() {
+            if (self.attackers_to(s, occupied) & self.get_occupied_player(us.other_player())).is_not_empty() {
+                return false;
+            }
+        }
+        true
     }
 
     /// Returns if a move gives check to the opposing player's King.
@@ -1977,6 +2924,119 @@ impl Board {
         self.piece_at_sq(dst)
     }
 
+    /// Returns the Static Exchange Evaluation of a move, the material the side to move wins
+    /// or loses from the exchange of pieces started by the move on its destination square.
+    ///
+    /// Each side recaptures with its least valuable piece, and may stop capturing whenever
+    /// continuing would lose material. Attackers revealed behind other pieces (x-rays) join the
+    /// exchange, pieces pinned to their king cannot recapture while the pinner remains, and
+    /// pawns recapturing onto the last rank promote to a queen. Castling has an evaluation
+    /// of zero.
+    ///
+    /// # Safety
+    ///
+    /// Assumes the move is legal for the current board.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::Board;
+    /// use pleco::helper::prelude::piece_value;
+    /// use pleco::PieceType;
+    ///
+    /// // The pawn on d5 is defended by a rook, but white has a second rook behind the first.
+    /// let board = Board::new_from_fen("3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1").unwrap();
+    /// let capture = *board.generate_moves().iter().find(|m| m.stringify() == "d2d5").unwrap();
+    /// assert_eq!(board.see(capture), piece_value(PieceType::P, false));
+    /// ```
+    pub fn see(&self, m: BitMove) -> Value {
+        if m.is_castle() {
+            return 0;
+        }
+        let from: SQ = m.get_src();
+        let to: SQ = m.get_dest();
+        let promo_gain: Value = piece_value(PieceType::Q, false) - piece_value(PieceType::P, false);
+
+        let mut occupied: BitBoard = self.occ_all ^ from.to_bb();
+        let mut gain: [Value; 32] = [0; 32];
+        let mut on_square: Value = piece_value(self.moved_piece(m), false);
+        gain[0] = self.captured_piece(m).map_or(0, |p| piece_value(p, false));
+        if m.is_en_passant() {
+            occupied ^= SQ(to.0 ^ 8).to_bb();
+        }
+        if m.is_promo() {
+            on_square = piece_value(m.promo_piece(), false);
+            gain[0] += on_square - piece_value(PieceType::P, false);
+        }
+
+        let mut attackers: BitBoard = self.attackers_to(to, occupied) & occupied;
+        let mut stm: Player = self.turn.other_player();
+        let mut d: usize = 0;
+
+        loop {
+            let mut stm_attackers: BitBoard = attackers & self.get_occupied_player(stm);
+            if (self.state.pinners_king[stm as usize] & occupied).is_not_empty() {
+                stm_attackers &= !self.state.blockers_king[stm as usize];
+            }
+            if stm_attackers.is_empty() {
+                break;
+            }
+
+            let piece: PieceType = *[PieceType::P, PieceType::N, PieceType::B,
+                                     PieceType::R, PieceType::Q, PieceType::K].iter()
+                .find(|p| (stm_attackers & self.piece_bb(stm, **p)).is_not_empty())
+                .unwrap();
+
+            // The king can only recapture if the square is no longer defended.
+            if piece == PieceType::K
+                && (attackers & self.get_occupied_player(stm.other_player())).is_not_empty() {
+                break;
+            }
+
+            d += 1;
+            gain[d] = on_square - gain[d - 1];
+            on_square = piece_value(piece, false);
+            if piece == PieceType::P && stm.relative_rank_of_sq(to) == Rank::R8 {
+                gain[d] += promo_gain;
+                on_square += promo_gain;
+            }
+
+            occupied ^= (stm_attackers & self.piece_bb(stm, piece)).lsb();
+            if piece == PieceType::P || piece == PieceType::B || piece == PieceType::Q {
+                attackers |= bishop_moves(occupied, to) & self.piece_two_bb_both_players(PieceType::B, PieceType::Q);
+            }
+            if piece == PieceType::R || piece == PieceType::Q {
+                attackers |= rook_moves(occupied, to) & self.piece_two_bb_both_players(PieceType::R, PieceType::Q);
+            }
+            attackers &= occupied;
+            stm = stm.other_player();
+        }
+
+        while d > 0 {
+            gain[d - 1] = -max(-gain[d - 1], gain[d]);
+            d -= 1;
+        }
+        gain[0]
+    }
+
+    /// Returns if the Static Exchange Evaluation of a move is at least `threshold`. See
+    /// `Board::see()` for how the exchange is evaluated.
+    ///
+    /// # Safety
+    ///
+    /// Assumes the move is legal for the current board.
+    pub fn see_ge(&self, m: BitMove, threshold: Value) -> bool {
+        // The move can never win more than the captured piece plus any promotion.
+        let mut best_case: Value = self.captured_piece(m).map_or(0, |p| piece_value(p, false));
+        if m.is_promo() {
+            best_case += piece_value(m.promo_piece(), false) - piece_value(PieceType::P, false);
+        }
+        if best_case < threshold || (m.is_castle() && threshold > 0) {
+            return false;
+        }
+        self.see(m) >= threshold
+    }
+
     /// Returns a prettified String of the current `Board`, for easy command line displaying.
     ///
     /// Capital Letters represent white pieces, while lower case represents black pieces.
@@ -2060,17 +3120,37 @@ impl Board {
 
 }
 
+/// Parses a square written as a file and rank, such as `e4`.
+fn parse_uci_square(s: &[u8]) -> Option<SQ> {
+    match (s[0], s[1]) {
+        (f @ b'a'...b'h', r @ b'1'...b'8') => Some(SQ((r - b'1') * 8 + (f - b'a'))),
+        _ => None
+    }
+}
+
+/// Returns the state before the move that led to `state`, stopping at null moves and at the
+/// first state of the game.
+fn prev_played_state(state: &BoardState) -> Option<&Arc<BoardState>> {
+    if state.prev_move.is_null() {
+        None
+    } else {
+        state.prev.as_ref()
+    }
+}
+
 // TODO: Error Propagation
 
 /// Errors concerning the current `Board` position.
 pub enum BoardError {
     IncorrectKingNum {player: Player, num: u8},
+    OpponentInCheck {player: Player},
 }
 
 impl fmt::Debug for BoardError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             BoardError::IncorrectKingNum{player, num} => writeln!(f, "incorrect number of kings for {}: {}", player, num),
+            BoardError::OpponentInCheck{player} => writeln!(f, "the king of {} is in check, but it is not their turn", player),
         }
     }
 }
@@ -2085,17 +3165,24 @@ impl Board {
     }
 
     /// Checks if the current state of the Board is okay.
+    ///
+    /// Each player must have exactly one king, and the player not to move cannot be in check.
     pub fn is_okay(&self) -> Result<(), BoardError> {
         self.check_king()?;
         Ok(())
     }
 
     fn check_king(&self) -> Result<(), BoardError> {
-        // TODO: Implement attacks to opposing king must be zero
         let w_king_num = self.count_piece(Player::White, PieceType::K);
         let b_king_num = self.count_piece(Player::Black, PieceType::K);
         if w_king_num != 1 { return Err(BoardError::IncorrectKingNum {player: Player::White, num: w_king_num}); }
-        if w_king_num != 1 { return Err(BoardError::IncorrectKingNum {player: Player::Black, num: b_king_num}); }
+        if b_king_num != 1 { return Err(BoardError::IncorrectKingNum {player: Player::Black, num: b_king_num}); }
+
+        // The side that just moved cannot have left its king attacked
+        let them = self.turn.other_player();
+        let attackers = self.attackers_to(self.king_sq(them), self.get_occupied())
+            & self.get_occupied_player(self.turn);
+        if attackers.is_not_empty() { return Err(BoardError::OpponentInCheck {player: them}); }
         Ok(())
     }
 //
@@ -2323,8 +3410,11 @@ impl RandBoard {
 mod tests {
 
     extern crate rand;
-    use board::Board;
-    use {BitMove, SQ, PieceType};
+    use board::{Board, GameOutcome, Termination, IllegalMove, MoveParseError, UndoMoveError};
+    use board::perft;
+    use board::fen::{STANDARD_FEN_MATE_STALEMATE, ALL_FENS};
+    use helper::prelude::piece_value;
+    use {BitMove, SQ, PieceType, Player};
 
     #[test]
     fn random_move_apply() {
@@ -2424,4 +3514,299 @@ mod tests {
         assert_eq!(b.moved_piece(bmove), PieceType::P);
         assert_eq!(b.captured_piece(bmove), None);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn repetition_draw() {
+        let mut b = Board::default();
+        assert!(!b.is_repetition(2));
+        for _ in 0..2 {
+            for mov in ["g1f3", "g8f6", "f3g1", "f6g8"].iter() {
+                assert!(!b.is_draw());
+                assert!(b.apply_uci_move(mov));
+            }
+        }
+        assert!(b.is_repetition(3));
+        assert!(b.is_draw());
+
+        // A pawn move resets the rule 50 window
+        assert!(b.apply_uci_move("e2e4"));
+        assert!(!b.is_repetition(2));
+    }
+
+    #[test]
+    fn fifty_move_draw() {
+        let b = Board::new_from_fen("8/8/4k3/8/8/4K3/8/R7 w - - 99 80").unwrap();
+        assert!(!b.is_fifty_move_draw());
+        let b = Board::new_from_fen("8/8/4k3/8/8/4K3/8/R7 w - - 100 80").unwrap();
+        assert!(b.is_fifty_move_draw());
+        assert!(b.is_draw());
+    }
+
+    #[test]
+    fn chess960_start_positions() {
+        assert_eq!(Board::new_chess960(0).get_fen(),
+                   "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+        assert_eq!(Board::new_chess960(518).get_fen(),
+                   "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(Board::new_chess960(959).get_fen(),
+                   "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w KQkq - 0 1");
+        for i in 0..960 {
+            let board = Board::new_chess960(i);
+            assert!(board.is_chess960());
+            let fen = board.get_fen();
+            assert_eq!(Board::new_from_fen(&fen).unwrap().get_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn chess960_fen_castling() {
+        let shredder = "1r2k1r1/8/8/8/8/8/8/R3KRR1 w FAgb - 0 1";
+        let board = Board::new_from_fen(shredder).unwrap();
+        assert!(board.is_chess960());
+        assert_eq!(board.get_fen(), "1r2k1r1/8/8/8/8/8/8/R3KRR1 w FQkq - 0 1");
+
+        let x_fen = Board::new_from_fen("1r2k1r1/8/8/8/8/8/8/R3KRR1 w FQkq - 0 1").unwrap();
+        assert_eq!(x_fen.get_fen(), board.get_fen());
+        assert_eq!(x_fen.generate_moves().vec(), board.generate_moves().vec());
+
+        assert!(Board::new_from_fen("1r2k1r1/8/8/8/8/8/8/R3KRR1 w C - 0 1").is_err());
+    }
+
+    #[test]
+    fn chess960_perft() {
+        let positions = [
+            ("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", [21, 528, 12189]),
+            ("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", [21, 807, 18002]),
+            ("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", [20, 479, 10471]),
+            ("qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9", [22, 593, 13440]),
+            ("1nbbnrkr/p1p1ppp1/3p4/1p3P1p/3Pq2P/8/PPP1P1P1/QNBBNRKR w HFhf - 0 9", [28, 1120, 31058])];
+        for &(fen, ref nodes) in positions.iter() {
+            let board = Board::new_from_fen(fen).unwrap();
+            for (depth, count) in nodes.iter().enumerate() {
+                assert_eq!(perft::perft(&board, depth as u16 + 1), *count);
+            }
+        }
+    }
+
+    #[test]
+    fn chess960_castle_apply() {
+        // King and rook swap squares when castling king-side
+        let mut board = Board::new_from_fen("4k3/8/8/8/8/8/8/5KR1 w G - 0 1").unwrap();
+        assert!(board.apply_uci_move("f1g1"));
+        assert_eq!(board.get_fen(), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+        board.undo_move();
+        assert_eq!(board.get_fen(), "4k3/8/8/8/8/8/8/5KR1 w K - 0 1");
+
+        // An enemy rook behind the castling rook prevents castling
+        let board = Board::new_from_fen("4k3/8/8/8/8/8/8/rRK5 w B - 0 1").unwrap();
+        assert!(board.generate_moves().iter().all(|m| !m.is_castle()));
+    }
+
+    #[test]
+    fn insufficient_material() {
+        let insufficient = ["8/8/4k3/8/8/4K3/8/8 w - - 0 1",
+                            "8/8/4k3/8/8/4K3/8/5B2 w - - 0 1",
+                            "8/8/4k3/8/8/4K3/8/6N1 b - - 0 1",
+                            "8/8/2b1k3/8/8/4K3/8/5B2 w - - 0 1"];
+        let sufficient = ["8/8/3bk3/8/8/4K3/8/5B2 w - - 0 1",
+                          "8/8/4k3/8/8/4K3/8/5BN1 w - - 0 1",
+                          "8/8/4k3/8/8/4K3/7P/8 w - - 0 1",
+                          "8/8/4k3/8/8/4K3/8/7R w - - 0 1"];
+        for fen in insufficient.iter() {
+            let b = Board::new_from_fen(fen).unwrap();
+            assert!(b.is_insufficient_material());
+            let outcome = b.outcome().unwrap();
+            assert!(outcome.is_draw());
+            assert_eq!(outcome.termination, Termination::InsufficientMaterial);
+        }
+        for fen in sufficient.iter() {
+            let b = Board::new_from_fen(fen).unwrap();
+            assert!(!b.is_insufficient_material());
+            assert!(b.outcome().is_none());
+        }
+    }
+
+    #[test]
+    fn game_outcomes() {
+        let mate = Board::new_from_fen(STANDARD_FEN_MATE_STALEMATE[3]).unwrap();
+        assert_eq!(mate.outcome(), Some(GameOutcome {
+            winner: Some(Player::White),
+            termination: Termination::Checkmate
+        }));
+
+        let stalemate = Board::new_from_fen(STANDARD_FEN_MATE_STALEMATE[2]).unwrap();
+        assert_eq!(stalemate.outcome(), Some(GameOutcome {
+            winner: None,
+            termination: Termination::Stalemate
+        }));
+    }
+
+    #[test]
+    fn static_exchange_evaluation() {
+        let value = |p: PieceType| piece_value(p, false);
+        let see = |fen: &str, uci: &str| {
+            let board = Board::new_from_fen(fen).unwrap();
+            let m = *board.generate_moves().iter().find(|m| m.stringify() == uci).unwrap();
+            assert_eq!(board.see_ge(m, board.see(m)), true);
+            assert_eq!(board.see_ge(m, board.see(m) + 1), false);
+            board.see(m)
+        };
+
+        // Undefended and defended pawns.
+        assert_eq!(see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"), value(PieceType::P));
+        assert_eq!(see("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3e5"),
+                   value(PieceType::P) - value(PieceType::N));
+        // Pawn takes knight and knight takes rook, recaptured by a pawn.
+        assert_eq!(see("4k3/8/2p5/3n4/4P3/8/8/4K3 w - - 0 1", "e4d5"),
+                   value(PieceType::N) - value(PieceType::P));
+        assert_eq!(see("4k3/8/2p5/3r4/8/4N3/8/4K3 w - - 0 1", "e3d5"),
+                   value(PieceType::R) - value(PieceType::N));
+        assert_eq!(see("4k3/8/2p5/1p1n4/4P3/8/8/1Q2K3 w - - 0 1", "e4d5"),
+                   value(PieceType::N) - value(PieceType::P));
+        // The second rook x-rays through the first.
+        assert_eq!(see("3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5"), value(PieceType::P));
+        assert_eq!(see("3rk3/8/8/3p4/8/8/3R4/4K3 w - - 0 1", "d2d5"),
+                   value(PieceType::P) - value(PieceType::R));
+        // The knight on f6 is pinned, and cannot recapture.
+        assert_eq!(see("7k/8/5n2/3p4/8/2B5/8/3RK3 w - - 0 1", "d1d5"), value(PieceType::P));
+        // The king cannot recapture a defended piece.
+        assert_eq!(see("8/8/8/4k3/3p4/8/3R4/3RK3 w - - 0 1", "d2d4"), value(PieceType::P));
+        assert_eq!(see("8/8/8/4k3/3p4/8/3R4/4K3 w - - 0 1", "d2d4"),
+                   value(PieceType::P) - value(PieceType::R));
+        // En passant, promotions and recaptures that promote.
+        assert_eq!(see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), value(PieceType::P));
+        assert_eq!(see("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7a8q"),
+                   value(PieceType::R) + value(PieceType::Q) - value(PieceType::P));
+        assert_eq!(see("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q"), -value(PieceType::P));
+        assert_eq!(see("4k3/8/8/8/8/8/p7/1N2K3 b - - 0 1", "a2b1q"),
+                   value(PieceType::N) + value(PieceType::Q) - value(PieceType::P));
+        assert_eq!(see("4k3/8/8/8/8/8/p1p5/1N2K3 w - - 0 1", "b1a3"), 0);
+        assert_eq!(see("4k3/8/8/8/2n5/8/1P6/4K3 b - - 0 1", "c4b2"), value(PieceType::P));
+        assert_eq!(see("1n2k3/P7/8/8/8/8/8/4K3 b - - 0 1", "b8c6"), 0);
+        assert_eq!(see("1r2k3/8/8/8/8/8/8/4K3 b - - 0 1", "b8b2"), 0);
+        assert_eq!(see("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1", "e1c1"), 0);
+    }
+
+    #[test]
+    fn pseudo_legal_all_moves() {
+        let mut fens: Vec<&str> = ALL_FENS.clone();
+        fens.extend_from_slice(&[
+            "r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1",
+            "r3k2r/8/8/8/8/8/6p1/R3K2R w KQkq - 0 1",
+            "8/8/8/KPp4r/8/8/8/6k1 w - c6 0 1",
+            "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+            "4k3/8/8/8/1b6/8/8/R3K2R w KQ - 0 1",
+            "4k3/4r3/8/8/8/8/3PP3/4K3 w - - 0 1",
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        ]);
+        for fen in fens.iter() {
+            let board = Board::new_from_fen(fen).unwrap();
+            let legal = board.generate_moves();
+            for raw in 0..=u16::max_value() {
+                let m = BitMove::new(raw);
+                let expected = legal.iter().any(|l| *l == m);
+                assert_eq!(board.pseudo_legal_move(m) && board.legal_move(m), expected,
+                           "{} {} {:016b}", fen, m, raw);
+            }
+            if !board.in_check() {
+                for m in board.generate_pseudolegal_moves().iter() {
+                    assert!(board.pseudo_legal_move(*m), "{} {}", fen, m);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fallible_moves() {
+        let fens: [&str; 4] = [
+            "r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1",
+            "4k3/4r3/8/8/8/8/3PP3/4K3 w - - 0 1",
+            "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        ];
+        for fen in fens.iter().chain(ALL_FENS.iter()) {
+            let board = if fen.contains("HFhf") {
+                Board::new_from_fen_chess960(fen).unwrap()
+            } else {
+                Board::new_from_fen(fen).unwrap()
+            };
+            for m in board.generate_moves().iter() {
+                assert_eq!(board.parse_uci_move(&board.move_to_uci(*m)), Ok(*m), "{} {}", fen, m);
+                let mut applied = board.shallow_clone();
+                assert_eq!(applied.try_apply_move(*m), Ok(()));
+            }
+        }
+
+        let mut board = Board::new_from_fen("4k3/4r3/8/8/8/8/3PP3/4K3 w - - 0 1").unwrap();
+        let parse_err = |board: &Board, uci: &str| board.parse_uci_move(uci).unwrap_err();
+        for uci in ["", "e2", "e2e4qq", "e2e9", "i2e4", "e7e8k", "E2E4"].iter() {
+            assert_eq!(parse_err(&board, uci), MoveParseError::Malformed {uci: uci.to_string()});
+        }
+        assert_eq!(parse_err(&board, "e3e4"), MoveParseError::Illegal(IllegalMove::NoPiece {sq: SQ::E3}));
+        assert_eq!(parse_err(&board, "e7e6"), MoveParseError::Illegal(IllegalMove::WrongSide {sq: SQ::E7}));
+        assert!(board.parse_uci_move("e2e4").is_ok());
+        let pinned = Board::new_from_fen("4k3/8/8/b7/8/8/3P4/4K3 w - - 0 1").unwrap();
+        assert_eq!(parse_err(&pinned, "d2d3"), MoveParseError::Illegal(IllegalMove::LeavesKingInCheck {
+            bit_move: BitMove::make_quiet(SQ::D2, SQ::D3)
+        }));
+        let mut into_check = Board::new_from_fen("4k3/8/8/8/8/8/5r2/4K3 w - - 0 1").unwrap();
+        assert_eq!(into_check.try_apply_move(BitMove::make_quiet(SQ::E1, SQ::E2)),
+                   Err(IllegalMove::LeavesKingInCheck {bit_move: BitMove::make_quiet(SQ::E1, SQ::E2)}));
+        let king_move = BitMove::make_quiet(SQ::E1, SQ::F2);
+        assert!(board.try_apply_move(king_move).is_ok());
+        assert_eq!(board.try_apply_move(BitMove::make_quiet(SQ::E2, SQ::E3)),
+                   Err(IllegalMove::WrongSide {sq: SQ::E2}));
+        assert_eq!(board.try_undo_move(), Ok(()));
+        assert_eq!(board.try_apply_move(BitMove::make_quiet(SQ::D2, SQ::D5)),
+                   Err(IllegalMove::InvalidMove {bit_move: BitMove::make_quiet(SQ::D2, SQ::D5)}));
+        assert_eq!(board.try_apply_move(BitMove::make_quiet(SQ::E1, SQ::E2)),
+                   Err(IllegalMove::InvalidMove {bit_move: BitMove::make_quiet(SQ::E1, SQ::E2)}));
+        assert_eq!(board.try_apply_move(BitMove::make_quiet(SQ::E1, SQ::F1)), Ok(()));
+        assert_eq!(board.try_apply_move(BitMove::make_quiet(SQ::E7, SQ::E2)),
+                   Err(IllegalMove::InvalidMove {bit_move: BitMove::make_quiet(SQ::E7, SQ::E2)}));
+        assert_eq!(board.get_fen(), "4k3/4r3/8/8/8/8/3PP3/5K2 b - - 1 1");
+
+        assert_eq!(board.try_undo_move(), Ok(()));
+        assert_eq!(board.try_undo_move(), Err(UndoMoveError::NoPreviousMove));
+        assert!(board.apply_null_move());
+        assert_eq!(board.try_undo_move(), Err(UndoMoveError::NullMove));
+        board.undo_null_move();
+        assert!(board.apply_uci_move("e1d1"));
+        assert_eq!(board.shallow_clone().try_undo_move(), Err(UndoMoveError::NoPreviousMove));
+    }
+
+    #[test]
+    fn flip_and_mirror() {
+        let same_state = |a: &Board, b: &Board| {
+            assert_eq!(a.get_fen(), b.get_fen());
+            assert_eq!(a.zobrist(), b.zobrist());
+            assert_eq!(a.pawn_key(), b.pawn_key());
+            assert_eq!(a.material_key(), b.material_key());
+            assert_eq!(a.psq(), b.psq());
+            assert_eq!(a.checkers(), b.checkers());
+        };
+        let mut fens: Vec<&str> = ALL_FENS.clone();
+        fens.push("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9");
+        for fen in fens.iter() {
+            let mut board = Board::new_from_fen(fen).unwrap();
+            // Play a move so the flipped board must drop the previous state.
+            if let Some(mov) = board.generate_moves().iter().next() {
+                board.apply_move(*mov);
+            }
+
+            let flipped = board.flip_colors();
+            same_state(&flipped, &Board::new_from_fen(&flipped.get_fen()).unwrap());
+            same_state(&flipped.flip_colors(), &board);
+            assert_eq!(flipped.generate_moves().len(), board.generate_moves().len());
+            assert_eq!(flipped.psq(), -board.psq());
+            assert_eq!(flipped.non_pawn_material(Player::White), board.non_pawn_material(Player::Black));
+
+            if board.state.castling.no_castling() {
+                let mirrored = board.mirror_horizontal();
+                same_state(&mirrored, &Board::new_from_fen(&mirrored.get_fen()).unwrap());
+                same_state(&mirrored.mirror_horizontal(), &board);
+                assert_eq!(mirrored.generate_moves().len(), board.generate_moves().len());
+            }
+        }
+    }
+}
@@ -344,7 +344,7 @@ impl<'a, MP: MVPushable> InnerMoveGen<'a, MP>
     // Generates castling for a single side
     fn castling_side<L: Legality, P: PlayerTrait>(&mut self, side: CastleType) {
         // Make sure we can castle AND the space between the king / rook is clear AND the piece at castling_side is a Rook
-        if !self.board.castle_impeded(side) && self.board.can_castle(P::player(), side)
+        if self.board.can_castle(P::player(), side) && !self.board.castle_impeded(side)
             && self.board.piece_at_sq(self.board.castling_rook_square(side)) == Some(PieceType::R) {
             let king_side: bool = { side == CastleType::KingSide };
 
@@ -359,32 +359,25 @@ impl<'a, MP: MVPushable> InnerMoveGen<'a, MP>
             );
 
             let enemies: BitBoard = self.them_occ;
-            let direction: fn(SQ) -> SQ = if king_side {
-                |x: SQ| x - SQ(1)
-            } else {
-                |x: SQ| x + SQ(1)
-            };
 
-            let mut s: SQ = k_to;
-            let mut can_castle: bool = true;
+            // The castling rook leaves its square, so it cannot shield the king. This
+            // matters in Chess960, where an enemy slider may be behind the rook.
+            let occ: BitBoard = self.occ ^ r_from.to_bb();
+            let mut king_path: BitBoard = between_bb(ksq, k_to) | k_to.to_bb();
 
             // Loop through all the squares the king goes through
             // If any enemies attack that square, cannot castle
-            'outer: while s != ksq {
-                let attackers: BitBoard = self.board.attackers_to(s, self.occ) & enemies;
-                if attackers.is_not_empty() {
-                    can_castle = false;
-                    break 'outer;
+            while let Some(s) = king_path.pop_some_lsb() {
+                if (self.board.attackers_to(s, occ) & enemies).is_not_empty() {
+                    return;
                 }
-                s = direction(s);
-            }
-            if can_castle {
-                self.check_and_add::<L>(BitMove::init(PreMoveInfo {
-                    src: ksq,
-                    dst: r_from,
-                    flags: MoveFlag::Castle { king_side: king_side },
-                }));
             }
+
+            self.check_and_add::<L>(BitMove::init(PreMoveInfo {
+                src: ksq,
+                dst: r_from,
+                flags: MoveFlag::Castle { king_side: king_side },
+            }));
         }
     }
 
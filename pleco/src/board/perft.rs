@@ -1,8 +1,20 @@
 //! perft, or Performance Test, Move Path Enumeration, tests the correctness of move-generation.
 //!
 //! Use these functions on a `Board` to test that the correct amount of leaf nodes are created.
+//!
+//! Besides the single-threaded `perft`, `perft_parallel` splits the work at the root across
+//! threads, `perft_hashed` reuses the counts of transposed positions through a `PerftTable`, and
+//! `perft_divide` breaks the count down by each root move.
+//!
+//! A perft suite, listing the expected counts of many positions, can be checked with
+//! `check_perft_suite`.
+
+use std::{fmt, mem};
+
+use rayon;
 
-use super::{Board,MoveList};
+use super::{Board,MoveList,FenBuildError};
+use core::piece_move::BitMove;
 
 /// Holds all information about the number of nodes counted.
 pub struct PerftNodes {
@@ -47,6 +59,341 @@ pub fn perft_all(board: &Board, depth: u16) -> PerftNodes {
     perft
 }
 
+/// Returns the number of leaf nodes under each legal move of the board, searched to a certain
+/// depth. This is useful for finding where the move generation differs from that of another
+/// program.
+///
+/// # Examples
+///
+/// ```
+/// use pleco::Board;
+/// use pleco::board::perft::perft_divide;
+///
+/// let divide = perft_divide(&Board::default(), 3);
+/// assert_eq!(divide.len(), 20);
+/// assert_eq!(divide.iter().map(|&(_, nodes)| nodes).sum::<u64>(), 8902);
+/// ```
+pub fn perft_divide(board: &Board, depth: u16) -> Vec<(BitMove, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+    let mut pos = board.shallow_clone();
+    pos.generate_moves().iter()
+        .map(|mov| {
+            pos.apply_move(*mov);
+            let nodes = perft(&pos, depth - 1);
+            pos.undo_move();
+            (*mov, nodes)
+        })
+        .collect()
+}
+
+/// Returns the number of leaf nodes from generating moves to a certain depth, splitting the
+/// moves at the root across threads.
+///
+/// # Examples
+///
+/// ```
+/// use pleco::Board;
+/// use pleco::board::perft::perft_parallel;
+///
+/// assert_eq!(perft_parallel(&Board::default(), 4), 197_281);
+/// ```
+pub fn perft_parallel(board: &Board, depth: u16) -> u64 {
+    if depth <= 1 {
+        return perft(board, depth);
+    }
+    let moves: MoveList = board.generate_moves();
+    let mut pos = board.shallow_clone();
+    parallel_perft_task(&moves, &mut pos, depth)
+}
+
+fn parallel_perft_task(slice: &[BitMove], board: &mut Board, depth: u16) -> u64 {
+    if slice.len() <= 1 {
+        let mut count: u64 = 0;
+        for mov in slice {
+            board.apply_move(*mov);
+            count += inner_perft(board, depth - 1);
+            board.undo_move();
+        }
+        count
+    } else {
+        let mid_point = slice.len() / 2;
+        let (left, right) = slice.split_at(mid_point);
+        let mut left_clone = board.parallel_clone();
+
+        let (left_count, right_count) = rayon::join(
+            || parallel_perft_task(left, &mut left_clone, depth),
+            || parallel_perft_task(right, board, depth),
+        );
+
+        left_count + right_count
+    }
+}
+
+/// A table of perft results, keyed by the zobrist key of a position and the depth it was
+/// counted to.
+///
+/// Entries are always replaced, and a result is only reused if both its key and depth match.
+pub struct PerftTable {
+    entries: Vec<PerftEntry>,
+}
+
+#[derive(Copy, Clone, Default)]
+struct PerftEntry {
+    key: u64,
+    nodes: u64,
+    depth: u16,
+}
+
+impl PerftTable {
+    /// Creates a `PerftTable` of at most the given size in megabytes, with the number of
+    /// entries rounded down to a power of two.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mb_size` is zero.
+    pub fn new(mb_size: usize) -> PerftTable {
+        assert!(mb_size > 0);
+        let max_entries: usize = mb_size * 1024 * 1024 / mem::size_of::<PerftEntry>();
+        let num_entries: usize = 1 << (63 - (max_entries as u64).leading_zeros());
+        PerftTable {
+            entries: vec![PerftEntry::default(); num_entries],
+        }
+    }
+
+    /// Returns the number of entries in the table.
+    pub fn num_entries(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Removes all entries from the table.
+    pub fn clear(&mut self) {
+        for entry in self.entries.iter_mut() {
+            *entry = PerftEntry::default();
+        }
+    }
+
+    fn index(&self, key: u64, depth: u16) -> usize {
+        // Spreads the depth across the key, so a position has a different entry at each depth
+        let mixed: u64 = key ^ (depth as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        (mixed as usize) & (self.entries.len() - 1)
+    }
+
+    fn probe(&self, key: u64, depth: u16) -> Option<u64> {
+        let entry: &PerftEntry = &self.entries[self.index(key, depth)];
+        if entry.key == key && entry.depth == depth {
+            Some(entry.nodes)
+        } else {
+            None
+        }
+    }
+
+    fn store(&mut self, key: u64, depth: u16, nodes: u64) {
+        let index: usize = self.index(key, depth);
+        self.entries[index] = PerftEntry {key, nodes, depth};
+    }
+}
+
+/// Returns the number of leaf nodes from generating moves to a certain depth, reusing the
+/// counts of positions already found in the `PerftTable`.
+///
+/// As positions are only identified by their zobrist key, a collision between two positions
+/// can give a wrong count, though this is very unlikely.
+///
+/// # Examples
+///
+/// ```
+/// use pleco::Board;
+/// use pleco::board::perft::{perft_hashed, PerftTable};
+///
+/// let mut table = PerftTable::new(16);
+/// assert_eq!(perft_hashed(&Board::default(), 5, &mut table), 4_865_609);
+/// ```
+pub fn perft_hashed(board: &Board, depth: u16, table: &mut PerftTable) -> u64 {
+    if depth == 0 {
+        1
+    } else {
+        let mut pos = board.shallow_clone();
+        inner_perft_hashed(&mut pos, depth, table)
+    }
+}
+
+fn inner_perft_hashed(board: &mut Board, depth: u16, table: &mut PerftTable) -> u64 {
+    let moves: MoveList = board.generate_moves();
+
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let key: u64 = board.zobrist();
+    if let Some(nodes) = table.probe(key, depth) {
+        return nodes;
+    }
+
+    let mut count: u64 = 0;
+
+    for mov in moves {
+        board.apply_move(mov);
+        count += inner_perft_hashed(board, depth - 1, table);
+        board.undo_move();
+    }
+
+    table.store(key, depth, count);
+    count
+}
+
+/// Represents possible Errors encountered while reading a perft suite.
+pub enum PerftSuiteError {
+    /// A line could not be parsed.
+    Unreadable {line: usize, reason: String},
+    /// The position of a line is not a valid FEN.
+    Fen {line: usize, err: FenBuildError},
+}
+
+impl fmt::Debug for PerftSuiteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PerftSuiteError::Unreadable{line, ref reason} => writeln!(f, "unreadable perft suite line {}: {}", line, reason),
+            PerftSuiteError::Fen{line, ref err} => write!(f, "invalid position on perft suite line {}, {:?}", line, err),
+        }
+    }
+}
+
+/// A position of a perft suite, along with the expected number of leaf nodes at each depth.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PerftPosition {
+    /// The FEN of the position.
+    pub fen: String,
+    /// Pairs of a depth and the number of leaf nodes at that depth.
+    pub depths: Vec<(u16, u64)>,
+}
+
+/// Describes the first count of a perft suite that differed from the expected count.
+pub struct PerftMismatch {
+    /// The FEN of the position.
+    pub fen: String,
+    /// The depth the position was counted to.
+    pub depth: u16,
+    /// The number of leaf nodes given by the suite.
+    pub expected: u64,
+    /// The number of leaf nodes counted.
+    pub found: u64,
+    /// The number of leaf nodes under each legal move of the position, at the same depth.
+    pub divide: Vec<(BitMove, u64)>,
+}
+
+impl fmt::Debug for PerftMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "perft mismatch at depth {} of {}", self.depth, self.fen)?;
+        writeln!(f, "expected {} nodes, found {}", self.expected, self.found)?;
+        for &(mov, nodes) in self.divide.iter() {
+            writeln!(f, "{}: {}", mov, nodes)?;
+        }
+        Ok(())
+    }
+}
+
+impl PerftPosition {
+    /// Parses a single line of a perft suite, in the form `fen ;D1 20 ;D2 400 ...`.
+    ///
+    /// The FEN may leave out the half-move and full-move counters, as in an EPD line.
+    pub fn parse(line: &str) -> Result<PerftPosition, PerftSuiteError> {
+        Self::parse_line(line, 1)
+    }
+
+    fn parse_line(line: &str, line_num: usize) -> Result<PerftPosition, PerftSuiteError> {
+        let unreadable = |reason: String| PerftSuiteError::Unreadable {line: line_num, reason};
+
+        let mut sections = line.split(';');
+        let mut fen: String = sections.next().unwrap_or("").trim().to_string();
+        if fen.split_whitespace().count() == 4 {
+            fen.push_str(" 0 1");
+        }
+        if let Err(err) = Board::new_from_fen(&fen) {
+            return Err(PerftSuiteError::Fen {line: line_num, err});
+        }
+
+        let mut depths: Vec<(u16, u64)> = Vec::new();
+        for section in sections {
+            let section: &str = section.trim();
+            if section.is_empty() {
+                continue;
+            }
+            let mut parts = section.split_whitespace();
+            let depth: Option<u16> = parts.next()
+                .and_then(|d| if d.starts_with('D') { d[1..].parse().ok() } else { None });
+            let nodes: Option<u64> = parts.next().and_then(|n| n.parse().ok());
+            match (depth, nodes, parts.next()) {
+                (Some(depth), Some(nodes), None) => depths.push((depth, nodes)),
+                _ => return Err(unreadable(format!("expected a depth and node count, found \"{}\"", section))),
+            }
+        }
+        if depths.is_empty() {
+            return Err(unreadable("no node counts given".to_string()));
+        }
+        Ok(PerftPosition {fen, depths})
+    }
+
+    /// Counts the leaf nodes of the position at each depth up to `max_depth` with `perft_all`,
+    /// returning the first count that differs from the expected count.
+    pub fn check(&self, max_depth: u16) -> Result<(), PerftMismatch> {
+        let board = Board::new_from_fen(&self.fen).unwrap();
+        for &(depth, expected) in self.depths.iter() {
+            if depth > max_depth {
+                continue;
+            }
+            let found: u64 = perft_all(&board, depth).nodes;
+            if found != expected {
+                return Err(PerftMismatch {
+                    fen: self.fen.clone(),
+                    depth,
+                    expected,
+                    found,
+                    divide: perft_divide(&board, depth),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a perft suite, with one position per line in the form `fen ;D1 20 ;D2 400 ...`.
+///
+/// Blank lines, and lines starting with `#`, are skipped.
+pub fn parse_perft_suite(suite: &str) -> Result<Vec<PerftPosition>, PerftSuiteError> {
+    suite.lines()
+        .enumerate()
+        .filter(|&(_, line)| !line.trim().is_empty() && !line.trim().starts_with('#'))
+        .map(|(i, line)| PerftPosition::parse_line(line, i + 1))
+        .collect()
+}
+
+/// Checks every position of a perft suite at each depth up to `max_depth`, returning the
+/// first count that differs from the suite. Otherwise, returns the number of positions checked.
+///
+/// # Examples
+///
+/// ```
+/// use pleco::board::perft::{parse_perft_suite, check_perft_suite};
+///
+/// let suite = parse_perft_suite("\
+///     rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - ;D1 20 ;D2 400 ;D3 8902\n\
+///     4k3/8/8/8/8/8/8/4K2R w K - 0 1 ;D1 15 ;D2 66 ;D3 1197").unwrap();
+///
+/// assert_eq!(check_perft_suite(&suite, 3).unwrap(), 2);
+///
+/// let wrong = parse_perft_suite("4k3/8/8/8/8/8/8/4K2R w K - ;D1 15 ;D2 65").unwrap();
+/// let mismatch = check_perft_suite(&wrong, 3).unwrap_err();
+/// assert_eq!((mismatch.depth, mismatch.expected, mismatch.found), (2, 65, 66));
+/// assert_eq!(mismatch.divide.len(), 15);
+/// ```
+pub fn check_perft_suite(suite: &[PerftPosition], max_depth: u16) -> Result<usize, PerftMismatch> {
+    for position in suite.iter() {
+        position.check(max_depth)?;
+    }
+    Ok(suite.len())
+}
 
 fn inner_perft(board: &mut Board, depth: u16) -> u64 {
     let moves: MoveList = board.generate_moves();
@@ -119,6 +466,61 @@ mod tests {
                     4_865_609, 82_719, 258, 0, 27351, 347);
     }
 
+    #[test]
+    fn perft_modes() {
+        let fens: [&str; 3] = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        ];
+        let mut table = PerftTable::new(4);
+        for fen in fens.iter() {
+            let b: Board = Board::new_from_fen(fen).unwrap();
+            for depth in 0..4 {
+                let nodes = perft(&b, depth);
+                assert_eq!(perft_parallel(&b, depth), nodes);
+                assert_eq!(perft_hashed(&b, depth, &mut table), nodes);
+                // The second count reads from the table.
+                assert_eq!(perft_hashed(&b, depth, &mut table), nodes);
+
+                let divide = perft_divide(&b, depth);
+                if depth > 0 {
+                    assert_eq!(divide.len(), b.generate_moves().len());
+                    assert_eq!(divide.iter().map(|&(_, n)| n).sum::<u64>(), nodes);
+                }
+            }
+        }
+        assert!(table.num_entries().is_power_of_two());
+    }
+
+    #[test]
+    fn perft_suite_parse() {
+        let suite = parse_perft_suite("# comment\n\n\
+            4k3/8/8/8/8/8/8/4K2R w K - ;D1 15 ;D2 66\n\
+            r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 3 10;D1 26;").unwrap();
+        assert_eq!(suite.len(), 2);
+        assert_eq!(suite[0].fen, "4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+        assert_eq!(suite[0].depths, vec![(1, 15), (2, 66)]);
+        assert_eq!(suite[1].fen, "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 3 10");
+        assert_eq!(suite[1].depths, vec![(1, 26)]);
+
+        match parse_perft_suite("4k3/8/8/8/8/8/8/4K2R w K - ;D1 15\n4k3/8/8/8/8/8/8/4K2R w K - ;D2") {
+            Err(PerftSuiteError::Unreadable {line: 2, ..}) => {},
+            Err(e) => panic!("{:?}", e),
+            Ok(_) => panic!(),
+        }
+        match parse_perft_suite("4k3/8/8/8/8/8/8/4K2R w K -") {
+            Err(PerftSuiteError::Unreadable {line: 1, ..}) => {},
+            Err(e) => panic!("{:?}", e),
+            Ok(_) => panic!(),
+        }
+        match PerftPosition::parse("4k3/8/8/8/8/8/8/4K2R x K - ;D1 15") {
+            Err(PerftSuiteError::Fen {line: 1, ..}) => {},
+            Err(e) => panic!("{:?}", e),
+            Ok(_) => panic!(),
+        }
+    }
+
     fn check_perft(perft: PerftNodes,
                    nodes: u64,      captures: u64, en_passant: u64,
                    promotions: u64, checks: u64,   checkmates: u64) {
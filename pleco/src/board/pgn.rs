@@ -1,9 +1,37 @@
-//! Module for generating chess boards from PGN notation.
+//! Module for reading and writing chess games in Portable Game Notation (PGN).
+//!
+//! A game is parsed with [`PGN::parse`], which reads the tag pairs and the movetext,
+//! including comments, Numeric Annotation Glyphs (NAGs) and nested variations. Every move,
+//! variations included, is replayed on a [`Board`], so a game containing an illegal move is
+//! rejected with the line and column of the offending move. Games are written back out in
+//! export format with `PGN::to_string`, and whole databases are read one game at a time
+//! with a `PgnReader`.
+//!
+//! # Examples
+//!
+//! ```
+//! use pleco::board::pgn::{PGN, GameResult};
+//!
+//! let pgn = PGN::parse("[White \"Morphy\"]\n\n1. e4 e5 2. Nf3 {A classic.} d6 (2... Nc6) 1-0").unwrap();
+//! assert_eq!(pgn.tags().white(), "Morphy");
+//! assert_eq!(pgn.moves().len(), 4);
+//! assert_eq!(pgn.moves()[2].comment, Some("A classic.".to_string()));
+//! assert_eq!(pgn.moves()[3].variations.len(), 1);
+//! assert_eq!(pgn.result(), GameResult::WhiteWins);
+//! ```
+//!
+//! [`PGN::parse`]: struct.PGN.html#method.parse
+//! [`Board`]: ../struct.Board.html
 
-//use super::Board;
+use super::{Board, FenBuildError};
+use super::fen::OPENING_POS_FEN;
+use core::piece_move::BitMove;
 use core::sq::SQ;
-use core::{PieceType, File, Rank};
-use std::fmt;
+use core::{PieceType, File, Rank, ALL_FILES, ALL_RANKS};
+use core::masks::{FILE_DISPLAYS, RANK_DISPLAYS};
+use super::san::SanError;
+use std::{fmt, io, iter, str};
+use std::io::BufRead;
 
 
 //[Event "F/S Return Match"]
@@ -24,6 +52,7 @@ use std::fmt;
 
 // https://www.chessclub.com/user/help/PGN-spec
 
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum GameResult {
     WhiteWins,
     BlackWins,
@@ -31,6 +60,31 @@ pub enum GameResult {
     Other
 }
 
+impl GameResult {
+    /// Parses a game termination marker, such as `1-0` or `1/2-1/2`. The marker `*` is
+    /// parsed as `GameResult::Other`.
+    pub fn parse(input: &str) -> Option<GameResult> {
+        match input.trim().trim_matches('"') {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Other),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GameResult::WhiteWins => f.pad("1-0"),
+            GameResult::BlackWins => f.pad("0-1"),
+            GameResult::Draw => f.pad("1/2-1/2"),
+            GameResult::Other => f.pad("*"),
+        }
+    }
+}
+
 pub enum ChessDate {
     Unknown,
     Year(u16),
@@ -81,7 +135,7 @@ impl ChessDate {
 
     pub fn to_string(&self) -> String {
         match *self {
-            ChessDate::Unknown => {("\"??\"").to_owned()},
+            ChessDate::Unknown => {("\"????.??.??\"").to_owned()},
             ChessDate::Year(y) => {
                 let mut s = ("\"").to_owned();
                 s.push_str(y.to_string().as_ref());
@@ -92,7 +146,7 @@ impl ChessDate {
                 let mut s = ("\"").to_owned();
                 s.push_str(y.to_string().as_ref());
                 s.push('.');
-                s.push_str(m.to_string().as_ref());
+                s.push_str(format!("{:02}", m).as_ref());
                 s.push_str(".??\"");
                 s
             },
@@ -100,9 +154,9 @@ impl ChessDate {
                 let mut s = ("\"").to_owned();
                 s.push_str(y.to_string().as_ref());
                 s.push('.');
-                s.push_str(m.to_string().as_ref());
+                s.push_str(format!("{:02}", m).as_ref());
                 s.push('.');
-                s.push_str(d.to_string().as_ref());
+                s.push_str(format!("{:02}", d).as_ref());
                 s.push('"');
                 s
             },
@@ -137,6 +191,9 @@ impl ChessRound {
     }
 
     pub fn to_string(&self) -> String {
+        if self.rounds.is_empty() {
+            return "\"?\"".to_string();
+        }
         let mut s = "\"".to_string();
         for (i, x) in self.rounds.iter().enumerate() {
             s.push_str(x.to_string().as_ref());
@@ -157,6 +214,7 @@ pub struct PGNTags {
     white: String,
     black: String,
     result: String,
+    other: Vec<(String, String)>,
 }
 
 impl fmt::Display for PGNTags {
@@ -169,23 +227,98 @@ impl fmt::Display for PGNTags {
 impl PGNTags {
     pub fn to_string(&self) -> String {
         let mut s: String = "[Event ".to_owned();
-        s.push_str(self.event.as_ref());
+        s.push_str(&or_unknown(&self.event, "?"));
         s.push_str("]\n[Site ");
-        s.push_str(self.site.as_ref());
+        s.push_str(&or_unknown(&self.site, "?"));
         s.push_str("]\n[Date ");
         s.push_str(self.date.to_string().as_ref());
         s.push_str("]\n[Round ");
         s.push_str(self.round.to_string().as_ref());
         s.push_str("]\n[White ");
-        s.push_str(self.white.as_ref());
+        s.push_str(&or_unknown(&self.white, "?"));
         s.push_str("]\n[Black ");
-        s.push_str(self.black.as_ref());
+        s.push_str(&or_unknown(&self.black, "?"));
         s.push_str("]\n[Result ");
-        s.push_str(self.result.as_ref());
+        s.push_str(&or_unknown(&self.result, "*"));
         s.push_str("]\n");
+        for &(ref tag, ref data) in self.other.iter() {
+            s.push('[');
+            s.push_str(tag);
+            s.push(' ');
+            s.push_str(&quote(data));
+            s.push_str("]\n");
+        }
         s
     }
 
+    /// Returns the name of the tournament or match event.
+    pub fn event(&self) -> &str {
+        &self.event
+    }
+
+    /// Returns the location of the event.
+    pub fn site(&self) -> &str {
+        &self.site
+    }
+
+    /// Returns the starting date of the game.
+    pub fn date(&self) -> &ChessDate {
+        &self.date
+    }
+
+    /// Returns the playing round of the game.
+    pub fn round(&self) -> &ChessRound {
+        &self.round
+    }
+
+    /// Returns the player of the white pieces.
+    pub fn white(&self) -> &str {
+        &self.white
+    }
+
+    /// Returns the player of the black pieces.
+    pub fn black(&self) -> &str {
+        &self.black
+    }
+
+    /// Returns the result of the game, as written in the `Result` tag.
+    pub fn result(&self) -> &str {
+        &self.result
+    }
+
+    /// Returns the result of the game from the `Result` tag, or `GameResult::Other` if it is
+    /// missing or unreadable.
+    pub fn game_result(&self) -> GameResult {
+        GameResult::parse(self.result()).unwrap_or(GameResult::Other)
+    }
+
+    /// Returns the value of a tag outside of the Seven Tag Roster, such as `FEN` or `WhiteElo`.
+    pub fn tag(&self, tag: &str) -> Option<&str> {
+        self.other.iter()
+            .find(|&&(ref t, _)| t == tag)
+            .map(|&(_, ref data)| data.as_str())
+    }
+
+    /// Returns the `Board` the game starts from. This is the standard starting position, unless
+    /// the game has a `FEN` tag.
+    ///
+    /// The position is parsed as a Chess960 position if the `Variant` tag names Chess960.
+    pub fn starting_board(&self) -> Result<Board, PGNError> {
+        match self.tag("FEN") {
+            Some(fen) => {
+                let chess960 = self.tag("Variant")
+                    .map(|v| v.to_lowercase())
+                    .map_or(false, |v| v.contains("960") || v == "fischerandom");
+                if chess960 {
+                    Board::new_from_fen_chess960(fen).map_err(PGNError::Fen)
+                } else {
+                    Board::new_from_fen(fen).map_err(PGNError::Fen)
+                }
+            },
+            None => Ok(Board::default())
+        }
+    }
+
     pub fn add(mut self, input: &str) -> Result<PGNTags,PGNError> {
         let first_char = input.chars().nth(0).ok_or(PGNError::TagParse)?;
         let last_char = input.chars().last().ok_or(PGNError::TagParse)?;
@@ -197,7 +330,7 @@ impl PGNTags {
             .ok_or(PGNError::TagParse)?;
         let quote_second = r.rfind('"')
             .ok_or(PGNError::TagParse)?;
-        if quote_first >= quote_second - 2 {
+        if quote_first == 0 || quote_first >= quote_second {
             return Err(PGNError::TagParse)
         }
         let in_quote = r[(quote_first)..(quote_second + 1)].to_owned();
@@ -212,17 +345,53 @@ impl PGNTags {
 
     pub fn parse_tag(mut self, tag: &str, data: String) -> Result<PGNTags,PGNError> {
         match tag {
-            "Event" => {self.event = data},
-            "Site" => {self.site = data},
+            "Event" => {self.event = unquote(&data)},
+            "Site" => {self.site = unquote(&data)},
             "Date" => {self.date = ChessDate::parse_chess_date(data.as_ref())},
             "Round" => {self.round = ChessRound::parse_chess_round(data.as_ref())},
-            "White" => {self.white = data},
-            "Black" => {self.black = data},
-            "Result" => {self.result = data},
-            _ => {return Err(PGNError::TagParse)}
+            "White" => {self.white = unquote(&data)},
+            "Black" => {self.black = unquote(&data)},
+            "Result" => {self.result = unquote(&data)},
+            _ => {
+                let data = unquote(&data);
+                match self.other.iter().position(|&(ref t, _)| t == tag) {
+                    Some(i) => self.other[i].1 = data,
+                    None => self.other.push((tag.to_string(), data))
+                }
+            }
         }
         Ok(self)
     }
+
+    /// Sets the value of a tag, quoting it as needed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::board::pgn::PGNTags;
+    ///
+    /// let tags = PGNTags::default()
+    ///     .with_tag("White", "Pleco")
+    ///     .with_tag("WhiteElo", "2800");
+    /// assert_eq!(tags.white(), "Pleco");
+    /// assert_eq!(tags.tag("WhiteElo"), Some("2800"));
+    /// ```
+    pub fn with_tag(self, tag: &str, value: &str) -> PGNTags {
+        self.parse_tag(tag, quote(value)).unwrap()
+    }
+
+    /// Sets the `SetUp` and `FEN` tags to start a game from the given `Board`, unless it is the
+    /// standard starting position. A Chess960 position also sets the `Variant` tag.
+    pub fn with_position(mut self, board: &Board) -> PGNTags {
+        let fen = board.get_fen();
+        self.other.retain(|&(ref t, _)| t != "SetUp" && t != "FEN");
+        if board.is_chess960() {
+            self = self.with_tag("Variant", "Chess960");
+        } else if fen == OPENING_POS_FEN {
+            return self;
+        }
+        self.with_tag("SetUp", "1").with_tag("FEN", &fen)
+    }
 }
 
 impl Default for PGNTags {
@@ -235,11 +404,51 @@ impl Default for PGNTags {
             white: String::new(),
             black: String::new(),
             result: String::new(),
+            other: Vec::new(),
         }
     }
 }
 
+fn or_unknown(data: &str, unknown: &str) -> String {
+    quote(if data.is_empty() { unknown } else { data })
+}
 
+/// Quotes a tag value, escaping any quotes and backslashes inside it.
+fn quote(value: &str) -> String {
+    let mut data = "\"".to_string();
+    data.push_str(&value.replace('\\', "\\\\").replace('"', "\\\""));
+    data.push('"');
+    data
+}
+
+/// Strips the quotes around a tag value, and unescapes any quotes and backslashes inside it.
+fn unquote(data: &str) -> String {
+    let data = if data.len() >= 2 && data.starts_with('"') && data.ends_with('"') {
+        &data[1..data.len() - 1]
+    } else {
+        data
+    };
+    let mut value = String::with_capacity(data.len());
+    let mut chars = data.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some(next) if next == '"' || next == '\\' => value.push(next),
+                Some(next) => {
+                    value.push(c);
+                    value.push(next);
+                },
+                None => value.push(c),
+            }
+        } else {
+            value.push(c);
+        }
+    }
+    value
+}
+
+
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum PGNMoveTag {
     None, // ''
     Good, // '!'
@@ -250,18 +459,83 @@ pub enum PGNMoveTag {
     Doubtful // '?!'
 }
 
+impl PGNMoveTag {
+    /// Parses a move suffix annotation, such as `!?`.
+    pub fn parse(input: &str) -> Option<PGNMoveTag> {
+        match input {
+            "" => Some(PGNMoveTag::None),
+            "!" => Some(PGNMoveTag::Good),
+            "!!" => Some(PGNMoveTag::Excellent),
+            "?" => Some(PGNMoveTag::Bad),
+            "??" => Some(PGNMoveTag::Blunder),
+            "!?" => Some(PGNMoveTag::Interesting),
+            "?!" => Some(PGNMoveTag::Doubtful),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for PGNMoveTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PGNMoveTag::None => f.pad(""),
+            PGNMoveTag::Good => f.pad("!"),
+            PGNMoveTag::Excellent => f.pad("!!"),
+            PGNMoveTag::Bad => f.pad("?"),
+            PGNMoveTag::Blunder => f.pad("??"),
+            PGNMoveTag::Interesting => f.pad("!?"),
+            PGNMoveTag::Doubtful => f.pad("?!"),
+        }
+    }
+}
+
 // Check = +
 // Checkmate = #
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum CheckType {
     Check,
     CheckMate
 }
 
+impl fmt::Display for CheckType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CheckType::Check => f.pad("+"),
+            CheckType::CheckMate => f.pad("#"),
+        }
+    }
+}
+
 // (File) OR (Rank) OR (Square)
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct PGNMoveSpecifier {
-    rank: Option<Rank>,
-    file: Option<File>,
-    square: Option<SQ>
+    pub rank: Option<Rank>,
+    pub file: Option<File>,
+    pub square: Option<SQ>
+}
+
+impl PGNMoveSpecifier {
+    /// Returns if a square matches the specifier.
+    pub fn matches(&self, sq: SQ) -> bool {
+        self.rank.map_or(true, |r| sq.rank() == r)
+            && self.file.map_or(true, |f| sq.file() == f)
+            && self.square.map_or(true, |s| sq == s)
+    }
+}
+
+impl fmt::Display for PGNMoveSpecifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(sq) = self.square {
+            return write!(f, "{}", sq);
+        }
+        if let Some(file) = self.file {
+            write!(f, "{}", FILE_DISPLAYS[file as usize])?;
+        }
+        if let Some(rank) = self.rank {
+            write!(f, "{}", RANK_DISPLAYS[rank as usize])?;
+        }
+        Ok(())
+    }
 }
 
 
@@ -270,72 +544,941 @@ pub struct PGNMoveSpecifier {
 // (specifier) => rank or file or square if needed
 // (capture) => x if capture
 //
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct PGNRegMove {
-    piece: Option<PieceType>,
-    specifier: Option<PGNMoveSpecifier>,
-    dest: SQ,
-    promo: Option<PieceType>,
-    capture: bool,
+    pub piece: Option<PieceType>,
+    pub specifier: Option<PGNMoveSpecifier>,
+    pub dest: SQ,
+    pub promo: Option<PieceType>,
+    pub capture: bool,
+}
+
+impl fmt::Display for PGNRegMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(piece) = self.piece {
+            write!(f, "{}", piece.char_upper())?;
+        }
+        if let Some(ref spec) = self.specifier {
+            write!(f, "{}", spec)?;
+        }
+        if self.capture {
+            write!(f, "x")?;
+        }
+        write!(f, "{}", self.dest)?;
+        if let Some(promo) = self.promo {
+            write!(f, "={}", promo.char_upper())?;
+        }
+        Ok(())
+    }
 }
 
 //
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum PGNMoveType {
     KingSideCastle, // O-O
     QueenSideCastle, // O-O-O
     Reg(PGNRegMove)
 }
 
+impl PGNMoveType {
+    /// Parses a single move in Standard Algebraic Notation, without any check or annotation
+    /// suffixes.
+    ///
+    /// The parsing is lenient: castling may be written with either `O` or `0`, promotions with or
+    /// without the `=`, and capture marks are optional.
+    pub fn parse(input: &str) -> Result<PGNMoveType, SanError> {
+        let unreadable = || SanError::Unreadable {san: input.to_string()};
+        match input {
+            "O-O" | "0-0" | "o-o" => return Ok(PGNMoveType::KingSideCastle),
+            "O-O-O" | "0-0-0" | "o-o-o" => return Ok(PGNMoveType::QueenSideCastle),
+            "" => return Err(SanError::Empty),
+            _ => {}
+        }
+
+        // Capture marks and dashes carry no information needed to find the move.
+        let mut chars: Vec<char> = input.chars()
+            .filter(|c| *c != 'x' && *c != ':' && *c != '-')
+            .collect();
+
+        let piece: Option<PieceType> = match chars.first() {
+            Some(&'K') => Some(PieceType::K),
+            Some(&'Q') => Some(PieceType::Q),
+            Some(&'R') => Some(PieceType::R),
+            Some(&'B') => Some(PieceType::B),
+            Some(&'N') => Some(PieceType::N),
+            _ => None,
+        };
+        if piece.is_some() || chars.first() == Some(&'P') {
+            chars.remove(0);
+        }
+
+        let mut promo: Option<PieceType> = None;
+        if piece.is_none() && chars.len() > 2 {
+            let last: char = *chars.last().unwrap();
+            promo = match last.to_ascii_uppercase() {
+                'Q' => Some(PieceType::Q),
+                'R' => Some(PieceType::R),
+                'B' => Some(PieceType::B),
+                'N' => Some(PieceType::N),
+                _ => None,
+            };
+            if promo.is_some() {
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+            }
+        }
+
+        if chars.len() < 2 {
+            return Err(unreadable());
+        }
+        let dest_rank: char = chars.pop().unwrap();
+        let dest_file: char = chars.pop().unwrap();
+        let dest: SQ = SQ::make(parse_file(dest_file).ok_or_else(&unreadable)?,
+                                parse_rank(dest_rank).ok_or_else(&unreadable)?);
+
+        let specifier: Option<PGNMoveSpecifier> = match chars.len() {
+            0 => None,
+            1 => {
+                let c = chars[0];
+                if let Some(file) = parse_file(c) {
+                    Some(PGNMoveSpecifier {rank: None, file: Some(file), square: None})
+                } else if let Some(rank) = parse_rank(c) {
+                    Some(PGNMoveSpecifier {rank: Some(rank), file: None, square: None})
+                } else {
+                    return Err(unreadable());
+                }
+            }
+            2 => {
+                let file = parse_file(chars[0]).ok_or_else(&unreadable)?;
+                let rank = parse_rank(chars[1]).ok_or_else(&unreadable)?;
+                Some(PGNMoveSpecifier {rank: None, file: None, square: Some(SQ::make(file, rank))})
+            }
+            _ => return Err(unreadable()),
+        };
+
+        Ok(PGNMoveType::Reg(PGNRegMove {
+            piece,
+            specifier,
+            dest,
+            promo,
+            capture: input.contains('x') || input.contains(':'),
+        }))
+    }
+}
+
+impl fmt::Display for PGNMoveType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PGNMoveType::KingSideCastle => f.pad("O-O"),
+            PGNMoveType::QueenSideCastle => f.pad("O-O-O"),
+            PGNMoveType::Reg(ref reg) => write!(f, "{}", reg),
+        }
+    }
+}
+
+fn parse_file(c: char) -> Option<File> {
+    match c {
+        'a'...'h' => Some(ALL_FILES[(c as u8 - b'a') as usize]),
+        _ => None
+    }
+}
+
+fn parse_rank(c: char) -> Option<Rank> {
+    match c {
+        '1'...'8' => Some(ALL_RANKS[(c as u8 - b'1') as usize]),
+        _ => None
+    }
+}
+
 // (move)(check ?)(tag)
+/// A single move of the movetext, along with the annotations following it.
+#[derive(Clone, PartialEq, Debug)]
 pub struct PGNMove {
-    move_type: PGNMoveType,
-    check: Option<CheckType>,
-    tag: PGNMoveTag
+    pub move_type: PGNMoveType,
+    pub check: Option<CheckType>,
+    pub tag: PGNMoveTag,
+    /// Numeric Annotation Glyphs, e.g. `$14`.
+    pub nags: Vec<u8>,
+    /// The comment following the move.
+    pub comment: Option<String>,
+    /// Alternatives to this move, each starting from the position before it.
+    pub variations: Vec<PGNVariation>,
+    /// The move as played on the `Board`. This is a null move unless the `PGNMove` was
+    /// parsed as part of a `PGN`.
+    pub bit_move: BitMove,
 }
 
 impl PGNMove {
-    pub fn parse(_input: &str) -> Result<PGNMove, PGNError> {
+    /// Parses a single move, including any check and suffix annotations, such as `Nxe5+!?`.
+    pub fn parse(input: &str) -> Result<PGNMove, PGNError> {
+        PGNMove::parse_san(input).map_err(PGNError::San)
+    }
+
+    /// Creates a `PGNMove` from a move played on a `Board`.
+    ///
+    /// # Safety
+    ///
+    /// Assumes the move is legal for the board.
+    pub fn new(board: &Board, bit_move: BitMove) -> PGNMove {
+        let mut pgn_move = PGNMove::parse_san(&board.move_to_san(bit_move))
+            .expect("SAN of a legal move is readable");
+        pgn_move.bit_move = bit_move;
+        pgn_move
+    }
 
-        unimplemented!()
+    fn parse_san(input: &str) -> Result<PGNMove, SanError> {
+        let mut san: &str = input.trim();
+        let mut tag = PGNMoveTag::None;
+        if let Some(i) = san.find(|c| c == '!' || c == '?') {
+            tag = PGNMoveTag::parse(&san[i..])
+                .ok_or_else(|| SanError::Unreadable {san: input.to_string()})?;
+            san = &san[..i];
+        }
+
+        let mut check = None;
+        if san.ends_with('#') {
+            check = Some(CheckType::CheckMate);
+            san = san.trim_right_matches('#');
+        } else if san.ends_with('+') {
+            check = Some(CheckType::Check);
+            san = san.trim_right_matches('+');
+        }
+
+        Ok(PGNMove {
+            move_type: PGNMoveType::parse(san)?,
+            check,
+            tag,
+            nags: Vec::new(),
+            comment: None,
+            variations: Vec::new(),
+            bit_move: BitMove::null(),
+        })
+    }
+}
+
+impl fmt::Display for PGNMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.move_type)?;
+        if let Some(check) = self.check {
+            write!(f, "{}", check)?;
+        }
+        write!(f, "{}", self.tag)
     }
 }
 
-pub struct PGNRound {
-    move_num: u32,
-    white_move: PGNMove,
-    black_move: Option<PGNMove>,
+/// A variation, or a line of moves alternative to a move of the game.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PGNVariation {
+    /// The comment preceding the first move of the variation.
+    pub comment: Option<String>,
+    pub moves: Vec<PGNMove>,
 }
 
+/// Represents possible Errors encountered while parsing a game in PGN.
+///
+/// Errors found in the movetext report the line and column where they occurred, both
+/// starting at 1.
 #[derive(Debug)]
 pub enum PGNError {
     TagParse,
     Length,
+    San(SanError),
+    Fen(FenBuildError),
+    Unexpected {line: usize, column: usize, token: String},
+    Unterminated {line: usize, column: usize},
+    Move {line: usize, column: usize, error: SanError},
+    Io(io::Error),
 }
 
+impl PGNError {
+    /// Moves the line of an error by `lines`, for games read from the middle of a file.
+    fn offset_lines(self, lines: usize) -> PGNError {
+        match self {
+            PGNError::Unexpected {line, column, token} =>
+                PGNError::Unexpected {line: line + lines, column, token},
+            PGNError::Unterminated {line, column} =>
+                PGNError::Unterminated {line: line + lines, column},
+            PGNError::Move {line, column, error} =>
+                PGNError::Move {line: line + lines, column, error},
+            e => e
+        }
+    }
+}
 
+/// A game in Portable Game Notation.
 pub struct PGN {
     tags: PGNTags,
-    moves: Vec<PGNRound>
+    comment: Option<String>,
+    moves: Vec<PGNMove>,
+    result: GameResult
+}
+
+impl fmt::Display for PGN {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = self.to_string();
+        f.pad(&s)
+    }
 }
 
 // [Event "F/S Return Match"]
 impl PGN {
+    /// Creates a game from its starting `Board` and a tree of moves. The `SetUp` and `FEN` tags
+    /// are set if the game does not start from the standard starting position, and the `Result`
+    /// tag is set to the result of the game.
+    ///
+    /// The moves are replayed to make sure they are legal. Moves without a `bit_move` are
+    /// looked up from their SAN, and the SAN of every move is rewritten from the position
+    /// it is played in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::Board;
+    /// use pleco::board::pgn::{PGN, PGNMove, PGNTags, GameResult};
+    ///
+    /// let mut board = Board::default();
+    /// let mut moves = Vec::new();
+    /// for uci in ["e2e4", "e7e5", "g1f3"].iter() {
+    ///     let mov = *board.generate_moves().iter().find(|m| m.stringify() == *uci).unwrap();
+    ///     moves.push(PGNMove::new(&board, mov));
+    ///     board.apply_move(mov);
+    /// }
+    ///
+    /// let tags = PGNTags::default().with_tag("White", "Pleco");
+    /// let pgn = PGN::new(tags, &Board::default(), moves, GameResult::Other).unwrap();
+    /// assert!(pgn.to_string().ends_with("\n\n1. e4 e5 2. Nf3 *\n"));
+    /// ```
+    pub fn new(tags: PGNTags, board: &Board, moves: Vec<PGNMove>, result: GameResult)
+            -> Result<PGN, PGNError> {
+        PGN::from_tags(tags.with_position(board), moves, result)
+    }
+
+    /// Creates a game from a tree of moves, starting from the position of the `SetUp` and `FEN`
+    /// tags, or the standard starting position if there are none.
+    ///
+    /// See `PGN::new()` for how the moves are checked.
+    pub fn from_tags(tags: PGNTags, mut moves: Vec<PGNMove>, result: GameResult)
+            -> Result<PGN, PGNError> {
+        let mut board = tags.starting_board()?;
+        replay_moves(&mut board, &mut moves)?;
+        Ok(PGN {
+            tags: tags.with_tag("Result", &result.to_string()),
+            comment: None,
+            moves: moves,
+            result: result
+        })
+    }
+
+    /// Returns the game in PGN export format.
+    ///
+    /// The tag pairs of the Seven Tag Roster come first, followed by any other tags and the
+    /// movetext, which is wrapped to lines of at most 80 characters.
+    pub fn to_string(&self) -> String {
+        let board = self.tags.starting_board()
+            .expect("starting position was checked when parsing");
+        let mut tokens: Vec<String> = Vec::new();
+        if let Some(ref comment) = self.comment {
+            push_comment_tokens(&mut tokens, comment);
+        }
+        push_move_tokens(&mut tokens, board.moves_played(), &self.moves);
+        tokens.push(self.result.to_string());
+
+        let mut s = self.tags.to_string();
+        s.push('\n');
+        let mut line_len: usize = 0;
+        for token in tokens.iter() {
+            if line_len > 0 && line_len + 1 + token.len() > PGN_LINE_WIDTH {
+                s.push('\n');
+                line_len = 0;
+            } else if line_len > 0 {
+                s.push(' ');
+                line_len += 1;
+            }
+            s.push_str(token);
+            line_len += token.len();
+        }
+        s.push('\n');
+        s
+    }
+    /// Parses a single game, replaying each move of the game, and of its variations, on a `Board`.
+    ///
+    /// The tag pairs are followed by the movetext. Move numbers are optional, and the game
+    /// may end with a game termination marker, such as `1/2-1/2`. If it does not, the result
+    /// is taken from the `Result` tag.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::board::pgn::{PGN, PGNError};
+    ///
+    /// let pgn = PGN::parse("1. f3 e5 2. g4 Qh4# 0-1").unwrap();
+    /// assert!(pgn.board().checkmate());
+    ///
+    /// match PGN::parse("1. e4 e5\n2. Ke3") {
+    ///     Err(PGNError::Move {line, column, ..}) => assert_eq!((line, column), (2, 4)),
+    ///     _ => panic!()
+    /// }
+    /// ```
     pub fn parse(input: &str) -> Result<PGN, PGNError> {
-        let mut tags = PGNTags::default();
+        if input.trim().is_empty() {
+            return Err(PGNError::Length);
+        }
 
-        let mut lines = input.lines();
-        loop {
-            let mut line = lines.next().ok_or(PGNError::Length)?;
-            if line == "" {
+        let mut tags = PGNTags::default();
+        let lines: Vec<&str> = input.lines().collect();
+        let mut movetext_start: usize = 0;
+        for (i, line) in lines.iter().enumerate() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                tags = tags.add(line)?;
+            } else if !line.is_empty() {
                 break;
             }
-            line = line.trim();
-            tags = tags.add(line)?;
+            movetext_start = i + 1;
+        }
+
+        let movetext: String = lines[movetext_start..].join("\n");
+        let mut lexer = Lexer::new(&movetext, movetext_start + 1);
+        let mut stack: Vec<OpenVariation> = vec![OpenVariation::new(tags.starting_board()?, 0, 0)];
+        let mut result: Option<GameResult> = None;
+
+        while let Some((token, line, column)) = lexer.next_token()? {
+            let unexpected = PGNError::Unexpected {line, column, token: token.to_string()};
+            if result.is_some() {
+                match token {
+                    Token::Comment(_) => continue,
+                    _ => return Err(unexpected)
+                }
+            }
+            match token {
+                Token::Symbol(symbol) => {
+                    // Move numbers carry no information, the side to move is known from the Board.
+                    if symbol.chars().all(|c| c.is_ascii_digit()) {
+                        continue;
+                    }
+                    if let Some(game_result) = GameResult::parse(&symbol) {
+                        if stack.len() > 1 {
+                            return Err(unexpected);
+                        }
+                        result = Some(game_result);
+                        continue;
+                    }
+                    let current = stack.last_mut().unwrap();
+                    let mut pgn_move = PGNMove::parse_san(&symbol)
+                        .map_err(|error| PGNError::Move {line, column, error})?;
+                    pgn_move.bit_move = current.board.parse_san(&symbol)
+                        .map_err(|error| PGNError::Move {line, column, error})?;
+                    current.board.apply_move(pgn_move.bit_move);
+                    current.moves.push(pgn_move);
+                },
+                Token::Annotation(annotation) => {
+                    let tag = PGNMoveTag::parse(&annotation);
+                    match (tag, stack.last_mut().unwrap().moves.last_mut()) {
+                        (Some(tag), Some(pgn_move)) => pgn_move.tag = tag,
+                        _ => return Err(unexpected)
+                    }
+                },
+                Token::Nag(nag) => {
+                    match stack.last_mut().unwrap().moves.last_mut() {
+                        Some(pgn_move) => pgn_move.nags.push(nag),
+                        None => return Err(unexpected)
+                    }
+                },
+                Token::Comment(text) => {
+                    let current = stack.last_mut().unwrap();
+                    match current.moves.last_mut() {
+                        Some(pgn_move) => push_comment(&mut pgn_move.comment, text),
+                        None => push_comment(&mut current.comment, text)
+                    }
+                },
+                Token::OpenVariation => {
+                    // A variation replaces the last move, so it starts from the position before it.
+                    let board = {
+                        let current = stack.last().unwrap();
+                        if current.moves.is_empty() {
+                            return Err(unexpected);
+                        }
+                        let mut board = current.board.parallel_clone();
+                        board.undo_move();
+                        board
+                    };
+                    stack.push(OpenVariation::new(board, line, column));
+                },
+                Token::CloseVariation => {
+                    if stack.len() == 1 {
+                        return Err(unexpected);
+                    }
+                    let closed = stack.pop().unwrap();
+                    let variation = PGNVariation {
+                        comment: closed.comment,
+                        moves: closed.moves
+                    };
+                    stack.last_mut().unwrap().moves.last_mut().unwrap().variations.push(variation);
+                }
+            }
         }
+
+        if stack.len() > 1 {
+            let open = stack.pop().unwrap();
+            return Err(PGNError::Unterminated {line: open.line, column: open.column});
+        }
+        let main = stack.pop().unwrap();
+        let result = result.unwrap_or_else(|| tags.game_result());
+
         Ok(PGN {
             tags: tags,
-            moves: Vec::new()
+            comment: main.comment,
+            moves: main.moves,
+            result: result
         })
     }
+
+    /// Returns the tag pairs of the game.
+    pub fn tags(&self) -> &PGNTags {
+        &self.tags
+    }
+
+    /// Returns the comment preceding the first move of the game.
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_ref().map(|c| c.as_str())
+    }
+
+    /// Returns the moves of the main line of the game.
+    pub fn moves(&self) -> &[PGNMove] {
+        &self.moves
+    }
+
+    /// Returns the result of the game.
+    pub fn result(&self) -> GameResult {
+        self.result
+    }
+
+    /// Returns the `Board` at the end of the main line of the game.
+    pub fn board(&self) -> Board {
+        let mut board = self.tags.starting_board()
+            .expect("starting position was checked when parsing");
+        for pgn_move in self.moves.iter() {
+            board.apply_move(pgn_move.bit_move);
+        }
+        board
+    }
+}
+
+/// The maximum length of a line of movetext written in export format.
+const PGN_LINE_WIDTH: usize = 80;
+
+/// Checks that the moves of a tree are legal from the given position, filling in the
+/// `bit_move` of moves that only have their SAN, and rewriting the SAN of every move.
+fn replay_moves(board: &mut Board, moves: &mut [PGNMove]) -> Result<(), PGNError> {
+    for pgn_move in moves.iter_mut() {
+        for variation in pgn_move.variations.iter_mut() {
+            let mut alternate = board.parallel_clone();
+            replay_moves(&mut alternate, &mut variation.moves)?;
+        }
+        if pgn_move.bit_move.is_null() {
+            pgn_move.bit_move = board.parse_san(&pgn_move.move_type.to_string())
+                .map_err(PGNError::San)?;
+        } else if !board.generate_moves().iter().any(|m| *m == pgn_move.bit_move) {
+            let san = board.move_to_uci(pgn_move.bit_move);
+            return Err(PGNError::San(SanError::IllegalMove {san}));
+        }
+        let written = PGNMove::new(board, pgn_move.bit_move);
+        pgn_move.move_type = written.move_type;
+        pgn_move.check = written.check;
+        board.apply_move(pgn_move.bit_move);
+    }
+    Ok(())
+}
+
+/// Adds the tokens of a line of moves, the first of which is played after `half_moves` moves.
+fn push_move_tokens(tokens: &mut Vec<String>, half_moves: u16, moves: &[PGNMove]) {
+    // Black's moves are numbered only when they start a line, or follow a comment or variation.
+    let mut needs_number = true;
+    for (i, pgn_move) in moves.iter().enumerate() {
+        let ply = half_moves + i as u16;
+        if ply % 2 == 0 {
+            tokens.push(format!("{}.", ply / 2 + 1));
+        } else if needs_number {
+            tokens.push(format!("{}...", ply / 2 + 1));
+        }
+        tokens.push(pgn_move.to_string());
+        needs_number = false;
+
+        for nag in pgn_move.nags.iter() {
+            tokens.push(format!("${}", nag));
+        }
+        if let Some(ref comment) = pgn_move.comment {
+            needs_number |= push_comment_tokens(tokens, comment);
+        }
+        for variation in pgn_move.variations.iter() {
+            let mut variation_tokens: Vec<String> = Vec::new();
+            if let Some(ref comment) = variation.comment {
+                push_comment_tokens(&mut variation_tokens, comment);
+            }
+            push_move_tokens(&mut variation_tokens, ply, &variation.moves);
+            match variation_tokens.len() {
+                0 => variation_tokens.push("()".to_string()),
+                len => {
+                    variation_tokens[0].insert(0, '(');
+                    variation_tokens[len - 1].push(')');
+                }
+            }
+            tokens.extend(variation_tokens);
+            needs_number = true;
+        }
+    }
+}
+
+/// Adds a comment one word at a time, so it can be wrapped across lines. Returns if
+/// the comment was not empty.
+fn push_comment_tokens(tokens: &mut Vec<String>, comment: &str) -> bool {
+    let comment: String = comment.replace('}', "");
+    let words: Vec<&str> = comment.split_whitespace().collect();
+    if words.is_empty() {
+        return false;
+    }
+    let first = tokens.len();
+    tokens.extend(words.iter().map(|w| w.to_string()));
+    tokens[first].insert(0, '{');
+    tokens.last_mut().unwrap().push('}');
+    true
+}
+
+/// A line of moves still being parsed, along with the position it has reached.
+struct OpenVariation {
+    board: Board,
+    comment: Option<String>,
+    moves: Vec<PGNMove>,
+    line: usize,
+    column: usize,
+}
+
+impl OpenVariation {
+    fn new(board: Board, line: usize, column: usize) -> OpenVariation {
+        OpenVariation {
+            board,
+            comment: None,
+            moves: Vec::new(),
+            line,
+            column
+        }
+    }
+}
+
+fn push_comment(comment: &mut Option<String>, text: String) {
+    if text.is_empty() {
+        return;
+    }
+    match *comment {
+        Some(ref mut c) => {
+            c.push(' ');
+            c.push_str(&text);
+        },
+        None => *comment = Some(text)
+    }
+}
+
+enum Token {
+    Symbol(String),
+    Annotation(String),
+    Nag(u8),
+    Comment(String),
+    OpenVariation,
+    CloseVariation,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Token::Symbol(ref s) | Token::Annotation(ref s) => f.pad(s),
+            Token::Nag(nag) => write!(f, "${}", nag),
+            Token::Comment(ref c) => write!(f, "{{{}}}", c),
+            Token::OpenVariation => f.pad("("),
+            Token::CloseVariation => f.pad(")"),
+        }
+    }
+}
+
+/// Splits movetext into tokens, keeping track of the line and column of each.
+struct Lexer<'a> {
+    chars: iter::Peekable<str::Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a str, line: usize) -> Lexer<'a> {
+        Lexer {
+            chars: input.chars().peekable(),
+            line,
+            column: 1
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        match c {
+            Some('\n') => {
+                self.line += 1;
+                self.column = 1;
+            },
+            Some(_) => self.column += 1,
+            None => {}
+        }
+        c
+    }
+
+    fn take_while<F: Fn(char) -> bool>(&mut self, f: F) -> String {
+        let mut s = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !f(c) {
+                break;
+            }
+            s.push(c);
+            self.bump();
+        }
+        s
+    }
+
+    /// Returns the next token with its line and column, or `None` at the end of the input.
+    fn next_token(&mut self) -> Result<Option<(Token, usize, usize)>, PGNError> {
+        loop {
+            self.take_while(char::is_whitespace);
+            let (line, column) = (self.line, self.column);
+            let c: char = match self.chars.peek() {
+                Some(&c) => c,
+                None => return Ok(None)
+            };
+            let token = match c {
+                '{' => {
+                    self.bump();
+                    let text = self.take_while(|c| c != '}');
+                    if self.bump().is_none() {
+                        return Err(PGNError::Unterminated {line, column});
+                    }
+                    Token::Comment(text.split_whitespace().collect::<Vec<&str>>().join(" "))
+                },
+                ';' => {
+                    self.bump();
+                    Token::Comment(self.take_while(|c| c != '\n').trim().to_string())
+                },
+                // Escaped lines, and the periods following move numbers, are skipped.
+                '%' if column == 1 => {
+                    self.take_while(|c| c != '\n');
+                    continue;
+                },
+                '.' => {
+                    self.take_while(|c| c == '.');
+                    continue;
+                },
+                '(' => {
+                    self.bump();
+                    Token::OpenVariation
+                },
+                ')' => {
+                    self.bump();
+                    Token::CloseVariation
+                },
+                '$' => {
+                    self.bump();
+                    let digits = self.take_while(|c| c.is_ascii_digit());
+                    match digits.parse::<u8>() {
+                        Ok(nag) => Token::Nag(nag),
+                        Err(_) => {
+                            let token = "$".to_string() + &digits;
+                            return Err(PGNError::Unexpected {line, column, token});
+                        }
+                    }
+                },
+                '!' | '?' => Token::Annotation(self.take_while(|c| c == '!' || c == '?')),
+                c if is_symbol_char(c) => Token::Symbol(self.take_while(is_symbol_char)),
+                c => {
+                    self.bump();
+                    return Err(PGNError::Unexpected {line, column, token: c.to_string()});
+                }
+            };
+            return Ok(Some((token, line, column)));
+        }
+    }
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_+#=:-/*".contains(c)
+}
+
+
+/// Reads the games of a PGN database one at a time, without loading the whole file.
+///
+/// `PgnReader` is an iterator over the games of the database. When only the tags of each game
+/// are needed, `PgnReader::next_tags()` skips the movetext without parsing it.
+///
+/// A game that fails to parse is returned as an error, after which reading continues with the
+/// next game. If reading fails partway through a game, reading instead resumes from the next
+/// `[Event` tag. Errors in the movetext report their line in the whole database.
+///
+/// Parse errors deliberately do not skip ahead to the next `[Event` tag. The end of a game is
+/// found from its text alone, before it is parsed, so the next game is already known to start
+/// at the following tag. Many databases leave out the `Event` tag, and skipping to it would
+/// silently drop every game until the next one that has it.
+///
+/// # Examples
+///
+/// ```
+/// use pleco::board::pgn::{PgnReader, GameResult};
+///
+/// let database = "[Event \"A\"]\n[Result \"1-0\"]\n\n1. e4 e5 1-0\n\n\
+///                 [Event \"B\"]\n[Result \"0-1\"]\n\n1. f3 e5 2. g4 Qh4# 0-1\n";
+///
+/// let games: Vec<_> = PgnReader::new(database.as_bytes()).collect();
+/// assert_eq!(games.len(), 2);
+/// assert!(games[1].as_ref().unwrap().board().checkmate());
+///
+/// let mut reader = PgnReader::new(database.as_bytes());
+/// assert_eq!(reader.next_tags().unwrap().unwrap().event(), "A");
+/// assert_eq!(reader.next_tags().unwrap().unwrap().game_result(), GameResult::BlackWins);
+/// assert!(reader.next_tags().is_none());
+/// ```
+pub struct PgnReader<R: BufRead> {
+    reader: R,
+    /// A line read past the end of the previous game.
+    peeked: Option<String>,
+    line: usize,
+    resync: bool,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    /// Creates a `PgnReader` reading games from `reader`.
+    pub fn new(reader: R) -> PgnReader<R> {
+        PgnReader {
+            reader,
+            peeked: None,
+            line: 0,
+            resync: false
+        }
+    }
+
+    /// Reads and parses the next game, or returns `None` once there are no more games.
+    pub fn next_game(&mut self) -> Option<Result<PGN, PGNError>> {
+        let (text, start_line) = match self.read_game(false) {
+            Ok(Some(game)) => game,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(e))
+        };
+        Some(PGN::parse(&text).map_err(|e| e.offset_lines(start_line - 1)))
+    }
+
+    /// Reads only the tags of the next game, skipping its movetext, or returns `None` once
+    /// there are no more games.
+    pub fn next_tags(&mut self) -> Option<Result<PGNTags, PGNError>> {
+        let text = match self.read_game(true) {
+            Ok(Some((text, _))) => text,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(e))
+        };
+        let mut tags = PGNTags::default();
+        for line in text.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+            tags = match tags.add(line) {
+                Ok(tags) => tags,
+                Err(e) => return Some(Err(e))
+            };
+        }
+        Some(Ok(tags))
+    }
+
+    fn next_line(&mut self) -> Result<Option<String>, PGNError> {
+        if let Some(line) = self.peeked.take() {
+            self.line += 1;
+            return Ok(Some(line));
+        }
+        // Older databases are often not UTF-8, so any invalid characters are replaced
+        // rather than failing the whole read.
+        let mut buf: Vec<u8> = Vec::new();
+        if self.reader.read_until(b'\n', &mut buf).map_err(PGNError::Io)? == 0 {
+            return Ok(None);
+        }
+        self.line += 1;
+        Ok(Some(String::from_utf8_lossy(&buf).trim_right().to_string()))
+    }
+
+    /// Reads the text of the next game, along with the line it starts on. The game ends at the
+    /// first tag following its movetext.
+    fn read_game(&mut self, skip_movetext: bool) -> Result<Option<(String, usize)>, PGNError> {
+        let mut text = String::new();
+        let mut start_line: usize = 0;
+        let mut in_movetext = false;
+        let mut in_comment = false;
+
+        loop {
+            // The end of the game is unknown after a failed read, so skip to the next one.
+            let line = match self.next_line() {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(e) => {
+                    self.resync = true;
+                    return Err(e);
+                }
+            };
+            if self.resync {
+                if !line.trim_left().starts_with("[Event") {
+                    continue;
+                }
+                self.resync = false;
+            }
+            let trimmed: &str = line.trim();
+            if trimmed.starts_with('[') && !in_comment {
+                if in_movetext {
+                    self.peeked = Some(line.clone());
+                    self.line -= 1;
+                    break;
+                }
+            } else if !trimmed.is_empty() {
+                in_movetext = true;
+            }
+            if start_line == 0 {
+                if trimmed.is_empty() {
+                    continue;
+                }
+                start_line = self.line;
+            }
+            if in_movetext {
+                in_comment = ends_in_comment(trimmed, in_comment);
+                if skip_movetext {
+                    continue;
+                }
+            }
+            text.push_str(&line);
+            text.push('\n');
+        }
+
+        if start_line == 0 {
+            Ok(None)
+        } else {
+            Ok(Some((text, start_line)))
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PGN, PGNError>;
+
+    fn next(&mut self) -> Option<Result<PGN, PGNError>> {
+        self.next_game()
+    }
+}
+
+/// Returns if a line of movetext ends inside of a `{}` comment.
+fn ends_in_comment(line: &str, mut in_comment: bool) -> bool {
+    for c in line.chars() {
+        match c {
+            '}' if in_comment => in_comment = false,
+            '{' if !in_comment => in_comment = true,
+            ';' if !in_comment => break,
+            _ => {}
+        }
+    }
+    in_comment
 }
 
 
@@ -359,4 +1502,259 @@ mod tests {
             .add(TEST_DATE).unwrap()
             .add(TEST_ROUND).unwrap();
     }
+
+    static FISCHER_SPASSKY: &'static str = "[Event \"F/S Return Match\"]
+[Site \"Belgrade, Serbia JUG\"]
+[Date \"1992.11.04\"]
+[Round \"29\"]
+[White \"Fischer, Robert J.\"]
+[Black \"Spassky, Boris V.\"]
+[Result \"1/2-1/2\"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 {This opening is called the Ruy Lopez.} 3... a6
+4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O 9. h3 Nb8 10. d4 Nbd7
+11. c4 c6 12. cxb5 axb5 13. Nc3 Bb7 14. Bg5 b4 15. Nb1 h6 16. Bh4 c5 17. dxe5
+Nxe4 18. Bxe7 Qxe7 19. exd6 Qf6 20. Nbd2 Nxd6 21. Nc4 Nxc4 22. Bxc4 Nb6
+23. Ne5 Rae8 24. Bxf7+ Rxf7 25. Nxf7 Rxe1+ 26. Qxe1 Kxf7 27. Qe3 Qg5 28. Qxg5
+hxg5 29. b3 Ke6 30. a3 Kd6 31. axb4 cxb4 32. Ra5 Nd5 33. f3 Bc8 34. Kf2 Bf5
+35. Ra7 g6 36. Ra6+ Kc5 37. Ke1 Nf4 38. g3 Nxh3 39. Kd2 Kb5 40. Rd6 Kc5 41. Ra6
+Nf2 42. g4 Bd3 43. Re6 1/2-1/2";
+
+    #[test]
+    fn full_game() {
+        let pgn = PGN::parse(FISCHER_SPASSKY).unwrap();
+        assert_eq!(pgn.tags().white(), "Fischer, Robert J.");
+        assert_eq!(pgn.tags().round().to_string(), "\"29\"");
+        assert_eq!(pgn.moves().len(), 85);
+        assert_eq!(pgn.moves()[4].comment, Some("This opening is called the Ruy Lopez.".to_string()));
+        assert_eq!(pgn.moves()[46].check, Some(CheckType::Check));
+        assert_eq!(pgn.result(), GameResult::Draw);
+        assert_eq!(pgn.board().get_fen(), "8/8/4R1p1/2k3p1/1p4P1/1P1b1P2/3K1n2/8 b - - 2 43");
+    }
+
+    #[test]
+    fn annotations_and_variations() {
+        let input = "[Event \"Annotated\"]\n[SetUp \"1\"]\n\n{Start} 1.e4 $1 e5!? \
+                     (1...c5 2.Nf3 (2.c3 ; Alapin\n d5) d6) (1...e6?! {French}) \
+                     2.Nf3 Nc6 $14 *";
+        let pgn = PGN::parse(input).unwrap();
+        assert_eq!(pgn.comment(), Some("Start"));
+        assert_eq!(pgn.tags().tag("SetUp"), Some("1"));
+        assert_eq!(pgn.result(), GameResult::Other);
+
+        let moves = pgn.moves();
+        assert_eq!(moves.len(), 4);
+        assert_eq!(moves[0].nags, vec![1]);
+        assert_eq!(moves[1].tag, PGNMoveTag::Interesting);
+        assert_eq!(moves[3].nags, vec![14]);
+        assert_eq!(moves[2].bit_move.stringify(), "g1f3");
+
+        let variations = &moves[1].variations;
+        assert_eq!(variations.len(), 2);
+        assert_eq!(variations[0].moves.len(), 3);
+        assert_eq!(variations[0].moves[0].bit_move.stringify(), "c7c5");
+        assert_eq!(variations[0].moves[1].variations[0].moves[0].comment, Some("Alapin".to_string()));
+        assert_eq!(variations[0].moves[1].variations[0].moves[1].bit_move.stringify(), "d7d5");
+        assert_eq!(variations[1].moves[0].tag, PGNMoveTag::Doubtful);
+        assert_eq!(variations[1].moves[0].comment, Some("French".to_string()));
+    }
+
+    #[test]
+    fn setup_position() {
+        let input = "[FEN \"4k3/8/8/8/8/8/8/4K2R w K - 0 1\"]\n\n1. O-O Kd7 2. Rd1+ 1-0";
+        let pgn = PGN::parse(input).unwrap();
+        assert_eq!(pgn.moves()[0].move_type, PGNMoveType::KingSideCastle);
+        assert_eq!(pgn.board().get_fen(), "8/3k4/8/8/8/8/8/3R2K1 b - - 3 2");
+        assert_eq!(pgn.result(), GameResult::WhiteWins);
+    }
+
+    #[test]
+    fn movetext_errors() {
+        match PGN::parse("1. e4 e5\n2. Nf3 (2. Nc3 Nc6\n3. Bc4 Bc5 3. Bb5 Nf6") {
+            Err(PGNError::Unterminated {line: 2, column: 8}) => {},
+            e => panic!("{:?}", e.map(|_| ()))
+        }
+        match PGN::parse("[Result \"*\"]\n\n1. e4 e5 2. Bc4 Nf6\n3. Qxf7 *") {
+            Err(PGNError::Move {line: 4, column: 4, ..}) => {},
+            e => panic!("{:?}", e.map(|_| ()))
+        }
+        match PGN::parse("1. d4 d5 2. c4) 1-0") {
+            Err(PGNError::Unexpected {line: 1, column: 15, ..}) => {},
+            e => panic!("{:?}", e.map(|_| ()))
+        }
+        match PGN::parse("1. d4 d5 1-0 3. c4") {
+            Err(PGNError::Unexpected {line: 1, column: 14, ..}) => {},
+            e => panic!("{:?}", e.map(|_| ()))
+        }
+        assert!(PGN::parse("1. e4 {unclosed").is_err());
+        assert!(PGN::parse("[FEN \"not a fen\"]\n\n1. e4").is_err());
+        assert!(PGN::parse("").is_err());
+    }
+
+    #[test]
+    fn write_round_trip() {
+        let pgn = PGN::parse(FISCHER_SPASSKY).unwrap();
+        let written = pgn.to_string();
+        assert!(written.starts_with("[Event \"F/S Return Match\"]\n[Site \"Belgrade, Serbia JUG\"]\n\
+                                     [Date \"1992.11.04\"]\n[Round \"29\"]\n"));
+        assert!(written.contains("3. Bb5 {This opening is called the Ruy Lopez.} 3... a6"));
+        assert!(written.ends_with("43. Re6\n1/2-1/2\n"));
+        assert!(written.lines().all(|l| l.len() <= 80));
+
+        let reparsed = PGN::parse(&written).unwrap();
+        assert!(reparsed.moves() == pgn.moves());
+        assert_eq!(reparsed.to_string(), written);
+    }
+
+    #[test]
+    fn write_annotations_and_variations() {
+        let input = "1.e4 $1 e5!? (1...c5 2.Nf3 (2.c3 ; Alapin\n d5) d6) (1...e6?! {French}) 2.Nf3 *";
+        let pgn = PGN::parse(input).unwrap();
+        assert_eq!(pgn.to_string(),
+                   "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+                    [White \"?\"]\n[Black \"?\"]\n[Result \"*\"]\n\n\
+                    1. e4 $1 e5!? (1... c5 2. Nf3 (2. c3 {Alapin} 2... d5) 2... d6) (1... e6?!\n\
+                    {French}) 2. Nf3 *\n");
+    }
+
+    #[test]
+    fn write_from_board() {
+        let board = Board::new_from_fen("4k3/8/8/8/8/8/8/4K2R b K - 0 12").unwrap();
+        let king = *board.generate_moves().iter().find(|m| m.stringify() == "e8d7").unwrap();
+        let mut moves = vec![PGNMove::new(&board, king)];
+        moves.push(PGNMove::parse("O-O").unwrap());
+        moves[1].comment = Some("Castling {with} check?".to_string());
+        moves.push(PGNMove::parse("Kc6").unwrap());
+
+        let tags = PGNTags::default()
+            .with_tag("Event", "Endgame \"study\"")
+            .with_tag("Date", "2018.03.07")
+            .with_tag("WhiteElo", "1500");
+        let pgn = PGN::new(tags, &board, moves, GameResult::Draw).unwrap();
+        assert_eq!(pgn.moves()[1].check, None);
+        assert_eq!(pgn.moves()[1].bit_move.stringify(), "e1g1");
+        assert_eq!(pgn.to_string(),
+                   "[Event \"Endgame \\\"study\\\"\"]\n[Site \"?\"]\n[Date \"2018.03.07\"]\n\
+                    [Round \"?\"]\n[White \"?\"]\n[Black \"?\"]\n[Result \"1/2-1/2\"]\n\
+                    [WhiteElo \"1500\"]\n[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/4K2R b K - 0 12\"]\n\n\
+                    12... Kd7 13. O-O {Castling {with check?} 13... Kc6 1/2-1/2\n");
+
+        // Quotes and backslashes inside tag values survive writing and reading back.
+        assert_eq!(pgn.tags().event(), "Endgame \"study\"");
+        let tags = PGNTags::default().with_tag("Annotator", "C:\\ \"Fritz\"");
+        assert_eq!(tags.tag("Annotator"), Some("C:\\ \"Fritz\""));
+        let read = PGN::parse(&pgn.to_string()).unwrap();
+        assert_eq!(read.tags().event(), "Endgame \"study\"");
+        assert_eq!(read.to_string(), pgn.to_string());
+
+        let illegal = vec![PGNMove::parse("Kd6").unwrap()];
+        assert!(PGN::new(PGNTags::default(), &board, illegal, GameResult::Other).is_err());
+    }
+
+    static DATABASE: &'static str = "[Event \"One\"]
+[Result \"1-0\"]
+
+1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0
+
+[Event \"Two\"]
+[Result \"*\"]
+
+1. d4 d5 2. c4 {a comment
+[spanning lines]} e6 3. Ke3 *
+[Event \"Three\"]
+[Result \"0-1\"]
+1. f3 e5 2. g4 Qh4# 0-1
+[Event \"Four\"
+[Result \"1/2-1/2\"]
+
+1. e4 1/2-1/2
+[Event \"Five\"]
+
+1. e4 *
+";
+
+    static BAD_THEN_NO_EVENT: &'static str = "[Event \"Bad\"]
+
+1. e4 e5 2. Ke3 *
+
+[White \"A\"]
+[Result \"1-0\"]
+
+1. e4 1-0
+";
+
+    #[test]
+    fn database_reader() {
+        let mut reader = PgnReader::new(DATABASE.as_bytes());
+        let one = reader.next_game().unwrap().unwrap();
+        assert_eq!(one.tags().event(), "One");
+        assert!(one.board().checkmate());
+
+        match reader.next_game().unwrap() {
+            Err(PGNError::Move {line: 10, column: 25, ..}) => {},
+            e => panic!("{:?}", e.map(|_| ()))
+        }
+
+        let three = reader.next_game().unwrap().unwrap();
+        assert_eq!(three.tags().event(), "Three");
+        assert_eq!(three.result(), GameResult::BlackWins);
+
+        assert!(reader.next_game().unwrap().is_err());
+        assert_eq!(reader.next_game().unwrap().unwrap().tags().event(), "Five");
+        assert!(reader.next_game().is_none());
+    }
+
+    #[test]
+    fn database_reader_after_error() {
+        // The game following a bad one is read even without an `[Event` tag.
+        let mut reader = PgnReader::new(BAD_THEN_NO_EVENT.as_bytes());
+        assert!(reader.next_game().unwrap().is_err());
+        let next = reader.next_game().unwrap().unwrap();
+        assert_eq!(next.tags().white(), "A");
+        assert_eq!(next.result(), GameResult::WhiteWins);
+        assert!(reader.next_game().is_none());
+
+        let mut reader = PgnReader::new(BAD_THEN_NO_EVENT.as_bytes());
+        assert!(reader.next_tags().unwrap().is_ok());
+        assert_eq!(reader.next_tags().unwrap().unwrap().white(), "A");
+        assert!(reader.next_tags().is_none());
+    }
+
+    #[test]
+    fn database_reader_malformed_game() {
+        let database = "[Event \"Bad\"]\n\n1. e4 e5 2. Qxf7 *\n\n\
+                        [Event \"Good\"]\n[Result \"1-0\"]\n\n1. e4 e5 1-0\n";
+        let mut reader = PgnReader::new(database.as_bytes());
+        match reader.next_game().unwrap() {
+            Err(PGNError::Move {line: 3, ..}) => {},
+            e => panic!("{:?}", e.map(|_| ()))
+        }
+        let good = reader.next_game().unwrap().unwrap();
+        assert_eq!(good.tags().event(), "Good");
+        assert_eq!(good.result(), GameResult::WhiteWins);
+        assert!(reader.next_game().is_none());
+    }
+
+    #[test]
+    fn database_tags_only() {
+        let events: Vec<String> = {
+            let mut reader = PgnReader::new(DATABASE.as_bytes());
+            let mut events = Vec::new();
+            while let Some(tags) = reader.next_tags() {
+                events.push(tags.map(|t| t.event().to_string()).unwrap_or_default());
+            }
+            events
+        };
+        assert_eq!(events, vec!["One", "Two", "Three", "", "Five"]);
+    }
+
+    #[test]
+    fn move_parsing() {
+        let pgn_move = PGNMove::parse("Nbxd7+!?").unwrap();
+        assert_eq!(pgn_move.check, Some(CheckType::Check));
+        assert_eq!(pgn_move.tag, PGNMoveTag::Interesting);
+        assert_eq!(pgn_move.to_string(), "Nbxd7+!?");
+        assert_eq!(PGNMove::parse("e8=Q#").unwrap().to_string(), "e8=Q#");
+        assert!(PGNMove::parse("Nbxd7!x").is_err());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,249 @@
+//! Module for reading and writing moves in Standard Algebraic Notation (SAN).
+//!
+//! SAN is the notation used by PGN files and most chess interfaces, e.g. `Nbd7`, `exd5`, `O-O`
+//! or `e8=Q+`. Unlike UCI notation, a SAN move can only be understood alongside the [`Board`]
+//! it is played on.
+//!
+//! [`Board`]: ../struct.Board.html
+
+use std::fmt;
+
+use core::piece_move::BitMove;
+use core::sq::SQ;
+use core::PieceType;
+
+use super::Board;
+use super::pgn::{PGNMoveType, PGNRegMove, PGNMoveSpecifier, CheckType};
+
+/// Represents possible Errors encountered while parsing a move in Standard Algebraic Notation.
+pub enum SanError {
+    Empty,
+    Unreadable {san: String},
+    IllegalMove {san: String},
+    AmbiguousMove {san: String},
+}
+
+impl fmt::Debug for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SanError::Empty => writeln!(f, "empty move"),
+            SanError::Unreadable{ref san} => writeln!(f, "unreadable move: {}", san),
+            SanError::IllegalMove{ref san} => writeln!(f, "illegal move: {}", san),
+            SanError::AmbiguousMove{ref san} => writeln!(f, "ambiguous move: {}", san),
+        }
+    }
+}
+
+impl Board {
+    /// Returns the Standard Algebraic Notation of a move for the current position.
+    ///
+    /// Moves are disambiguated by file, then rank, then by the full origin square when needed,
+    /// and end with `+` for a check or `#` for a checkmate.
+    ///
+    /// # Safety
+    ///
+    /// Assumes the move is legal for the current board.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::Board;
+    ///
+    /// let board = Board::default();
+    /// let knight = *board.generate_moves().iter().find(|m| m.stringify() == "g1f3").unwrap();
+    /// assert_eq!(board.move_to_san(knight), "Nf3");
+    /// ```
+    pub fn move_to_san(&self, bit_move: BitMove) -> String {
+        let mut san = self.san_move_type(bit_move).to_string();
+        if let Some(check) = self.san_check_type(bit_move) {
+            san.push_str(&check.to_string());
+        }
+        san
+    }
+
+    /// Parses a move in Standard Algebraic Notation, returning the matching legal move for the
+    /// current position.
+    ///
+    /// The parser is lenient about common variations: promotions may be written as `e8=Q` or
+    /// `e8Q`, castling as `O-O` or `0-0`, and capture marks, check marks and move annotations
+    /// (such as `!?`) are optional.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pleco::Board;
+    ///
+    /// let mut board = Board::default();
+    /// let mov = board.parse_san("e4").unwrap();
+    /// assert_eq!(mov.stringify(), "e2e4");
+    ///
+    /// board.apply_move(mov);
+    /// assert!(board.parse_san("Ke7").is_err());
+    /// ```
+    pub fn parse_san(&self, san: &str) -> Result<BitMove, SanError> {
+        let trimmed: &str = san.trim()
+            .trim_right_matches(|c| c == '+' || c == '#' || c == '!' || c == '?');
+        let trimmed: &str = if trimmed.ends_with("e.p.") {
+            trimmed[..trimmed.len() - 4].trim_right()
+        } else {
+            trimmed
+        };
+
+        let move_type: PGNMoveType = PGNMoveType::parse(trimmed)?;
+        let mut found: Option<BitMove> = None;
+
+        for mov in self.generate_moves().iter() {
+            let matches = match move_type {
+                PGNMoveType::KingSideCastle => mov.is_castle() && mov.is_king_castle(),
+                PGNMoveType::QueenSideCastle => mov.is_castle() && !mov.is_king_castle(),
+                PGNMoveType::Reg(ref reg) => self.san_matches(reg, *mov),
+            };
+            if matches {
+                if found.is_some() {
+                    return Err(SanError::AmbiguousMove {san: san.to_string()});
+                }
+                found = Some(*mov);
+            }
+        }
+
+        found.ok_or_else(|| SanError::IllegalMove {san: san.to_string()})
+    }
+
+    /// Returns the `PGNMoveType` of a legal move, disambiguating the origin of the piece
+    /// only if needed.
+    fn san_move_type(&self, bit_move: BitMove) -> PGNMoveType {
+        if bit_move.is_castle() {
+            return if bit_move.is_king_castle() {
+                PGNMoveType::KingSideCastle
+            } else {
+                PGNMoveType::QueenSideCastle
+            };
+        }
+
+        let src: SQ = bit_move.get_src();
+        let dest: SQ = bit_move.get_dest();
+        let piece: PieceType = self.moved_piece(bit_move);
+
+        let specifier = if piece == PieceType::P {
+            if bit_move.is_capture() {
+                Some(PGNMoveSpecifier {rank: None, file: Some(src.file()), square: None})
+            } else {
+                None
+            }
+        } else if piece == PieceType::K {
+            None
+        } else {
+            let others: Vec<SQ> = self.generate_moves().iter()
+                .filter(|m| !m.is_castle() && m.get_dest() == dest && m.get_src() != src
+                    && self.moved_piece(**m) == piece)
+                .map(|m| m.get_src())
+                .collect();
+
+            if others.is_empty() {
+                None
+            } else if others.iter().all(|s| s.file() != src.file()) {
+                Some(PGNMoveSpecifier {rank: None, file: Some(src.file()), square: None})
+            } else if others.iter().all(|s| s.rank() != src.rank()) {
+                Some(PGNMoveSpecifier {rank: Some(src.rank()), file: None, square: None})
+            } else {
+                Some(PGNMoveSpecifier {rank: None, file: None, square: Some(src)})
+            }
+        };
+
+        PGNMoveType::Reg(PGNRegMove {
+            piece: if piece == PieceType::P { None } else { Some(piece) },
+            specifier,
+            dest,
+            promo: if bit_move.is_promo() { Some(bit_move.promo_piece()) } else { None },
+            capture: bit_move.is_capture(),
+        })
+    }
+
+    /// Returns if a legal move gives check or checkmate.
+    fn san_check_type(&self, bit_move: BitMove) -> Option<CheckType> {
+        if !self.gives_check(bit_move) {
+            return None;
+        }
+        let mut board: Board = self.shallow_clone();
+        board.apply_move(bit_move);
+        if board.generate_moves().is_empty() {
+            Some(CheckType::CheckMate)
+        } else {
+            Some(CheckType::Check)
+        }
+    }
+
+    /// Returns if a legal, non-castling move matches a parsed SAN move.
+    fn san_matches(&self, reg: &PGNRegMove, mov: BitMove) -> bool {
+        if mov.is_castle() || mov.get_dest() != reg.dest {
+            return false;
+        }
+        if self.moved_piece(mov) != reg.piece.unwrap_or(PieceType::P) {
+            return false;
+        }
+        if let Some(ref spec) = reg.specifier {
+            if !spec.matches(mov.get_src()) {
+                return false;
+            }
+        }
+        match reg.promo {
+            Some(promo) => mov.is_promo() && mov.promo_piece() == promo,
+            None => !mov.is_promo(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn san_moves(fen: &str, expected: &[(&str, &str)]) {
+        let board = Board::new_from_fen(fen).unwrap();
+        for &(uci, san) in expected.iter() {
+            let mov = *board.generate_moves().iter()
+                .find(|m| m.stringify() == uci)
+                .unwrap();
+            assert_eq!(board.move_to_san(mov), san);
+            assert_eq!(board.parse_san(san).unwrap(), mov);
+        }
+    }
+
+    #[test]
+    fn san_disambiguation() {
+        // Knights on b1 and f3 can both reach d2, rooks on a1 and a5 can both reach a3,
+        // and queens on e4, h4 and h1 can all reach e1.
+        let fen = "4k2K/4p3/8/R7/4Q2Q/5N2/8/RN5Q w - - 0 1";
+        assert!(Board::new_from_fen(fen).unwrap().parse_san("Nd2").is_err());
+        san_moves(fen,
+                  &[("b1d2", "Nbd2"), ("f3d2", "Nfd2"), ("a1a3", "R1a3"), ("a5a3", "R5a3"),
+                    ("h4e1", "Qh4e1"), ("e4e1", "Qee1"), ("h1e1", "Q1e1"), ("f3e5", "Ne5")]);
+    }
+
+    #[test]
+    fn san_checks_and_specials() {
+        san_moves("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1",
+                  &[("e1g1", "O-O"), ("e1c1", "O-O-O"), ("e5d6", "exd6"),
+                    ("b7b8q", "b8=Q+"), ("b7a8n", "bxa8=N"), ("a1a8", "Rxa8+")]);
+        san_moves("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", &[("a1a8", "Ra8#")]);
+    }
+
+    #[test]
+    fn san_lenient_parsing() {
+        let board = Board::new_from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        let parsed = |san: &str| board.parse_san(san).unwrap().stringify();
+        assert_eq!(parsed("0-0"), "e1g1");
+        assert_eq!(parsed("O-O-O"), "e1c1");
+        assert_eq!(parsed("b8Q"), "b7b8q");
+        assert_eq!(parsed("b8=q+"), "b7b8q");
+        assert_eq!(parsed("ba8=N"), "b7a8n");
+        assert_eq!(parsed("ed6 e.p."), "e5d6");
+        assert_eq!(parsed("Ra8!?"), "a1a8");
+        assert_eq!(parsed("Ke2"), "e1e2");
+
+        assert!(board.parse_san("").is_err());
+        assert!(board.parse_san("Nf3").is_err());
+        assert!(board.parse_san("Rb2").is_err());
+        assert!(board.parse_san("b8").is_err());
+        assert!(board.parse_san("Rz9").is_err());
+    }
+}
@@ -0,0 +1,333 @@
+//! Contains [`BookBuilder`], for building Polyglot opening books from PGN games.
+//!
+//! [`BookBuilder`]: struct.BookBuilder.html
+
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+
+use board::Board;
+use board::pgn::{PGN, PgnReader, GameResult};
+use core::Player;
+
+use super::{BookEntry, PolyglotBook, ENTRY_SIZE, polyglot_move, to_polyglot_move};
+
+/// The results of the games a move was played in, from the perspective of the player making
+/// the move.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub struct MoveStats {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MoveStats {
+    /// Returns the number of games the move was played in.
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+}
+
+/// A single move of a book being built, as written by the inspection dumps.
+#[derive(Clone, PartialEq, Debug)]
+pub struct BookRecord {
+    /// The Polyglot key of the position.
+    pub key: u64,
+    /// The FEN of the position, as first seen.
+    pub fen: String,
+    /// The move in UCI notation.
+    pub uci_move: String,
+    /// The move as stored in a Polyglot entry.
+    pub raw_move: u16,
+    pub stats: MoveStats,
+    /// The weight of the move, before any scaling to fit a Polyglot entry.
+    pub weight: u32,
+}
+
+/// Builds a Polyglot opening book from PGN games.
+///
+/// Each game is replayed up to a ply limit, counting the wins, draws and losses of every move
+/// played in every position. A move's weight is the sum of its results, each multiplied by its
+/// weight, which default to 2 for a win, 1 for a draw and 0 for a loss.
+///
+/// Games with an unknown result are skipped. Once built, moves played in fewer than a minimum
+/// number of games are left out, as are moves with a weight of zero, which would never be
+/// chosen from the book.
+///
+/// # Examples
+///
+/// ```
+/// use pleco::Board;
+/// use pleco::book::builder::BookBuilder;
+///
+/// let games = "[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 1-0\n\n\
+///              [Result \"1/2-1/2\"]\n\n1. e4 c5 1/2-1/2\n\n\
+///              [Result \"0-1\"]\n\n1. d4 d5 0-1\n";
+///
+/// let mut builder = BookBuilder::new();
+/// builder.max_ply(2).min_games(2);
+/// assert_eq!(builder.add_pgn(games.as_bytes()), 3);
+///
+/// // Only 1. e4 was played in two games.
+/// let book = builder.build();
+/// assert_eq!(book.len(), 1);
+/// assert_eq!(book.best_move(&Board::default()).unwrap().stringify(), "e2e4");
+/// ```
+pub struct BookBuilder {
+    max_ply: usize,
+    min_games: u32,
+    min_rating: Option<u32>,
+    weights: [u32; 3],
+    moves: BTreeMap<(u64, u16), MoveStats>,
+    fens: BTreeMap<u64, String>,
+}
+
+impl Default for BookBuilder {
+    fn default() -> Self {
+        BookBuilder::new()
+    }
+}
+
+impl BookBuilder {
+    /// Creates a builder with no games, a limit of 20 plies, and no filters.
+    pub fn new() -> BookBuilder {
+        BookBuilder {
+            max_ply: 20,
+            min_games: 1,
+            min_rating: None,
+            weights: [2, 1, 0],
+            moves: BTreeMap::new(),
+            fens: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the number of plies of each game added to the book.
+    pub fn max_ply(&mut self, plies: usize) -> &mut Self {
+        self.max_ply = plies;
+        self
+    }
+
+    /// Sets the number of games a move must be played in to be kept in the book.
+    pub fn min_games(&mut self, games: u32) -> &mut Self {
+        self.min_games = games;
+        self
+    }
+
+    /// Sets the rating both players must have for a game to be added, read from the
+    /// `WhiteElo` and `BlackElo` tags. Games without these tags are then skipped.
+    pub fn min_rating(&mut self, rating: u32) -> &mut Self {
+        self.min_rating = Some(rating);
+        self
+    }
+
+    /// Sets the weight of a win, draw and loss.
+    pub fn weights(&mut self, win: u32, draw: u32, loss: u32) -> &mut Self {
+        self.weights = [win, draw, loss];
+        self
+    }
+
+    /// Adds the moves of a game, returning if the game passed the filters and was added.
+    ///
+    /// Only the main line of the game is added, not its variations.
+    pub fn add_game(&mut self, game: &PGN) -> bool {
+        if game.result() == GameResult::Other || !self.rating_allowed(game) {
+            return false;
+        }
+        let mut board: Board = match game.tags().starting_board() {
+            Ok(board) => board,
+            Err(_) => return false,
+        };
+
+        for pgn_move in game.moves().iter().take(self.max_ply) {
+            let key: u64 = board.polyglot_key();
+            let stats = self.moves.entry((key, to_polyglot_move(pgn_move.bit_move)))
+                .or_insert_with(MoveStats::default);
+            match (game.result(), board.turn()) {
+                (GameResult::Draw, _) => stats.draws += 1,
+                (GameResult::WhiteWins, Player::White) |
+                (GameResult::BlackWins, Player::Black) => stats.wins += 1,
+                _ => stats.losses += 1,
+            }
+            if !self.fens.contains_key(&key) {
+                self.fens.insert(key, board.get_fen());
+            }
+            board.apply_move(pgn_move.bit_move);
+        }
+        true
+    }
+
+    /// Adds every game of a PGN database, returning the number of games added.
+    ///
+    /// Games that cannot be parsed are skipped.
+    pub fn add_pgn<R: BufRead>(&mut self, reader: R) -> usize {
+        let mut added: usize = 0;
+        for game in PgnReader::new(reader) {
+            if let Ok(game) = game {
+                if self.add_game(&game) {
+                    added += 1;
+                }
+            }
+        }
+        added
+    }
+
+    fn rating_allowed(&self, game: &PGN) -> bool {
+        match self.min_rating {
+            None => true,
+            Some(min) => ["WhiteElo", "BlackElo"].iter().all(|tag| {
+                game.tags().tag(tag)
+                    .and_then(|elo| elo.trim().parse::<u32>().ok())
+                    .map_or(false, |elo| elo >= min)
+            }),
+        }
+    }
+
+    fn weight(&self, stats: &MoveStats) -> u32 {
+        stats.wins * self.weights[0] + stats.draws * self.weights[1] + stats.losses * self.weights[2]
+    }
+
+    /// Returns every move kept in the book, sorted by key and then by descending weight.
+    pub fn records(&self) -> Vec<BookRecord> {
+        let mut records: Vec<BookRecord> = Vec::new();
+        for (&(key, raw_move), stats) in self.moves.iter() {
+            let weight: u32 = self.weight(stats);
+            if stats.games() < self.min_games || weight == 0 {
+                continue;
+            }
+            let fen: String = self.fens[&key].clone();
+            let board = Board::new_from_fen(&fen).expect("FEN of a played position is valid");
+            let uci_move = polyglot_move(&board, raw_move)
+                .map(|m| board.move_to_uci(m))
+                .expect("book moves were played in the position");
+            records.push(BookRecord {key, fen, uci_move, raw_move, stats: *stats, weight});
+        }
+        records.sort_by(|a, b| a.key.cmp(&b.key).then(b.weight.cmp(&a.weight)));
+        records
+    }
+
+    /// Returns the entries of the book, sorted by key and then by descending weight.
+    ///
+    /// If any weight is too large for an entry, all weights are scaled down to fit, keeping
+    /// every weight at least 1.
+    pub fn entries(&self) -> Vec<BookEntry> {
+        let records = self.records();
+        let max_weight: u64 = records.iter().map(|r| u64::from(r.weight)).max().unwrap_or(0);
+        let max_entry: u64 = u64::from(u16::max_value());
+        records.iter()
+            .map(|r| {
+                let mut weight: u64 = u64::from(r.weight);
+                if max_weight > max_entry {
+                    weight = (weight * max_entry / max_weight).max(1);
+                }
+                BookEntry {
+                    key: r.key,
+                    raw_move: r.raw_move,
+                    weight: weight as u16,
+                    learn: 0,
+                }
+            })
+            .collect()
+    }
+
+    /// Builds the book.
+    pub fn build(&self) -> PolyglotBook {
+        PolyglotBook::from_entries(self.entries())
+    }
+
+    /// Writes the book as a Polyglot `.bin` file.
+    pub fn write_polyglot<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let entries = self.entries();
+        let mut bytes: Vec<u8> = Vec::with_capacity(entries.len() * ENTRY_SIZE);
+        for entry in entries.iter() {
+            bytes.extend_from_slice(&entry.to_bytes());
+        }
+        writer.write_all(&bytes)
+    }
+
+    /// Writes every move of the book as CSV, with a header line, for inspection.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "key,fen,move,wins,draws,losses,weight")?;
+        for r in self.records().iter() {
+            writeln!(writer, "{:016x},{},{},{},{},{},{}",
+                     r.key, r.fen, r.uci_move, r.stats.wins, r.stats.draws, r.stats.losses, r.weight)?;
+        }
+        Ok(())
+    }
+
+    /// Writes every move of the book as a JSON array of objects, for inspection.
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let records = self.records();
+        writeln!(writer, "[")?;
+        for (i, r) in records.iter().enumerate() {
+            writeln!(writer, "  {{\"key\": \"{:016x}\", \"fen\": \"{}\", \"move\": \"{}\", \
+                              \"wins\": {}, \"draws\": {}, \"losses\": {}, \"weight\": {}}}{}",
+                     r.key, r.fen, r.uci_move, r.stats.wins, r.stats.draws, r.stats.losses,
+                     r.weight, if i + 1 < records.len() { "," } else { "" })?;
+        }
+        writeln!(writer, "]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAMES: &str = "[WhiteElo \"2400\"]\n[BlackElo \"2300\"]\n[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 Nc6 1-0\n\n\
+                         [WhiteElo \"2500\"]\n[BlackElo \"1900\"]\n[Result \"0-1\"]\n\n1. e4 e5 2. Bc4 0-1\n\n\
+                         [Result \"1/2-1/2\"]\n\n1. e4 c5 1/2-1/2\n\n\
+                         [Result \"*\"]\n\n1. d4 *\n";
+
+    #[test]
+    fn build_weights() {
+        let mut builder = BookBuilder::new();
+        assert_eq!(builder.add_pgn(GAMES.as_bytes()), 3);
+
+        let records = builder.records();
+        let start = Board::default().polyglot_key();
+        let e4: Vec<&BookRecord> = records.iter().filter(|r| r.key == start).collect();
+        assert_eq!(e4.len(), 1);
+        assert_eq!(e4[0].uci_move, "e2e4");
+        assert_eq!(e4[0].stats, MoveStats {wins: 1, draws: 1, losses: 1});
+        assert_eq!(e4[0].weight, 3);
+
+        // Black's e5 won once and lost once, while c5 drew.
+        let mut board = Board::default();
+        board.apply_uci_move("e2e4");
+        let book = builder.build();
+        let moves = book.moves(&board);
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves.iter().map(|&(_, w)| w).collect::<Vec<u16>>(), vec![2, 1]);
+        assert_eq!(book.best_move(&board).unwrap().stringify(), "e7e5");
+
+        let mut bytes: Vec<u8> = Vec::new();
+        builder.write_polyglot(&mut bytes).unwrap();
+        let read = PolyglotBook::from_bytes(&bytes);
+        assert_eq!(read.entries(), book.entries());
+        assert!(read.entries().windows(2).all(|w| w[0].key <= w[1].key));
+    }
+
+    #[test]
+    fn build_filters() {
+        let mut builder = BookBuilder::new();
+        builder.min_rating(2000).max_ply(1);
+        assert_eq!(builder.add_pgn(GAMES.as_bytes()), 1);
+        assert_eq!(builder.records().len(), 1);
+
+        let mut builder = BookBuilder::new();
+        builder.min_games(2).weights(1, 1, 1);
+        builder.add_pgn(GAMES.as_bytes());
+        let moves: Vec<String> = builder.records().into_iter().map(|r| r.uci_move).collect();
+        assert_eq!(moves, vec!["e2e4", "e7e5"]);
+
+        let mut csv: Vec<u8> = Vec::new();
+        builder.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 3);
+        assert!(csv.lines().nth(1).unwrap().ends_with(",rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1,e2e4,1,1,1,3"));
+
+        let mut json: Vec<u8> = Vec::new();
+        builder.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.starts_with("[\n  {\"key\": \"463b96181691fc9c\""));
+        assert!(json.contains("\"move\": \"e7e5\""));
+    }
+}
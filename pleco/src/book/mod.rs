@@ -0,0 +1,359 @@
+//! Module for reading opening books in the Polyglot format.
+//!
+//! A Polyglot book is a file of 16-byte entries, sorted by the key of the position each entry
+//! belongs to. Each entry holds a move playable from that position, along with a weight giving
+//! how often the move should be played.
+//!
+//! Polyglot hashes positions with its own random numbers, rather than the zobrist keys used by
+//! the rest of Pleco, so positions are looked up with `Board::polyglot_key()`.
+//!
+//! Books can be built from PGN games with the [`BookBuilder`].
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use pleco::Board;
+//! use pleco::book::PolyglotBook;
+//!
+//! let book = PolyglotBook::open("book.bin").unwrap();
+//! if let Some(mov) = book.weighted_move(&Board::default()) {
+//!     println!("book move: {}", mov);
+//! }
+//! ```
+//!
+//! [`BookBuilder`]: builder/struct.BookBuilder.html
+
+pub mod builder;
+mod keys;
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use rand;
+
+use board::Board;
+use core::piece_move::BitMove;
+use core::sq::{SQ, NO_SQ};
+use core::{Player, PieceType, CastleType};
+use helper::prelude::pawn_attacks_from;
+
+use self::keys::*;
+
+/// The size in bytes of a single entry of a Polyglot book.
+pub const ENTRY_SIZE: usize = 16;
+
+/// A single entry of a Polyglot book.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct BookEntry {
+    /// The Polyglot key of the position.
+    pub key: u64,
+    /// The move, in Polyglot's encoding. See `polyglot_move()`.
+    pub raw_move: u16,
+    /// How often the move should be played, relative to the other moves of the position.
+    pub weight: u16,
+    /// Learning data, unused by Pleco.
+    pub learn: u32,
+}
+
+impl BookEntry {
+    /// Reads an entry from its 16 big-endian bytes.
+    pub fn from_bytes(bytes: &[u8; ENTRY_SIZE]) -> BookEntry {
+        let read = |start: usize, len: usize| -> u64 {
+            bytes[start..start + len].iter().fold(0, |acc, b| (acc << 8) | u64::from(*b))
+        };
+        BookEntry {
+            key: read(0, 8),
+            raw_move: read(8, 2) as u16,
+            weight: read(10, 2) as u16,
+            learn: read(12, 4) as u32,
+        }
+    }
+
+    /// Returns the entry as its 16 big-endian bytes.
+    pub fn to_bytes(&self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0u8; ENTRY_SIZE];
+        {
+            let mut write = |start: usize, len: usize, value: u64| {
+                for i in 0..len {
+                    bytes[start + i] = (value >> (8 * (len - 1 - i))) as u8;
+                }
+            };
+            write(0, 8, self.key);
+            write(8, 2, u64::from(self.raw_move));
+            write(10, 2, u64::from(self.weight));
+            write(12, 4, u64::from(self.learn));
+        }
+        bytes
+    }
+}
+
+/// An opening book in the Polyglot format.
+///
+/// The whole book is read into memory, and moves are found with a binary search over the keys
+/// of the entries.
+pub struct PolyglotBook {
+    entries: Vec<BookEntry>,
+}
+
+impl PolyglotBook {
+    /// Reads a Polyglot book from a file.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<PolyglotBook> {
+        let mut bytes: Vec<u8> = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        Ok(PolyglotBook::from_bytes(&bytes))
+    }
+
+    /// Creates a book from the bytes of a Polyglot book. Any trailing bytes not making up a
+    /// full entry are ignored.
+    pub fn from_bytes(bytes: &[u8]) -> PolyglotBook {
+        let mut entries: Vec<BookEntry> = bytes.chunks(ENTRY_SIZE)
+            .filter(|chunk| chunk.len() == ENTRY_SIZE)
+            .map(|chunk| {
+                let mut entry = [0u8; ENTRY_SIZE];
+                entry.copy_from_slice(chunk);
+                BookEntry::from_bytes(&entry)
+            })
+            .collect();
+        // Books are written sorted, but a stable sort keeps a badly written book usable
+        // without reordering the moves of each position.
+        entries.sort_by_key(|e| e.key);
+        PolyglotBook {entries}
+    }
+
+    /// Creates a book from a list of entries.
+    pub fn from_entries(mut entries: Vec<BookEntry>) -> PolyglotBook {
+        entries.sort_by_key(|e| e.key);
+        PolyglotBook {entries}
+    }
+
+    /// Returns the number of entries in the book.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns if the book has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns all entries of the book, sorted by key.
+    pub fn entries(&self) -> &[BookEntry] {
+        &self.entries
+    }
+
+    /// Returns the entries of the book for a Polyglot key.
+    pub fn entries_for(&self, key: u64) -> &[BookEntry] {
+        let start: usize = match self.entries.binary_search_by(|e| e.key.cmp(&key)) {
+            Ok(mut i) => {
+                while i > 0 && self.entries[i - 1].key == key {
+                    i -= 1;
+                }
+                i
+            }
+            Err(_) => return &[],
+        };
+        let len: usize = self.entries[start..].iter().take_while(|e| e.key == key).count();
+        &self.entries[start..start + len]
+    }
+
+    /// Returns the legal moves the book has for the position, along with their weights.
+    ///
+    /// Entries with moves that cannot be played in the position, such as those from a
+    /// colliding key, are skipped.
+    pub fn moves(&self, board: &Board) -> Vec<(BitMove, u16)> {
+        self.entries_for(board.polyglot_key())
+            .iter()
+            .filter_map(|e| polyglot_move(board, e.raw_move).map(|m| (m, e.weight)))
+            .collect()
+    }
+
+    /// Returns the book move with the highest weight, if the position is in the book.
+    pub fn best_move(&self, board: &Board) -> Option<BitMove> {
+        let moves = self.moves(board);
+        let mut best: Option<(BitMove, u16)> = None;
+        for &(mov, weight) in moves.iter() {
+            if best.map_or(true, |(_, w)| weight > w) {
+                best = Some((mov, weight));
+            }
+        }
+        best.map(|(mov, _)| mov)
+    }
+
+    /// Returns a random book move, with each move chosen in proportion to its weight.
+    ///
+    /// Moves with a weight of zero are never chosen, unless every move of the position has
+    /// a weight of zero, in which case the first move is returned.
+    pub fn weighted_move(&self, board: &Board) -> Option<BitMove> {
+        pick_weighted(&self.moves(board), rand::random::<u32>())
+    }
+}
+
+/// Picks a move in proportion to its weight, using `rand` as the source of randomness.
+fn pick_weighted(moves: &[(BitMove, u16)], rand: u32) -> Option<BitMove> {
+    let total: u32 = moves.iter().map(|&(_, w)| u32::from(w)).sum();
+    if total == 0 {
+        return moves.first().map(|&(mov, _)| mov);
+    }
+    let mut pick: u32 = rand % total;
+    for &(mov, weight) in moves.iter() {
+        if pick < u32::from(weight) {
+            return Some(mov);
+        }
+        pick -= u32::from(weight);
+    }
+    None
+}
+
+/// Returns the Polyglot key of a position.
+///
+/// This is used by `Board::polyglot_key()`.
+pub fn polyglot_key(board: &Board) -> u64 {
+    let mut key: u64 = 0;
+
+    for i in 0..64 {
+        let sq = SQ(i);
+        if let (Some(player), Some(piece)) = (board.player_at_sq(sq), board.piece_at_sq(sq)) {
+            // Polyglot orders its pieces as black pawn, white pawn, black knight, and so on
+            let kind: usize = 2 * piece as usize + if player == Player::White { 1 } else { 0 };
+            key ^= POLYGLOT_RANDOM[PIECE_SQUARE_OFFSET + 64 * kind + i as usize];
+        }
+    }
+
+    let castles = [(Player::White, CastleType::KingSide), (Player::White, CastleType::QueenSide),
+                   (Player::Black, CastleType::KingSide), (Player::Black, CastleType::QueenSide)];
+    for (i, &(player, side)) in castles.iter().enumerate() {
+        if board.can_castle(player, side) {
+            key ^= POLYGLOT_RANDOM[CASTLE_OFFSET + i];
+        }
+    }
+
+    // The en-passant square only counts if a pawn of the side to move can capture onto it.
+    let ep_sq: SQ = board.ep_square();
+    if ep_sq != NO_SQ {
+        let them: Player = board.turn().other_player();
+        if (pawn_attacks_from(ep_sq, them) & board.piece_bb(board.turn(), PieceType::P)).is_not_empty() {
+            key ^= POLYGLOT_RANDOM[EN_PASSANT_OFFSET + ep_sq.file() as usize];
+        }
+    }
+
+    if board.turn() == Player::White {
+        key ^= POLYGLOT_RANDOM[TURN_OFFSET];
+    }
+    key
+}
+
+/// Converts a move in Polyglot's encoding to the matching legal move of the position, if there
+/// is one.
+///
+/// Polyglot packs the destination square in the lowest six bits, the source square in the next
+/// six, and the promotion piece in the three above those. Castling is written as the king
+/// capturing its own rook, which is also how a `BitMove` castles.
+pub fn polyglot_move(board: &Board, raw_move: u16) -> Option<BitMove> {
+    let dst = SQ((raw_move & 0b11_1111) as u8);
+    let src = SQ(((raw_move >> 6) & 0b11_1111) as u8);
+    let promo: Option<PieceType> = match (raw_move >> 12) & 0b111 {
+        0 => None,
+        1 => Some(PieceType::N),
+        2 => Some(PieceType::B),
+        3 => Some(PieceType::R),
+        4 => Some(PieceType::Q),
+        _ => return None,
+    };
+
+    board.generate_moves().iter()
+        .find(|m| m.get_src() == src && m.get_dest() == dst
+            && (if m.is_promo() { Some(m.promo_piece()) } else { None }) == promo)
+        .cloned()
+}
+
+/// Converts a move to Polyglot's encoding.
+pub fn to_polyglot_move(mov: BitMove) -> u16 {
+    let promo: u16 = if mov.is_promo() {
+        match mov.promo_piece() {
+            PieceType::N => 1,
+            PieceType::B => 2,
+            PieceType::R => 3,
+            _ => 4,
+        }
+    } else {
+        0
+    };
+    (promo << 12) | (u16::from(mov.get_src().0) << 6) | u16::from(mov.get_dest().0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test positions from the Polyglot book format specification.
+    const KEYS: [(&str, u64); 9] = [
+        ("", 0x463b96181691fc9c),
+        ("e2e4", 0x823c9b50fd114196),
+        ("e2e4 d7d5", 0x0756b94461c50fb0),
+        ("e2e4 d7d5 e4e5", 0x662fafb965db29d4),
+        ("e2e4 d7d5 e4e5 f7f5", 0x22a48b5a8e47ff78),
+        ("e2e4 d7d5 e4e5 f7f5 e1e2", 0x652a607ca3f242c1),
+        ("e2e4 d7d5 e4e5 f7f5 e1e2 e8f7", 0x00fdd303c946bdd9),
+        ("a2a4 b7b5 h2h4 b5b4 c2c4", 0x3c8123ea7b067637),
+        ("a2a4 b7b5 h2h4 b5b4 c2c4 b4c3 a1a3", 0x5c3f9b829b279560),
+    ];
+
+    #[test]
+    fn polyglot_keys() {
+        for &(moves, key) in KEYS.iter() {
+            let mut board = Board::default();
+            for mov in moves.split_whitespace() {
+                assert!(board.apply_uci_move(mov));
+            }
+            assert_eq!(board.polyglot_key(), key, "{}", moves);
+            let from_fen = Board::new_from_fen(&board.get_fen()).unwrap();
+            assert_eq!(from_fen.polyglot_key(), key);
+        }
+    }
+
+    #[test]
+    fn book_moves() {
+        let board = Board::default();
+        let key: u64 = board.polyglot_key();
+        let e2e4: u16 = (12 << 6) | 28;
+        let d2d4: u16 = (11 << 6) | 27;
+        let entries = vec![
+            BookEntry {key: key ^ 1, raw_move: e2e4, weight: 50, learn: 0},
+            BookEntry {key, raw_move: d2d4, weight: 10, learn: 0},
+            BookEntry {key, raw_move: e2e4, weight: 30, learn: 0},
+            BookEntry {key, raw_move: (12 << 6) | 36, weight: 90, learn: 0},
+        ];
+        let bytes: Vec<u8> = entries.iter().flat_map(|e| e.to_bytes().to_vec()).collect();
+        let book = PolyglotBook::from_bytes(&bytes);
+        assert_eq!(book.len(), 4);
+        assert_eq!(book.entries_for(key).len(), 3);
+
+        // e2e5 is not a legal move, so is skipped.
+        let moves = book.moves(&board);
+        assert_eq!(moves.len(), 2);
+        assert_eq!(book.best_move(&board).unwrap().stringify(), "e2e4");
+
+        for i in 0..40 {
+            let picked = pick_weighted(&moves, i).unwrap().stringify();
+            assert_eq!(picked, if i < 10 { "d2d4" } else { "e2e4" });
+        }
+        assert!(book.best_move(&Board::new_from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap()).is_none());
+    }
+
+    #[test]
+    fn castling_moves() {
+        let board = Board::new_from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        // e1h1 and e1a1
+        let short = polyglot_move(&board, (4 << 6) | 7).unwrap();
+        let long = polyglot_move(&board, (4 << 6) | 0).unwrap();
+        assert!(short.is_king_castle());
+        assert!(long.is_queen_castle());
+        assert_eq!(to_polyglot_move(short), (4 << 6) | 7);
+
+        let board = Board::new_from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let promo = polyglot_move(&board, (1 << 12) | (49 << 6) | 57).unwrap();
+        assert_eq!(promo.promo_piece(), PieceType::N);
+        assert_eq!(to_polyglot_move(promo), (1 << 12) | (49 << 6) | 57);
+    }
+}
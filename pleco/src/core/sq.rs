@@ -392,7 +392,7 @@ impl SQ {
 
 impl fmt::Display for SQ {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.pad(&self.to_string())
+        f.pad(&(*self).to_string())
     }
 }
 
@@ -102,6 +102,8 @@ extern crate test;
 
 pub mod core;
 pub mod board;
+pub mod book;
+pub mod tablebase;
 pub mod bots;
 pub mod bot_prelude;
 pub mod helper;
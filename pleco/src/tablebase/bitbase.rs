@@ -0,0 +1,807 @@
+//! Win / draw / loss bitbases for small sets of material, generated by retrograde analysis.
+//!
+//! A [`Bitbase`] holds the perfect result of every position of a set of material, such as
+//! `KPK` or `KQKR`, with up to four pieces including the kings. Unlike the Syzygy tables, a
+//! bitbase needs no external files: it is generated in memory from the move generator, and can
+//! then be saved to a compact file holding two bits per position.
+//!
+//! Generation starts from the checkmates and the captures and promotions leading to smaller
+//! sets of material, and works backwards until every position is resolved. The positions left
+//! over are draws. The fifty-move rule, castling and en-passant captures are not taken into
+//! account.
+//!
+//! # Examples
+//!
+//! ```
+//! use pleco::Board;
+//! use pleco::tablebase::Wdl;
+//! use pleco::tablebase::bitbase::Bitbase;
+//!
+//! let kpk = Bitbase::generate("KPK").unwrap();
+//!
+//! let board = Board::new_from_fen("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1").unwrap();
+//! assert_eq!(kpk.probe(&board), Some(Wdl::Loss));
+//!
+//! let board = Board::new_from_fen("k7/8/8/8/8/8/P7/7K w - - 0 1").unwrap();
+//! assert_eq!(kpk.probe(&board), Some(Wdl::Draw));
+//! ```
+//!
+//! [`Bitbase`]: struct.Bitbase.html
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write, BufReader, BufWriter};
+use std::path::Path;
+
+use board::Board;
+use board::builder::BoardBuilder;
+use core::{Player, PieceType, CastleType};
+use core::sq::SQ;
+use core::bitboard::BitBoard;
+use helper::Helper;
+
+use super::Wdl;
+
+/// The most pieces, kings included, a bitbase can be generated for.
+pub const MAX_BITBASE_PIECES: usize = 4;
+
+const MAGIC: &[u8; 4] = b"PLBB";
+const VERSION: u8 = 1;
+
+// The value of a position, as stored in two bits. Unused indexes are either illegal positions,
+// or positions that are stored under a symmetrical index.
+const UNUSED: u8 = 0;
+const LOSS: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 3;
+// Only used while generating, for positions whose value is not known yet.
+const PENDING: u8 = 4;
+
+// Set in the move counter of a pending position when a capture or promotion draws.
+const CONVERSION_DRAW: u8 = 0x80;
+
+/// The squares the first king is mapped to for sets of material without pawns.
+const KING_TRIANGLE: [u8; 10] = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27];
+
+type Squares = [u8; MAX_BITBASE_PIECES];
+
+/// Represents possible Errors encountered while generating or reading a `Bitbase`.
+pub enum BitbaseError {
+    /// The material signature could not be parsed.
+    InvalidSignature(String),
+    /// The material signature has more pieces than `MAX_BITBASE_PIECES`.
+    TooManyPieces(usize),
+    /// The file is not a bitbase, or is truncated.
+    InvalidFile,
+    Io(io::Error),
+}
+
+impl From<io::Error> for BitbaseError {
+    fn from(err: io::Error) -> BitbaseError {
+        BitbaseError::Io(err)
+    }
+}
+
+impl fmt::Debug for BitbaseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BitbaseError::InvalidSignature(ref s) => write!(f, "invalid material signature \"{}\"", s),
+            BitbaseError::TooManyPieces(n) => write!(f, "too many pieces: {}, maximum is {}", n, MAX_BITBASE_PIECES),
+            BitbaseError::InvalidFile => write!(f, "not a valid bitbase file"),
+            BitbaseError::Io(ref err) => write!(f, "io error, {:?}", err),
+        }
+    }
+}
+
+fn piece_from_char(c: char) -> Option<PieceType> {
+    match c {
+        'P' => Some(PieceType::P),
+        'N' => Some(PieceType::N),
+        'B' => Some(PieceType::B),
+        'R' => Some(PieceType::R),
+        'Q' => Some(PieceType::Q),
+        'K' => Some(PieceType::K),
+        _ => None
+    }
+}
+
+/// The pieces of a set of material, in index order: the white king, the other white pieces
+/// from the most valuable, then the black king and the other black pieces.
+#[derive(Clone, PartialEq, Debug)]
+struct Layout {
+    pieces: Vec<(Player, PieceType)>,
+    has_pawns: bool,
+}
+
+impl Layout {
+    /// Parses a signature such as "KQKR" or "KQvKR", the white pieces coming first.
+    fn parse(signature: &str) -> Result<Layout, BitbaseError> {
+        let err = || BitbaseError::InvalidSignature(signature.to_string());
+        let chars: Vec<char> = signature.trim().chars().filter(|c| *c != 'v').collect();
+        if chars.first() != Some(&'K') {
+            return Err(err());
+        }
+        let split = match chars.iter().skip(1).position(|c| *c == 'K') {
+            Some(i) => i + 1,
+            None => return Err(err())
+        };
+        let mut sides: [Vec<PieceType>; 2] = [Vec::new(), Vec::new()];
+        for (i, c) in chars.iter().enumerate() {
+            let player = if i < split { 0 } else { 1 };
+            match piece_from_char(*c) {
+                Some(PieceType::K) if i == 0 || i == split => {},
+                Some(PieceType::K) | None => return Err(err()),
+                Some(piece) => sides[player].push(piece),
+            }
+        }
+        Layout::from_pieces(&sides[0], &sides[1])
+    }
+
+    /// Creates the layout of the given non-king pieces of each side.
+    fn from_pieces(white: &[PieceType], black: &[PieceType]) -> Result<Layout, BitbaseError> {
+        let count = white.len() + black.len() + 2;
+        if count > MAX_BITBASE_PIECES {
+            return Err(BitbaseError::TooManyPieces(count));
+        }
+        let mut pieces = Vec::with_capacity(count);
+        for &(player, side) in [(Player::White, white), (Player::Black, black)].iter() {
+            let mut side = side.to_vec();
+            side.sort_by(|a, b| (*b as u8).cmp(&(*a as u8)));
+            pieces.push((player, PieceType::K));
+            pieces.extend(side.into_iter().map(|p| (player, p)));
+        }
+        let has_pawns = pieces.iter().any(|&(_, p)| p == PieceType::P);
+        Ok(Layout { pieces, has_pawns })
+    }
+
+    /// Returns the non-king pieces of a player.
+    fn side(&self, player: Player) -> Vec<PieceType> {
+        self.pieces.iter()
+            .filter(|&&(pl, p)| pl == player && p != PieceType::K)
+            .map(|&(_, p)| p)
+            .collect()
+    }
+
+    fn signature(&self) -> String {
+        self.pieces.iter().map(|&(_, p)| p.char_upper()).collect()
+    }
+
+    /// The same material, with the colors swapped.
+    fn flipped(&self) -> Layout {
+        Layout::from_pieces(&self.side(Player::Black), &self.side(Player::White)).unwrap()
+    }
+
+    /// The sets of material that can be reached by a single capture or promotion.
+    fn conversions(&self) -> Vec<Layout> {
+        let sides = [self.side(Player::White), self.side(Player::Black)];
+        let mut layouts: Vec<Layout> = Vec::new();
+        for us in 0..2 {
+            let them = 1 - us;
+            // One of our pieces is captured.
+            for i in 0..sides[us].len() {
+                let mut ours = sides[us].clone();
+                ours.remove(i);
+                layouts.push(Layout::with_sides(us, ours, sides[them].clone()));
+            }
+            // One of our pawns promotes, possibly while capturing.
+            if let Some(pawn) = sides[us].iter().position(|p| *p == PieceType::P) {
+                for promo in &[PieceType::Q, PieceType::R, PieceType::B, PieceType::N] {
+                    let mut ours = sides[us].clone();
+                    ours[pawn] = *promo;
+                    layouts.push(Layout::with_sides(us, ours.clone(), sides[them].clone()));
+                    for i in 0..sides[them].len() {
+                        let mut theirs = sides[them].clone();
+                        theirs.remove(i);
+                        layouts.push(Layout::with_sides(us, ours.clone(), theirs));
+                    }
+                }
+            }
+        }
+        layouts.retain(|l| l.pieces.len() > 2);
+        layouts.dedup();
+        layouts
+    }
+
+    fn with_sides(us: usize, ours: Vec<PieceType>, theirs: Vec<PieceType>) -> Layout {
+        if us == 0 {
+            Layout::from_pieces(&ours, &theirs).unwrap()
+        } else {
+            Layout::from_pieces(&theirs, &ours).unwrap()
+        }
+    }
+
+    fn king_squares(&self) -> usize {
+        if self.has_pawns { 32 } else { KING_TRIANGLE.len() }
+    }
+
+    /// The number of indexes of the layout.
+    fn len(&self) -> usize {
+        self.king_squares() * (1 << (6 * (self.pieces.len() - 1))) * 2
+    }
+
+    fn index(&self, squares: &Squares, turn: Player) -> usize {
+        let ksq = squares[0];
+        let mut idx = if self.has_pawns {
+            ((ksq >> 3) * 4 + (ksq & 7)) as usize
+        } else {
+            KING_TRIANGLE.iter().position(|s| *s == ksq).unwrap()
+        };
+        for sq in squares.iter().take(self.pieces.len()).skip(1) {
+            idx = idx * 64 + *sq as usize;
+        }
+        idx * 2 + turn as usize
+    }
+
+    fn decode(&self, mut idx: usize) -> (Squares, Player) {
+        let turn = if idx & 1 == 0 { Player::White } else { Player::Black };
+        idx >>= 1;
+        let mut squares: Squares = [0; MAX_BITBASE_PIECES];
+        for i in (1..self.pieces.len()).rev() {
+            squares[i] = (idx & 63) as u8;
+            idx >>= 6;
+        }
+        squares[0] = if self.has_pawns {
+            ((idx / 4) * 8 + idx % 4) as u8
+        } else {
+            KING_TRIANGLE[idx]
+        };
+        (squares, turn)
+    }
+
+    /// Maps a placement to the one stored in the bitbase, among its symmetrical placements.
+    ///
+    /// The board can be mirrored left to right, and without pawns also top to bottom and along
+    /// the a1-h8 diagonal. The first king is brought to the a1-d1-d4 triangle, or to the a-d
+    /// files with pawns. The squares of identical pieces are sorted, and the smallest placement
+    /// left is kept.
+    fn canonical(&self, squares: &Squares) -> Squares {
+        let n = self.pieces.len();
+        let transforms = if self.has_pawns { 2 } else { 8 };
+        let mut best: Option<Squares> = None;
+        for t in 0..transforms {
+            let mut mapped: Squares = [0; MAX_BITBASE_PIECES];
+            for i in 0..n {
+                let mut sq = squares[i];
+                if t & 1 != 0 {
+                    sq ^= 7;
+                }
+                if t & 2 != 0 {
+                    sq ^= 56;
+                }
+                if t & 4 != 0 {
+                    sq = ((sq & 7) << 3) | (sq >> 3);
+                }
+                mapped[i] = sq;
+            }
+            let (file, rank) = (mapped[0] & 7, mapped[0] >> 3);
+            if file > 3 || (!self.has_pawns && (rank > 3 || rank > file)) {
+                continue;
+            }
+            self.sort_identical(&mut mapped);
+            if best.map_or(true, |b| mapped[..n] < b[..n]) {
+                best = Some(mapped);
+            }
+        }
+        best.unwrap()
+    }
+
+    fn sort_identical(&self, squares: &mut Squares) {
+        let n = self.pieces.len();
+        let mut start = 1;
+        while start < n {
+            let mut end = start + 1;
+            while end < n && self.pieces[end] == self.pieces[start] {
+                end += 1;
+            }
+            squares[start..end].sort();
+            start = end;
+        }
+    }
+
+    /// Reads the placement of a board, if it has exactly this material. The colors are
+    /// swapped if needed, returning the placement and side to move after swapping.
+    fn placement(&self, board: &Board) -> Option<(Squares, Player)> {
+        if board.count_all_pieces() as usize != self.pieces.len() {
+            return None;
+        }
+        for &flip in &[false, true] {
+            let mut squares: Squares = [0; MAX_BITBASE_PIECES];
+            let mut i = 0;
+            while i < self.pieces.len() {
+                let (player, piece) = self.pieces[i];
+                let player = if flip { player.other_player() } else { player };
+                let mut bb: BitBoard = board.piece_bb(player, piece);
+                let end = (i..self.pieces.len()).find(|j| self.pieces[*j] != self.pieces[i])
+                    .unwrap_or_else(|| self.pieces.len());
+                if bb.count_bits() as usize != end - i {
+                    break;
+                }
+                while let Some(sq) = bb.pop_some_lsb() {
+                    squares[i] = if flip { sq.0 ^ 56 } else { sq.0 };
+                    i += 1;
+                }
+            }
+            if i == self.pieces.len() {
+                let turn = if flip { board.turn().other_player() } else { board.turn() };
+                return Some((squares, turn));
+            }
+        }
+        None
+    }
+}
+
+/// The win / draw / loss value of every position of a set of material.
+pub struct Bitbase {
+    layout: Layout,
+    data: Vec<u8>,
+}
+
+impl Bitbase {
+    /// Generates the bitbase of a material signature, such as "KPK" or "KQKR". The pieces of
+    /// the stronger side are usually written first, but any split works.
+    ///
+    /// The bitbases of the material reached by captures and promotions are generated as well,
+    /// and then dropped. Use `Bitbases::generate()` to keep them.
+    pub fn generate(signature: &str) -> Result<Bitbase, BitbaseError> {
+        let layout = Layout::parse(signature)?;
+        let mut bitbases = Bitbases::new();
+        bitbases.generate(signature)?;
+        Ok(bitbases.bitbases.remove(&layout.signature()).unwrap())
+    }
+
+    /// Returns the material signature of the bitbase, such as "KQKR".
+    pub fn signature(&self) -> String {
+        self.layout.signature()
+    }
+
+    /// Returns the number of indexes of the bitbase, counting illegal and symmetrical
+    /// positions.
+    pub fn len(&self) -> usize {
+        self.layout.len()
+    }
+
+    /// Returns the number of distinct positions with the given value. `Wdl::CursedWin` and
+    /// `Wdl::BlessedLoss` are never stored.
+    pub fn count(&self, wdl: Wdl) -> usize {
+        let value = match wdl {
+            Wdl::Loss => LOSS,
+            Wdl::Draw => DRAW,
+            Wdl::Win => WIN,
+            _ => return 0
+        };
+        (0..self.len()).filter(|i| self.value(*i) == value).count()
+    }
+
+    /// Returns the value of a position for the side to move, if the position has the same
+    /// material as the bitbase, with either color, and no castling rights.
+    pub fn probe(&self, board: &Board) -> Option<Wdl> {
+        if [Player::White, Player::Black].iter().any(|p|
+                board.can_castle(*p, CastleType::KingSide) || board.can_castle(*p, CastleType::QueenSide)) {
+            return None;
+        }
+        let (squares, turn) = self.layout.placement(board)?;
+        let idx = self.layout.index(&self.layout.canonical(&squares), turn);
+        match self.value(idx) {
+            LOSS => Some(Wdl::Loss),
+            DRAW => Some(Wdl::Draw),
+            WIN => Some(Wdl::Win),
+            _ => None
+        }
+    }
+
+    /// Writes the bitbase in its compact format.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let signature = self.signature();
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION, signature.len() as u8])?;
+        writer.write_all(signature.as_bytes())?;
+        writer.write_all(&self.data)
+    }
+
+    /// Reads a bitbase written by `Bitbase::write()`.
+    pub fn read<R: Read>(mut reader: R) -> Result<Bitbase, BitbaseError> {
+        let mut header = [0u8; 6];
+        reader.read_exact(&mut header)?;
+        if &header[0..4] != MAGIC || header[4] != VERSION {
+            return Err(BitbaseError::InvalidFile);
+        }
+        let mut signature = vec![0u8; header[5] as usize];
+        reader.read_exact(&mut signature)?;
+        let signature = String::from_utf8(signature).map_err(|_| BitbaseError::InvalidFile)?;
+        let layout = Layout::parse(&signature)?;
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        if data.len() != (layout.len() + 3) / 4 {
+            return Err(BitbaseError::InvalidFile);
+        }
+        Ok(Bitbase { layout, data })
+    }
+
+    /// Saves the bitbase to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Opens a bitbase file saved by `Bitbase::save()`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Bitbase, BitbaseError> {
+        Bitbase::read(BufReader::new(File::open(path)?))
+    }
+
+    #[inline]
+    fn value(&self, idx: usize) -> u8 {
+        (self.data[idx / 4] >> ((idx % 4) * 2)) & 3
+    }
+}
+
+/// A set of bitbases, probed by the material of a position.
+pub struct Bitbases {
+    bitbases: HashMap<String, Bitbase>,
+}
+
+impl Default for Bitbases {
+    fn default() -> Self {
+        Bitbases::new()
+    }
+}
+
+impl Bitbases {
+    /// Creates an empty set of bitbases.
+    pub fn new() -> Bitbases {
+        Bitbases { bitbases: HashMap::new() }
+    }
+
+    /// Returns the number of bitbases in the set.
+    pub fn len(&self) -> usize {
+        self.bitbases.len()
+    }
+
+    /// Returns if the set holds no bitbases.
+    pub fn is_empty(&self) -> bool {
+        self.bitbases.is_empty()
+    }
+
+    /// Adds a bitbase to the set, replacing any bitbase of the same material.
+    pub fn insert(&mut self, bitbase: Bitbase) {
+        self.bitbases.insert(bitbase.signature(), bitbase);
+    }
+
+    /// Returns the bitbase of a material signature, with either color.
+    pub fn get(&self, signature: &str) -> Option<&Bitbase> {
+        let layout = Layout::parse(signature).ok()?;
+        self.find(&layout)
+    }
+
+    /// Generates the bitbase of a material signature, along with the bitbases of the
+    /// material reached by captures and promotions. Bitbases already in the set are kept.
+    pub fn generate(&mut self, signature: &str) -> Result<&Bitbase, BitbaseError> {
+        let layout = Layout::parse(signature)?;
+        self.generate_layout(&layout);
+        Ok(self.bitbases.get(&layout.signature()).unwrap())
+    }
+
+    /// Returns the value of a position for the side to move, if the set holds its material.
+    /// Positions with only the kings are always drawn.
+    pub fn probe(&self, board: &Board) -> Option<Wdl> {
+        if board.count_all_pieces() == 2 {
+            return Some(Wdl::Draw);
+        }
+        self.bitbases.values().filter_map(|b| b.probe(board)).next()
+    }
+
+    fn find(&self, layout: &Layout) -> Option<&Bitbase> {
+        self.bitbases.get(&layout.signature())
+            .or_else(|| self.bitbases.get(&layout.flipped().signature()))
+    }
+
+    fn generate_layout(&mut self, layout: &Layout) {
+        if self.bitbases.contains_key(&layout.signature()) {
+            return;
+        }
+        for sub in layout.conversions() {
+            if self.find(&sub).is_none() {
+                self.generate_layout(&sub);
+            }
+        }
+        let bitbase = Generator::new(layout.clone(), self).run();
+        self.insert(bitbase);
+    }
+}
+
+/// Generates a single bitbase, given the bitbases of the material it converts to.
+struct Generator<'a> {
+    layout: Layout,
+    subs: &'a Bitbases,
+    helper: Helper,
+    states: Vec<u8>,
+    // The number of distinct positions reached by moves that are not yet known to win for the
+    // opponent, along with `CONVERSION_DRAW`.
+    counters: Vec<u8>,
+    queue: Vec<usize>,
+}
+
+impl<'a> Generator<'a> {
+    fn new(layout: Layout, subs: &'a Bitbases) -> Generator<'a> {
+        let len = layout.len();
+        Generator {
+            layout,
+            subs,
+            helper: Helper::new(),
+            states: vec![UNUSED; len],
+            counters: vec![0; len],
+            queue: Vec::new(),
+        }
+    }
+
+    fn run(mut self) -> Bitbase {
+        for idx in 0..self.layout.len() {
+            self.init_position(idx);
+        }
+        while let Some(idx) = self.queue.pop() {
+            self.propagate(idx);
+        }
+
+        let mut data = vec![0u8; (self.layout.len() + 3) / 4];
+        for (idx, state) in self.states.iter().enumerate() {
+            let value = if *state == PENDING { DRAW } else { *state };
+            data[idx / 4] |= value << ((idx % 4) * 2);
+        }
+        Bitbase { layout: self.layout, data }
+    }
+
+    fn resolve(&mut self, idx: usize, value: u8) {
+        self.states[idx] = value;
+        if value != DRAW {
+            self.queue.push(idx);
+        }
+    }
+
+    /// Builds the position of an index, to find the checkmates, the results of captures and
+    /// promotions, and the positions reached by the other moves.
+    fn init_position(&mut self, idx: usize) {
+        let (squares, turn) = self.layout.decode(idx);
+        let n = self.layout.pieces.len();
+        if self.layout.canonical(&squares) != squares {
+            return;
+        }
+        let mut builder = BoardBuilder::new();
+        builder.turn(turn);
+        for i in 0..n {
+            let (player, piece) = self.layout.pieces[i];
+            let sq = SQ(squares[i]);
+            if builder.piece_at(sq).is_some() || (piece == PieceType::P && (sq.0 < 8 || sq.0 >= 56)) {
+                return;
+            }
+            builder.place(sq, player, piece);
+        }
+        let mut board = match builder.build() {
+            Ok(board) => board,
+            Err(_) => return
+        };
+
+        let moves = board.generate_moves();
+        if moves.is_empty() {
+            let value = if board.in_check() { LOSS } else { DRAW };
+            self.resolve(idx, value);
+            return;
+        }
+
+        let mut conversion = Wdl::Loss;
+        let mut successors: Vec<usize> = Vec::with_capacity(moves.len());
+        for mov in moves.iter() {
+            if mov.is_capture() || mov.is_promo() {
+                board.apply_move(*mov);
+                let wdl = self.subs.probe(&board).expect("missing bitbase");
+                board.undo_move();
+                conversion = conversion.max(-wdl);
+            } else {
+                let mut next = squares;
+                let i = (0..n).find(|i| next[*i] == mov.get_src_u8()).unwrap();
+                next[i] = mov.get_dest_u8();
+                successors.push(self.layout.index(&self.layout.canonical(&next), turn.other_player()));
+            }
+        }
+        successors.sort();
+        successors.dedup();
+
+        if conversion == Wdl::Win {
+            self.resolve(idx, WIN);
+        } else if successors.is_empty() {
+            self.resolve(idx, if conversion == Wdl::Draw { DRAW } else { LOSS });
+        } else {
+            self.states[idx] = PENDING;
+            self.counters[idx] = successors.len() as u8
+                | if conversion == Wdl::Draw { CONVERSION_DRAW } else { 0 };
+        }
+    }
+
+    /// Updates the positions that lead to a newly won or lost position.
+    fn propagate(&mut self, idx: usize) {
+        let value = self.states[idx];
+        for pred in self.predecessors(idx) {
+            if self.states[pred] != PENDING {
+                continue;
+            }
+            if value == LOSS {
+                self.resolve(pred, WIN);
+            } else {
+                self.counters[pred] -= 1;
+                if self.counters[pred] & !CONVERSION_DRAW == 0 {
+                    let result = if self.counters[pred] & CONVERSION_DRAW != 0 { DRAW } else { LOSS };
+                    self.resolve(pred, result);
+                }
+            }
+        }
+    }
+
+    /// Returns the distinct positions that reach a position by a quiet move, the pieces of
+    /// the side that just moved being moved back to empty squares.
+    fn predecessors(&self, idx: usize) -> Vec<usize> {
+        let (squares, turn) = self.layout.decode(idx);
+        let mover = turn.other_player();
+        let n = self.layout.pieces.len();
+        let occupied: BitBoard = squares.iter().take(n)
+            .fold(BitBoard(0), |bb, sq| bb | SQ(*sq).to_bb());
+
+        let mut preds: Vec<usize> = Vec::new();
+        for i in 0..n {
+            let (player, piece) = self.layout.pieces[i];
+            if player != mover {
+                continue;
+            }
+            let sq = SQ(squares[i]);
+            let mut targets: BitBoard = match piece {
+                PieceType::K => self.helper.king_moves(sq),
+                PieceType::N => self.helper.knight_moves(sq),
+                PieceType::B => self.helper.bishop_moves(occupied, sq),
+                PieceType::R => self.helper.rook_moves(occupied, sq),
+                PieceType::Q => self.helper.queen_moves(occupied, sq),
+                PieceType::P => pawn_origins(sq, player, occupied),
+            } & !occupied;
+            while let Some(from) = targets.pop_some_lsb() {
+                let mut prev = squares;
+                prev[i] = from.0;
+                preds.push(self.layout.index(&self.layout.canonical(&prev), mover));
+            }
+        }
+        preds.sort();
+        preds.dedup();
+        preds
+    }
+}
+
+/// Returns the squares a pawn can have been pushed from.
+fn pawn_origins(sq: SQ, player: Player, occupied: BitBoard) -> BitBoard {
+    let rank = player.relative_rank_of_sq(sq) as u8;
+    if rank < 2 {
+        return BitBoard(0);
+    }
+    let back = |s: u8| if player == Player::White { s - 8 } else { s + 8 };
+    let one = back(sq.0);
+    let mut origins = SQ(one).to_bb();
+    if rank == 3 && (SQ(one).to_bb() & occupied).is_empty() {
+        origins |= SQ(back(one)).to_bb();
+    }
+    origins
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(fen: &str) -> Board {
+        Board::new_from_fen(fen).unwrap()
+    }
+
+    #[test]
+    fn signatures() {
+        assert_eq!(Layout::parse("KQKR").unwrap().signature(), "KQKR");
+        assert_eq!(Layout::parse("KQvKR").unwrap().signature(), "KQKR");
+        assert_eq!(Layout::parse("KNBK").unwrap().signature(), "KBNK");
+        assert_eq!(Layout::parse("KKP").unwrap().signature(), "KKP");
+        assert_eq!(Layout::parse("KKP").unwrap().flipped().signature(), "KPK");
+        assert!(Layout::parse("QKK").is_err());
+        assert!(Layout::parse("KQK K").is_err());
+        assert!(Layout::parse("KQ").is_err());
+        assert!(Layout::parse("KQKQK").is_err());
+        assert!(Layout::parse("KQQKR").is_err());
+
+        let kpk = Layout::parse("KPK").unwrap();
+        let subs: Vec<String> = kpk.conversions().iter().map(|l| l.signature()).collect();
+        assert_eq!(subs, vec!["KQK", "KRK", "KBK", "KNK"]);
+    }
+
+    #[test]
+    fn index_round_trip() {
+        for sig in &["KRK", "KPK", "KRKN"] {
+            let layout = Layout::parse(sig).unwrap();
+            for idx in (0..layout.len()).step_by(997) {
+                let (squares, turn) = layout.decode(idx);
+                assert_eq!(layout.index(&squares, turn), idx);
+                let canonical = layout.canonical(&squares);
+                assert_eq!(layout.canonical(&canonical), canonical);
+            }
+        }
+    }
+
+    #[test]
+    fn krk() {
+        let krk = Bitbase::generate("KRK").unwrap();
+        assert_eq!(krk.probe(&board("4k3/8/8/8/8/8/8/R3K3 w - - 0 1")), Some(Wdl::Win));
+        assert_eq!(krk.probe(&board("4k3/8/8/8/8/8/8/R3K3 b - - 0 1")), Some(Wdl::Loss));
+        // The rook hangs, or is protected.
+        assert_eq!(krk.probe(&board("8/8/8/8/8/3K4/4r3/7k w - - 0 1")), Some(Wdl::Draw));
+        assert_eq!(krk.probe(&board("8/8/8/8/8/3k4/4r3/4K3 w - - 0 1")), Some(Wdl::Loss));
+        // Stalemate.
+        assert_eq!(krk.probe(&board("k7/8/K7/8/8/8/8/1R6 b - - 0 1")), Some(Wdl::Draw));
+        // Checkmate.
+        assert_eq!(krk.probe(&board("k7/2K5/8/8/8/8/8/R7 b - - 0 1")), Some(Wdl::Loss));
+        // Other material.
+        assert_eq!(krk.probe(&board("4k3/8/8/8/8/8/8/Q3K3 w - - 0 1")), None);
+        assert_eq!(krk.probe(&board("4k3/8/8/8/8/8/8/R3K2R w K - 0 1")), None);
+        assert!(krk.count(Wdl::Win) > krk.count(Wdl::Draw));
+    }
+
+    #[test]
+    fn kpk() {
+        let mut bitbases = Bitbases::new();
+        bitbases.generate("KPK").unwrap();
+        assert_eq!(bitbases.len(), 5);
+
+        let kpk = bitbases.get("KKP").unwrap();
+        // The king is in front of its pawn on the sixth rank.
+        assert_eq!(kpk.probe(&board("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1")), Some(Wdl::Win));
+        assert_eq!(kpk.probe(&board("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1")), Some(Wdl::Loss));
+        // Stalemate.
+        assert_eq!(kpk.probe(&board("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1")), Some(Wdl::Draw));
+        // A rook pawn with the defending king in front of it.
+        assert_eq!(kpk.probe(&board("k7/8/8/8/8/8/P7/7K w - - 0 1")), Some(Wdl::Draw));
+        // The pawn outruns the king.
+        assert_eq!(kpk.probe(&board("7k/8/8/8/P7/8/8/K7 w - - 0 1")), Some(Wdl::Win));
+        // Colors flipped.
+        assert_eq!(kpk.probe(&board("8/8/8/8/8/4k3/4p3/4K3 w - - 0 1")), Some(Wdl::Draw));
+        assert_eq!(kpk.probe(&board("8/8/8/8/4p3/4k3/8/4K3 b - - 0 1")), Some(Wdl::Win));
+        assert_eq!(bitbases.probe(&board("8/8/8/8/4p3/4k3/8/4K3 b - - 0 1")), Some(Wdl::Win));
+    }
+
+    #[test]
+    #[ignore]
+    fn four_men() {
+        let mut bitbases = Bitbases::new();
+        let kqkr = bitbases.generate("KQKR").unwrap();
+        assert_eq!(kqkr.probe(&board("8/8/8/3k4/8/8/2QK4/7r w - - 0 1")), Some(Wdl::Win));
+        // The queen hangs.
+        assert_eq!(kqkr.probe(&board("7r/8/8/8/8/2k5/1Q6/4K3 b - - 0 1")), Some(Wdl::Win));
+
+        let kbnk = Bitbase::generate("KBNK").unwrap();
+        assert_eq!(kbnk.probe(&board("8/8/8/4k3/8/8/8/2B1KN2 w - - 0 1")), Some(Wdl::Win));
+        assert_eq!(kbnk.probe(&board("8/8/8/4k3/8/8/8/2B1KN2 b - - 0 1")), Some(Wdl::Loss));
+        // Either piece hangs.
+        assert_eq!(kbnk.probe(&board("8/8/8/8/8/8/2k5/2BNK3 b - - 0 1")), Some(Wdl::Draw));
+    }
+
+    #[test]
+    fn insufficient_material() {
+        let knk = Bitbase::generate("KNK").unwrap();
+        assert_eq!(knk.count(Wdl::Win), 0);
+        assert_eq!(knk.count(Wdl::Loss), 0);
+        assert!(knk.count(Wdl::Draw) > 0);
+    }
+
+    #[test]
+    fn write_read() {
+        let krk = Bitbase::generate("KRK").unwrap();
+        let mut bytes: Vec<u8> = Vec::new();
+        krk.write(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 6 + 3 + krk.len() / 4);
+
+        let read = Bitbase::read(&bytes[..]).unwrap();
+        assert_eq!(read.signature(), "KRK");
+        assert!(read.data == krk.data);
+
+        assert!(Bitbase::read(&bytes[1..]).is_err());
+        assert!(Bitbase::read(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
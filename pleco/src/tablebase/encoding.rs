@@ -0,0 +1,143 @@
+//! Constant tables used to turn a position into an index of a Syzygy table.
+
+/// The most pieces a table can hold.
+pub const TB_PIECES: usize = 7;
+
+/// Index tables shared by every Syzygy table.
+pub struct Encoding {
+    /// Maps the squares a2-h7 to 0..47, giving the number of squares left for the other pawns
+    /// when the leading pawn is on that square.
+    pub map_pawns: [usize; 64],
+    /// Maps a square below the a1-h8 diagonal to 0..27.
+    pub map_b1h1h7: [usize; 64],
+    /// Maps a square of the a1-d1-d4 triangle to 0..9, with the diagonal squares last.
+    pub map_a1d1d4: [usize; 64],
+    /// Maps the 462 legal placements of two kings, the first one inside the a1-d1-d4 triangle.
+    pub map_kk: [[usize; 64]; 10],
+    /// `binomial[k][n]` is the number of ways to choose `k` elements from `n`.
+    pub binomial: [[u64; 64]; TB_PIECES],
+    /// Start index of each leading pawn square, by the number of leading pawns.
+    pub lead_pawn_idx: [[u64; 64]; TB_PIECES],
+    /// Number of placements of the leading pawns, by the number of leading pawns and by file.
+    pub lead_pawns_size: [[u64; 4]; TB_PIECES],
+}
+
+lazy_static! {
+    pub static ref ENCODING: Encoding = Encoding::new();
+}
+
+/// Returns how far a square is above the a1-h8 diagonal, negative if below it.
+#[inline]
+pub fn off_a1h8(sq: u8) -> i8 {
+    (sq >> 3) as i8 - (sq & 7) as i8
+}
+
+/// Returns the king distance between two squares.
+#[inline]
+pub fn distance(s1: u8, s2: u8) -> u8 {
+    let file = ((s1 & 7) as i8 - (s2 & 7) as i8).abs();
+    let rank = ((s1 >> 3) as i8 - (s2 >> 3) as i8).abs();
+    file.max(rank) as u8
+}
+
+impl Encoding {
+    fn new() -> Encoding {
+        let mut e = Encoding {
+            map_pawns: [0; 64],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            binomial: [[0; 64]; TB_PIECES],
+            lead_pawn_idx: [[0; 64]; TB_PIECES],
+            lead_pawns_size: [[0; 4]; TB_PIECES],
+        };
+
+        let mut code = 0;
+        for s in 0..64u8 {
+            if off_a1h8(s) < 0 {
+                e.map_b1h1h7[s as usize] = code;
+                code += 1;
+            }
+        }
+
+        // The triangle squares below the diagonal come first, then the ones on it.
+        let mut diagonal = Vec::new();
+        code = 0;
+        for rank in 0..4u8 {
+            for file in 0..4u8 {
+                let s = rank * 8 + file;
+                if off_a1h8(s) < 0 {
+                    e.map_a1d1d4[s as usize] = code;
+                    code += 1;
+                } else if off_a1h8(s) == 0 {
+                    diagonal.push(s);
+                }
+            }
+        }
+        for s in diagonal {
+            e.map_a1d1d4[s as usize] = code;
+            code += 1;
+        }
+
+        // If the first king is on the a1-d4 diagonal, the other one must not be above the
+        // a1-h8 diagonal. Placements with both kings on the diagonal come last.
+        let mut both_on_diagonal = Vec::new();
+        code = 0;
+        for idx in 0..10 {
+            for s1 in 0..28u8 {
+                if e.map_a1d1d4[s1 as usize] != idx || (idx == 0 && s1 != 1) {
+                    continue;
+                }
+                for s2 in 0..64u8 {
+                    if distance(s1, s2) <= 1 {
+                        continue;
+                    } else if off_a1h8(s1) == 0 && off_a1h8(s2) > 0 {
+                        continue;
+                    } else if off_a1h8(s1) == 0 && off_a1h8(s2) == 0 {
+                        both_on_diagonal.push((idx, s2));
+                    } else {
+                        e.map_kk[idx][s2 as usize] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, s2) in both_on_diagonal {
+            e.map_kk[idx][s2 as usize] = code;
+            code += 1;
+        }
+
+        e.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..TB_PIECES {
+                if k > n {
+                    break;
+                }
+                e.binomial[k][n] = if k > 0 { e.binomial[k - 1][n - 1] } else { 0 }
+                    + if k < n { e.binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        // The leading pawn is the one nearest the edge and, on the same file, the one with
+        // the lowest rank. There are 47 squares left for the other pawns when it is on a2,
+        // and two less for every rank it advances.
+        let mut available: i32 = 47;
+        for lead_pawns_cnt in 1..(TB_PIECES - 1) {
+            for file in 0..4u8 {
+                let mut idx = 0;
+                for rank in 1..7u8 {
+                    let sq = (rank * 8 + file) as usize;
+                    if lead_pawns_cnt == 1 {
+                        e.map_pawns[sq] = available as usize;
+                        e.map_pawns[sq ^ 7] = (available - 1) as usize;
+                        available -= 2;
+                    }
+                    e.lead_pawn_idx[lead_pawns_cnt][sq] = idx;
+                    idx += e.binomial[lead_pawns_cnt - 1][e.map_pawns[sq]];
+                }
+                e.lead_pawns_size[lead_pawns_cnt][file as usize] = idx;
+            }
+        }
+        e
+    }
+}
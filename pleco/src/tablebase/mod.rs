@@ -0,0 +1,571 @@
+//! Module for probing Syzygy endgame tablebases.
+//!
+//! Syzygy tablebases hold the perfect result of every position with few enough pieces. Each
+//! set of material has two files: a `.rtbw` file giving whether the side to move wins, draws
+//! or loses, and a `.rtbz` file giving the distance, in plies, to the next capture or pawn
+//! move (DTZ) that keeps that result.
+//!
+//! Both kinds of results take the fifty-move rule into account: a "cursed win" is a position
+//! that would be won, but only after the fifty-move rule allows the losing side to claim a
+//! draw. Likewise, a "blessed loss" is a loss that is saved by the fifty-move rule.
+//!
+//! Tables are found by [`init`], given the directories holding them, and are read on the first
+//! probe that needs them. Positions with castling rights are never found in the tables.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use pleco::Board;
+//! use pleco::tablebase::{self, Wdl};
+//!
+//! tablebase::init("/path/to/syzygy");
+//!
+//! let board = Board::new_from_fen("8/8/8/8/8/8/2Rk4/1K6 b - - 0 1").unwrap();
+//! assert_eq!(tablebase::probe_wdl(&board), Some(Wdl::Loss));
+//! ```
+//!
+//! [`init`]: fn.init.html
+
+pub mod bitbase;
+
+mod encoding;
+mod table;
+#[cfg(test)]
+mod writer;
+
+use std::collections::HashMap;
+use std::env;
+use std::ops::Neg;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use board::Board;
+use core::{Player, PieceType};
+
+use self::table::*;
+
+/// The win / draw / loss value of a position, from the view of the side to move.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub enum Wdl {
+    /// The side to move loses.
+    Loss,
+    /// The side to move loses, but can claim a draw by the fifty-move rule.
+    BlessedLoss,
+    /// The position is drawn.
+    Draw,
+    /// The side to move wins, but the other side can claim a draw by the fifty-move rule.
+    CursedWin,
+    /// The side to move wins.
+    Win,
+}
+
+impl Wdl {
+    /// Returns the value as an integer, from -2 for a loss to 2 for a win.
+    #[inline]
+    pub fn to_i32(self) -> i32 {
+        self as i32 - 2
+    }
+
+    /// Creates a value from an integer between -2 and 2.
+    #[inline]
+    pub fn from_i32(value: i32) -> Wdl {
+        match value {
+            -2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+
+    /// Returns 1 for a (cursed) win, -1 for a (blessed) loss, and 0 for a draw.
+    #[inline]
+    pub fn signum(self) -> i32 {
+        self.to_i32().signum()
+    }
+}
+
+impl Neg for Wdl {
+    type Output = Wdl;
+
+    fn neg(self) -> Wdl {
+        Wdl::from_i32(-self.to_i32())
+    }
+}
+
+/// Returns the DTZ of a position whose best move is a capture or pawn move, given its WDL
+/// value. DTZ tables do not hold values for such positions.
+#[inline]
+pub fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+        Wdl::Draw => 0,
+    }
+}
+
+/// A table found on disk, along with its files once they are read.
+struct TableEntry {
+    info: TableInfo,
+    wdl: RwLock<Option<Option<Arc<Table>>>>,
+    dtz: RwLock<Option<Option<Arc<Table>>>>,
+}
+
+/// A set of Syzygy tables, found in a list of directories.
+pub struct Tablebases {
+    paths: Vec<PathBuf>,
+    entries: HashMap<u64, Arc<TableEntry>>,
+    count: usize,
+    max_pieces: usize,
+}
+
+impl Tablebases {
+    /// Creates a set of tablebases with no tables.
+    pub fn empty() -> Tablebases {
+        Tablebases {
+            paths: Vec::new(),
+            entries: HashMap::new(),
+            count: 0,
+            max_pieces: 0,
+        }
+    }
+
+    /// Finds the tables in a list of directories, separated by `:` (or `;` on Windows). The
+    /// UCI default of "<empty>" gives no tables.
+    ///
+    /// Only the existence of the WDL files is checked, the tables themselves are read once
+    /// they are first needed.
+    pub fn new(path: &str) -> Tablebases {
+        let mut tablebases = Tablebases::empty();
+        let path = path.trim();
+        if path.is_empty() || path == "<empty>" {
+            return tablebases;
+        }
+        tablebases.paths = env::split_paths(path).collect();
+
+        for name in table_names() {
+            if tablebases.find_file(&name, TableType::Wdl).is_none() {
+                continue;
+            }
+            if let Some(info) = TableInfo::from_name(&name) {
+                tablebases.count += 1;
+                tablebases.max_pieces = tablebases.max_pieces.max(info.piece_count);
+                let entry = Arc::new(TableEntry {
+                    info,
+                    wdl: RwLock::new(None),
+                    dtz: RwLock::new(None),
+                });
+                tablebases.entries.insert(entry.info.key, Arc::clone(&entry));
+                tablebases.entries.insert(entry.info.key2, entry);
+            }
+        }
+        tablebases
+    }
+
+    /// Returns the number of tables found.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns if no tables were found.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns the largest number of pieces of the tables found, or zero if there are none.
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Returns the win / draw / loss value of a position, or `None` if the position is not in
+    /// the tables.
+    pub fn probe_wdl(&self, board: &Board) -> Option<Wdl> {
+        if !self.can_probe(board) {
+            return None;
+        }
+        let mut board = board.shallow_clone();
+        self.search(&mut board, false).map(|(wdl, _)| wdl)
+    }
+
+    /// Returns the distance to zeroing of a position, or `None` if the position is not in the
+    /// tables.
+    ///
+    /// The distance is in plies, positive if the side to move wins and negative if it loses,
+    /// with 100 added to the distance of cursed wins and blessed losses. A value of 1 or -1
+    /// means the next move is a capture or pawn move. Drawn positions have a distance of 0.
+    pub fn probe_dtz(&self, board: &Board) -> Option<i32> {
+        if !self.can_probe(board) {
+            return None;
+        }
+        let mut board = board.shallow_clone();
+        self.dtz(&mut board)
+    }
+
+    fn can_probe(&self, board: &Board) -> bool {
+        board.count_all_pieces() as usize <= self.max_pieces
+            && !board.can_castle(Player::White, ::core::CastleType::KingSide)
+            && !board.can_castle(Player::White, ::core::CastleType::QueenSide)
+            && !board.can_castle(Player::Black, ::core::CastleType::KingSide)
+            && !board.can_castle(Player::Black, ::core::CastleType::QueenSide)
+    }
+
+    fn find_file(&self, name: &str, table_type: TableType) -> Option<PathBuf> {
+        let file = name.to_string() + table_type.extension();
+        self.paths.iter()
+            .map(|dir| dir.join(&file))
+            .find(|path| path.is_file())
+    }
+
+    /// Returns the table of an entry, reading it on first use.
+    fn table(&self, entry: &TableEntry, table_type: TableType) -> Option<Arc<Table>> {
+        let slot = match table_type {
+            TableType::Wdl => &entry.wdl,
+            TableType::Dtz => &entry.dtz,
+        };
+        if let Some(ref table) = *slot.read().unwrap() {
+            return table.clone();
+        }
+        let mut slot = slot.write().unwrap();
+        if slot.is_none() {
+            *slot = Some(self.find_file(&entry.info.name, table_type)
+                .and_then(|path| Table::open(path, &entry.info, table_type).ok())
+                .map(Arc::new));
+        }
+        slot.as_ref().unwrap().clone()
+    }
+
+    /// Looks up a position in its table, without looking at any moves.
+    fn probe_table(&self, board: &Board, table_type: TableType, wdl: Wdl) -> Option<TableValue> {
+        if board.count_all_pieces() == 2 {
+            return Some(TableValue::Value(0));
+        }
+        let key = material_key(&board_counts(board, false));
+        let entry = self.entries.get(&key)?;
+        let table = self.table(entry, table_type)?;
+        Some(table.probe(board, &entry.info, wdl.to_i32()))
+    }
+
+    /// Finds the WDL value of a position by probing it alongside its captures, and if
+    /// `zeroing` is set, its pawn moves as well. Also returns if a capture or pawn move
+    /// is the best move.
+    ///
+    /// Tables are free to store any value for positions with a winning capture, and may
+    /// store a loss for positions with a drawing capture, so captures must be searched.
+    /// Positions with an en-passant square are not stored at all.
+    fn search(&self, board: &mut Board, zeroing: bool) -> Option<(Wdl, bool)> {
+        let moves = board.generate_moves();
+        let mut best = Wdl::Loss;
+        let mut move_count = 0;
+
+        for mov in moves.iter() {
+            if !mov.is_capture() && (!zeroing || board.moved_piece(*mov) != PieceType::P) {
+                continue;
+            }
+            move_count += 1;
+            board.apply_move(*mov);
+            let result = self.search(board, false);
+            board.undo_move();
+            let value = -result?.0;
+            if value > best {
+                best = value;
+                if value >= Wdl::Win {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        // When every move has been searched, the stored value can't be trusted.
+        let no_more_moves = move_count != 0 && move_count == moves.len();
+        let value = if no_more_moves {
+            best
+        } else {
+            match self.probe_table(board, TableType::Wdl, Wdl::Draw)? {
+                TableValue::Value(v) => Wdl::from_i32(v),
+                TableValue::ChangeStm => return None,
+            }
+        };
+
+        if best >= value {
+            Some((best, best > Wdl::Draw || no_more_moves))
+        } else {
+            Some((value, false))
+        }
+    }
+
+    fn dtz(&self, board: &mut Board) -> Option<i32> {
+        let (wdl, zeroing_best) = self.search(board, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if zeroing_best {
+            return Some(dtz_before_zeroing(wdl));
+        }
+
+        match self.probe_table(board, TableType::Dtz, wdl)? {
+            TableValue::Value(dtz) => {
+                let cursed = wdl == Wdl::CursedWin || wdl == Wdl::BlessedLoss;
+                Some((dtz + if cursed { 100 } else { 0 }) * wdl.signum())
+            },
+            TableValue::ChangeStm => {
+                // The table only stores the other side to move, so find the move with the
+                // best DTZ one ply deeper.
+                let mut min_dtz = 0xFFFF;
+                for mov in board.generate_moves().iter() {
+                    let zeroing = mov.is_capture() || board.moved_piece(*mov) == PieceType::P;
+                    board.apply_move(*mov);
+                    // For zeroing moves, the DTZ is the one before the move.
+                    let result = if zeroing {
+                        self.search(board, false).map(|(w, _)| -dtz_before_zeroing(w))
+                    } else {
+                        self.dtz(board).map(|d| -d)
+                    };
+                    let mates = result == Some(1) && board.in_check() && board.generate_moves().is_empty();
+                    board.undo_move();
+
+                    let mut dtz = result?;
+                    if mates {
+                        min_dtz = 1;
+                    }
+                    if !zeroing {
+                        dtz += dtz.signum();
+                    }
+                    if dtz < min_dtz && dtz.signum() == wdl.signum() {
+                        min_dtz = dtz;
+                    }
+                }
+                Some(if min_dtz == 0xFFFF { -1 } else { min_dtz })
+            }
+        }
+    }
+}
+
+/// Returns the name of every table with up to six pieces, the stronger side first.
+fn table_names() -> Vec<String> {
+    let pieces = [PieceType::P, PieceType::N, PieceType::B, PieceType::R, PieceType::Q];
+    let mut names = Vec::new();
+    for (i1, p1) in pieces.iter().enumerate() {
+        names.push(table_name(&[*p1], &[]));
+        for (i2, p2) in pieces[..i1 + 1].iter().enumerate() {
+            names.push(table_name(&[*p1, *p2], &[]));
+            names.push(table_name(&[*p1], &[*p2]));
+            for p3 in pieces.iter() {
+                names.push(table_name(&[*p1, *p2], &[*p3]));
+            }
+            for (i3, p3) in pieces[..i2 + 1].iter().enumerate() {
+                names.push(table_name(&[*p1, *p2, *p3], &[]));
+                for p4 in pieces[..i3 + 1].iter() {
+                    names.push(table_name(&[*p1, *p2, *p3, *p4], &[]));
+                }
+                for p4 in pieces.iter() {
+                    names.push(table_name(&[*p1, *p2, *p3], &[*p4]));
+                }
+            }
+            for (i3, p3) in pieces[..i1 + 1].iter().enumerate() {
+                let last = if i1 == i3 { i2 } else { i3 };
+                for p4 in pieces[..last + 1].iter() {
+                    names.push(table_name(&[*p1, *p2], &[*p3, *p4]));
+                }
+            }
+        }
+    }
+    names
+}
+
+/// Returns the name of the table with a king and `strong` pieces against a king and `weak`
+/// pieces.
+fn table_name(strong: &[PieceType], weak: &[PieceType]) -> String {
+    let side = |pieces: &[PieceType]| -> String {
+        let mut s = String::from("K");
+        s.extend(pieces.iter().map(|p| p.char_upper()));
+        s
+    };
+    side(strong) + "v" + &side(weak)
+}
+
+lazy_static! {
+    static ref TABLEBASES: RwLock<Tablebases> = RwLock::new(Tablebases::empty());
+}
+
+static MAX_PIECES: AtomicUsize = AtomicUsize::new(0);
+
+/// Replaces the global tablebases with the tables found in a list of directories, separated
+/// by `:` (or `;` on Windows). Returns the number of tables found.
+pub fn init(path: &str) -> usize {
+    let tablebases = Tablebases::new(path);
+    let count = tablebases.len();
+    MAX_PIECES.store(tablebases.max_pieces(), Ordering::SeqCst);
+    *TABLEBASES.write().unwrap() = tablebases;
+    count
+}
+
+/// Returns the largest number of pieces of the global tablebases, or zero if there are none.
+#[inline]
+pub fn max_pieces() -> usize {
+    MAX_PIECES.load(Ordering::Relaxed)
+}
+
+/// Returns the win / draw / loss value of a position from the global tablebases, or `None`
+/// if it is not in the tables.
+pub fn probe_wdl(board: &Board) -> Option<Wdl> {
+    if board.count_all_pieces() as usize > max_pieces() {
+        return None;
+    }
+    TABLEBASES.read().unwrap().probe_wdl(board)
+}
+
+/// Returns the distance to zeroing of a position from the global tablebases, or `None` if it
+/// is not in the tables. See `Tablebases::probe_dtz()`.
+pub fn probe_dtz(board: &Board) -> Option<i32> {
+    if board.count_all_pieces() as usize > max_pieces() {
+        return None;
+    }
+    TABLEBASES.read().unwrap().probe_dtz(board)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::process;
+
+    const WDL_MAGIC_BYTES: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+
+    #[test]
+    fn table_names_unique() {
+        let names = table_names();
+        let unique: HashSet<&String> = names.iter().collect();
+        assert_eq!(names.len(), 510);
+        assert_eq!(unique.len(), names.len());
+        assert!(names.iter().all(|n| TableInfo::from_name(n).is_some()));
+        assert!(unique.contains(&"KQRvK".to_string()));
+        assert!(unique.contains(&"KRPvKR".to_string()));
+    }
+
+    #[test]
+    fn no_tables() {
+        let tablebases = Tablebases::new("<empty>");
+        assert!(tablebases.is_empty());
+        assert_eq!(tablebases.max_pieces(), 0);
+        let board = Board::new_from_fen("8/8/4k3/8/8/3K4/8/8 w - - 0 1").unwrap();
+        assert_eq!(tablebases.probe_wdl(&board), None);
+        assert_eq!(tablebases.probe_dtz(&board), None);
+    }
+
+    /// Writes a WDL file holding a single value for every position, for each side to move.
+    fn write_single_value_table(dir: &PathBuf, name: &str, pieces: &[u8], order: &[u8], values: [i32; 2]) {
+        let info = TableInfo::from_name(name).unwrap();
+        let sides = if info.symmetric() { 1 } else { 2 };
+        let files = if info.has_pawns { 4 } else { 1 };
+        let mut data = WDL_MAGIC_BYTES.to_vec();
+        data.push(if info.symmetric() { 0 } else { 1 } | if info.has_pawns { 2 } else { 0 });
+        for _ in 0..files {
+            data.extend_from_slice(order);
+            data.extend_from_slice(pieces);
+        }
+        if data.len() & 1 != 0 {
+            data.push(0);
+        }
+        for _ in 0..files {
+            for side in 0..sides {
+                data.push(0x80);
+                data.push((values[side] + 2) as u8);
+            }
+        }
+        while data.len() % 64 != 16 {
+            data.push(0);
+        }
+        data.extend_from_slice(&[0; 64]);
+        File::create(dir.join(name.to_string() + ".rtbw")).unwrap().write_all(&data).unwrap();
+    }
+
+    #[test]
+    fn synthetic_tables() {
+        let dir = env::temp_dir().join(format!("pleco_syzygy_{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // White wins KRvK with either side to move.
+        write_single_value_table(&dir, "KRvK", &[0x66, 0x44, 0xEE], &[0x00], [2, -2]);
+        // KNvK and KNNvK are drawn.
+        write_single_value_table(&dir, "KNvK", &[0x66, 0x22, 0xEE], &[0x00], [0, 0]);
+        write_single_value_table(&dir, "KNNvK", &[0x66, 0xEE, 0x22, 0x22], &[0x00], [0, 0]);
+        // White wins KPvK with white to move, and draws with black to move.
+        write_single_value_table(&dir, "KPvK", &[0x11, 0x66, 0xEE], &[0x00], [2, 0]);
+        // KPvKP is won by the side to move.
+        write_single_value_table(&dir, "KPvKP", &[0x11, 0x99, 0x66, 0xEE], &[0x00, 0x11], [2, 2]);
+
+        let tablebases = Tablebases::new(dir.to_str().unwrap());
+        assert_eq!(tablebases.len(), 5);
+        assert_eq!(tablebases.max_pieces(), 4);
+
+        let probe = |fen: &str| tablebases.probe_wdl(&Board::new_from_fen(fen).unwrap());
+        assert_eq!(probe("8/8/4k3/8/8/3K4/8/8 w - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(probe("8/8/4k3/8/8/3K4/8/R7 w - - 0 1"), Some(Wdl::Win));
+        assert_eq!(probe("8/8/4k3/8/8/3K4/8/R7 b - - 0 1"), Some(Wdl::Loss));
+        // Colors are flipped when black has the rook.
+        assert_eq!(probe("r7/8/4k3/8/8/3K4/8/8 b - - 0 1"), Some(Wdl::Win));
+        assert_eq!(probe("r7/8/4k3/8/8/3K4/8/8 w - - 0 1"), Some(Wdl::Loss));
+        // The rook can be captured.
+        assert_eq!(probe("8/8/8/8/8/3K4/4r3/7k w - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(probe("8/8/4k3/8/8/3K4/7N/7N b - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(probe("8/8/4k3/8/3P4/3K4/8/8 b - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(probe("8/8/4k3/3p4/8/3K4/8/8 b - - 0 1"), Some(Wdl::Win));
+        assert_eq!(probe("8/8/4k3/3p4/3P4/3K4/8/8 b - - 0 1"), Some(Wdl::Win));
+        // Capturing en-passant into KPvK, which black to move draws.
+        assert_eq!(probe("8/8/4k3/8/2Pp4/8/8/K7 b - c3 0 1"), Some(Wdl::Win));
+        // No KBvK table, and no tables for positions with castling rights.
+        assert_eq!(probe("8/8/4k3/8/8/3K4/8/B7 w - - 0 1"), None);
+        assert_eq!(probe("4k3/8/8/8/8/8/8/4K2R w K - 0 1"), None);
+
+        // Every placement of the pieces is indexed inside its table.
+        let mut seed: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut random_sq = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % 64) as u8
+        };
+        for pieces in ["KRk", "Krk", "KNNk", "Kknn", "KPk", "Kkp", "KPkp"].iter() {
+            for _ in 0..300 {
+                let mut squares = [None; 64];
+                for c in pieces.chars() {
+                    let mut sq = random_sq();
+                    while squares[sq as usize].is_some() || (c.to_ascii_lowercase() == 'p' && (sq < 8 || sq >= 56)) {
+                        sq = random_sq();
+                    }
+                    squares[sq as usize] = Some(c);
+                }
+                let mut fen = String::new();
+                for rank in (0..8).rev() {
+                    for file in 0..8 {
+                        fen.push(squares[rank * 8 + file].unwrap_or('1'));
+                    }
+                    fen.push(if rank == 0 { ' ' } else { '/' });
+                }
+                for turn in [" w - - 0 1", " b - - 0 1"].iter() {
+                    let board = match Board::new_from_fen(&(fen.clone() + turn)) {
+                        Ok(ref board) if board.is_okay().is_ok() => board.shallow_clone(),
+                        _ => continue
+                    };
+                    {
+                        assert!(tablebases.probe_wdl(&board).is_some(), "{}", board.get_fen());
+                    }
+                }
+            }
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn wdl_values() {
+        assert_eq!(-Wdl::Win, Wdl::Loss);
+        assert_eq!(-Wdl::CursedWin, Wdl::BlessedLoss);
+        assert_eq!(-Wdl::Draw, Wdl::Draw);
+        assert!(Wdl::Win > Wdl::CursedWin && Wdl::CursedWin > Wdl::Draw);
+        assert!(Wdl::Draw > Wdl::BlessedLoss && Wdl::BlessedLoss > Wdl::Loss);
+        assert_eq!(dtz_before_zeroing(Wdl::CursedWin), 101);
+    }
+}
@@ -0,0 +1,830 @@
+//! Reading and decompressing single Syzygy table files.
+//!
+//! A table file is made of one or more sub-tables: one per side to move, and for tables with
+//! pawns, one per file of the leading pawn. Each sub-table stores its values compressed with
+//! a canonical Huffman code over "Recursive Pairing" symbols, in blocks of a fixed size.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use board::Board;
+use core::{Player, PieceType};
+
+use super::encoding::*;
+
+/// The magic bytes starting every WDL file.
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+/// The magic bytes starting every DTZ file.
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+// Flags of a sub-table. All of them are used by DTZ tables, only the last by WDL tables.
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+/// The two kinds of table files.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TableType {
+    /// Win / draw / loss tables, stored in `.rtbw` files.
+    Wdl,
+    /// Distance-to-zeroing tables, stored in `.rtbz` files.
+    Dtz,
+}
+
+impl TableType {
+    /// The file extension of the table type.
+    pub fn extension(self) -> &'static str {
+        match self {
+            TableType::Wdl => ".rtbw",
+            TableType::Dtz => ".rtbz",
+        }
+    }
+}
+
+/// Returns the code Syzygy files use for a piece: 1 to 6 for the white pawn to king, and 9 to
+/// 14 for the black ones.
+#[inline]
+pub fn piece_code(player: Player, piece: PieceType) -> u8 {
+    (piece as u8 + 1) | if player == Player::Black { 8 } else { 0 }
+}
+
+/// Material information about a table, taken from its name, such as "KRvK".
+#[derive(Clone, Debug)]
+pub struct TableInfo {
+    /// The name of the table, and of its files without the extension.
+    pub name: String,
+    /// The material key of positions where white has the first side of the name.
+    pub key: u64,
+    /// The material key of positions where black has the first side of the name.
+    pub key2: u64,
+    pub piece_count: usize,
+    pub has_pawns: bool,
+    /// If any side has a single piece other than the king.
+    pub has_unique_pieces: bool,
+    /// The pawns of the leading side, then of the other side. The leading side is the one
+    /// with the fewest pawns, as long as it has any.
+    pub pawn_count: [u8; 2],
+}
+
+/// Computes a key identifying the material of a position from the piece counts of each
+/// player, indexed by `[player][piece]`.
+pub fn material_key(counts: &[[u8; 6]; 2]) -> u64 {
+    let mut key = 0;
+    for player in 0..2 {
+        for piece in 0..6 {
+            key |= u64::from(counts[player][piece]) << (4 * (player * 6 + piece));
+        }
+    }
+    key
+}
+
+/// Returns the piece counts of a board, indexed by `[player][piece]`. If `flip` is set, the
+/// players are swapped.
+pub fn board_counts(board: &Board, flip: bool) -> [[u8; 6]; 2] {
+    let mut counts = [[0; 6]; 2];
+    for (p, player) in [Player::White, Player::Black].iter().enumerate() {
+        for (i, piece) in ::core::ALL_PIECE_TYPES.iter().enumerate() {
+            counts[p ^ flip as usize][i] = board.count_piece(*player, *piece);
+        }
+    }
+    counts
+}
+
+impl TableInfo {
+    /// Creates the information of a table from its name, returning `None` if the name
+    /// does not describe a table, like "KQvKR".
+    pub fn from_name(name: &str) -> Option<TableInfo> {
+        let mut sides = name.split('v');
+        let (white, black) = match (sides.next(), sides.next(), sides.next()) {
+            (Some(w), Some(b), None) => (w, b),
+            _ => return None
+        };
+
+        let mut counts = [[0u8; 6]; 2];
+        for (p, side) in [white, black].iter().enumerate() {
+            for c in side.chars() {
+                let piece = match c {
+                    'P' => PieceType::P,
+                    'N' => PieceType::N,
+                    'B' => PieceType::B,
+                    'R' => PieceType::R,
+                    'Q' => PieceType::Q,
+                    'K' => PieceType::K,
+                    _ => return None
+                };
+                counts[p][piece as usize] += 1;
+            }
+            if counts[p][PieceType::K as usize] != 1 {
+                return None;
+            }
+        }
+
+        let piece_count = counts.iter().flat_map(|c| c.iter()).map(|c| *c as usize).sum();
+        if piece_count > TB_PIECES {
+            return None;
+        }
+
+        let pawns = [counts[0][PieceType::P as usize], counts[1][PieceType::P as usize]];
+        let has_unique_pieces = counts.iter()
+            .any(|c| c[..PieceType::K as usize].iter().any(|n| *n == 1));
+        let white_leads = pawns[1] == 0 || (pawns[0] != 0 && pawns[1] >= pawns[0]);
+        let pawn_count = if white_leads { pawns } else { [pawns[1], pawns[0]] };
+
+        Some(TableInfo {
+            name: name.to_string(),
+            key: material_key(&counts),
+            key2: material_key(&[counts[1], counts[0]]),
+            piece_count,
+            has_pawns: pawns[0] + pawns[1] > 0,
+            has_unique_pieces,
+            pawn_count,
+        })
+    }
+
+    /// If both sides have the same material.
+    #[inline]
+    pub fn symmetric(&self) -> bool {
+        self.key == self.key2
+    }
+}
+
+/// Indexing and decompression information of a single sub-table. Positions into the file
+/// are stored as offsets into `Table::data`.
+#[derive(Clone, Default)]
+struct PairsData {
+    flags: u8,
+    block_size: usize,
+    /// About every `span` values there is an entry in the sparse index.
+    span: u64,
+    num_blocks: usize,
+    min_sym_len: u8,
+    lowest_sym: usize,
+    btree: usize,
+    block_length: usize,
+    block_length_size: usize,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    data: usize,
+    /// `base64[l - min_sym_len]` is the lowest symbol of length `l`, padded to 64 bits.
+    base64: Vec<u64>,
+    /// The number of values, minus one, that each symbol expands to.
+    symlen: Vec<u8>,
+    pieces: [u8; TB_PIECES],
+    /// Start index used for the encoding of each group of pieces.
+    group_idx: [u64; TB_PIECES + 1],
+    /// The number of pieces in each group, terminated by a zero.
+    group_len: [usize; TB_PIECES + 1],
+    /// Offsets into the DTZ value map for a win, loss, cursed win and blessed loss.
+    map_idx: [usize; 4],
+}
+
+/// A table file read into memory.
+pub struct Table {
+    table_type: TableType,
+    data: Vec<u8>,
+    /// Sub-tables indexed by `side * 4 + file`.
+    items: Vec<PairsData>,
+    /// Start of the DTZ value map.
+    map: usize,
+}
+
+/// The result of looking up a position in a table.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TableValue {
+    /// The value stored for the position.
+    Value(i32),
+    /// The DTZ table only stores positions with the other side to move.
+    ChangeStm,
+}
+
+#[inline]
+fn read_u16_le(data: &[u8], pos: usize) -> u16 {
+    u16::from(data[pos]) | u16::from(data[pos + 1]) << 8
+}
+
+#[inline]
+fn read_u32_le(data: &[u8], pos: usize) -> u32 {
+    u32::from(read_u16_le(data, pos)) | u32::from(read_u16_le(data, pos + 2)) << 16
+}
+
+/// Reads a big-endian u32, treating bytes past the end of the data as zero.
+#[inline]
+fn read_u32_be(data: &[u8], pos: usize) -> u32 {
+    (0..4).fold(0, |acc, i| (acc << 8) | u32::from(*data.get(pos + i).unwrap_or(&0)))
+}
+
+impl Table {
+    /// Reads the file of a table, checking its magic bytes.
+    pub fn open<P: AsRef<Path>>(path: P, info: &TableInfo, table_type: TableType) -> io::Result<Table> {
+        let mut data = Vec::new();
+        File::open(path)?.read_to_end(&mut data)?;
+        let magic = match table_type {
+            TableType::Wdl => WDL_MAGIC,
+            TableType::Dtz => DTZ_MAGIC,
+        };
+        if data.len() < 5 || data.len() % 64 != 16 || data[0..4] != magic {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "corrupted tablebase file"));
+        }
+        Table::new(data, info, table_type)
+    }
+
+    fn new(data: Vec<u8>, info: &TableInfo, table_type: TableType) -> io::Result<Table> {
+        let mut table = Table {
+            table_type,
+            data,
+            items: Vec::new(),
+            map: 0,
+        };
+        table.setup(info).map(|_| table)
+    }
+
+    #[inline]
+    fn sides(&self, info: &TableInfo) -> usize {
+        if self.table_type == TableType::Wdl && !info.symmetric() { 2 } else { 1 }
+    }
+
+    #[inline]
+    fn item(&self, stm: usize, file: usize) -> &PairsData {
+        let sides = self.items.len() / 4;
+        &self.items[(stm % sides) * 4 + file]
+    }
+
+    /// Reads the headers of every sub-table.
+    fn setup(&mut self, info: &TableInfo) -> io::Result<()> {
+        let sides = self.sides(info);
+        let max_file = if info.has_pawns { 4 } else { 1 };
+        let pp = info.has_pawns && info.pawn_count[1] > 0;
+        self.items = vec![PairsData::default(); sides * 4];
+
+        let corrupted = || io::Error::new(io::ErrorKind::InvalidData, "corrupted tablebase file");
+        let expect_pawns = self.data[4] & 2 != 0;
+        let expect_split = self.data[4] & 1 != 0;
+        if expect_pawns != info.has_pawns || (self.table_type == TableType::Wdl && expect_split == info.symmetric()) {
+            return Err(corrupted());
+        }
+
+        let mut pos = 5;
+        for f in 0..max_file {
+            let first = self.data[pos];
+            let second = if pp { self.data[pos + 1] } else { 0xFF };
+            let order = [[first & 0xF, second & 0xF], [first >> 4, second >> 4]];
+            pos += 1 + pp as usize;
+
+            for k in 0..info.piece_count {
+                for i in 0..sides {
+                    let byte = self.data[pos];
+                    self.items[i * 4 + f].pieces[k] = if i == 1 { byte >> 4 } else { byte & 0xF };
+                }
+                pos += 1;
+            }
+            for i in 0..sides {
+                set_groups(info, &mut self.items[i * 4 + f], &order[i], f);
+            }
+        }
+        pos += pos & 1;
+
+        for f in 0..max_file {
+            for i in 0..sides {
+                pos = self.set_sizes(i * 4 + f, pos).ok_or_else(corrupted)?;
+            }
+        }
+
+        if self.table_type == TableType::Dtz {
+            self.map = pos;
+            for f in 0..max_file {
+                let flags = self.items[f].flags;
+                if flags & FLAG_MAPPED == 0 {
+                    continue;
+                }
+                if flags & FLAG_WIDE != 0 {
+                    pos += pos & 1;
+                    for i in 0..4 {
+                        self.items[f].map_idx[i] = (pos - self.map) / 2 + 1;
+                        pos += 2 * read_u16_le(&self.data, pos) as usize + 2;
+                    }
+                } else {
+                    for i in 0..4 {
+                        self.items[f].map_idx[i] = pos - self.map + 1;
+                        pos += self.data[pos] as usize + 1;
+                    }
+                }
+            }
+            pos += pos & 1;
+        }
+
+        for f in 0..max_file {
+            for i in 0..sides {
+                let d = &mut self.items[i * 4 + f];
+                d.sparse_index = pos;
+                pos += d.sparse_index_size * 6;
+            }
+        }
+        for f in 0..max_file {
+            for i in 0..sides {
+                let d = &mut self.items[i * 4 + f];
+                d.block_length = pos;
+                pos += d.block_length_size * 2;
+            }
+        }
+        for f in 0..max_file {
+            for i in 0..sides {
+                pos = (pos + 0x3F) & !0x3F;
+                let d = &mut self.items[i * 4 + f];
+                d.data = pos;
+                pos += d.num_blocks * d.block_size;
+            }
+        }
+
+        if pos > self.data.len() {
+            return Err(corrupted());
+        }
+        Ok(())
+    }
+
+    /// Reads the sizes and the Huffman code of a sub-table, returning the position of the
+    /// following data.
+    fn set_sizes(&mut self, item: usize, mut pos: usize) -> Option<usize> {
+        let data = &self.data;
+        let d = &mut self.items[item];
+        d.flags = *data.get(pos)?;
+        pos += 1;
+
+        if d.flags & FLAG_SINGLE_VALUE != 0 {
+            // The single value of the table is stored in place of the minimum symbol length.
+            d.min_sym_len = *data.get(pos)?;
+            return Some(pos + 1);
+        }
+
+        let n = d.group_len.iter().position(|l| *l == 0).unwrap_or(TB_PIECES);
+        let tb_size = d.group_idx[n];
+
+        if pos + 8 > data.len() {
+            return None;
+        }
+        d.block_size = 1 << data[pos];
+        d.span = 1 << data[pos + 1];
+        d.sparse_index_size = ((tb_size + d.span - 1) / d.span) as usize;
+        let padding = data[pos + 2] as usize;
+        d.num_blocks = read_u32_le(data, pos + 3) as usize;
+        d.block_length_size = d.num_blocks + padding;
+        let max_sym_len = data[pos + 7];
+        d.min_sym_len = *data.get(pos + 8)?;
+        pos += 9;
+        if max_sym_len < d.min_sym_len || max_sym_len - d.min_sym_len >= 64 {
+            return None;
+        }
+        d.lowest_sym = pos;
+
+        // Longer symbols have lower values in a canonical code, so the lowest symbol of each
+        // length can be padded to 64 bits into a decreasing table.
+        let len = (max_sym_len - d.min_sym_len) as usize + 1;
+        if pos + 2 * len + 2 > data.len() {
+            return None;
+        }
+        d.base64 = vec![0; len];
+        for i in (0..len - 1).rev() {
+            let lowest = u64::from(read_u16_le(data, pos + 2 * i));
+            let next_lowest = u64::from(read_u16_le(data, pos + 2 * (i + 1)));
+            d.base64[i] = (d.base64[i + 1] + lowest - next_lowest) / 2;
+        }
+        for i in 0..len {
+            let shift = 64 - i as u32 - u32::from(d.min_sym_len);
+            d.base64[i] = d.base64[i].checked_shl(shift).unwrap_or(0);
+        }
+        pos += 2 * len;
+
+        let num_syms = read_u16_le(data, pos) as usize;
+        pos += 2;
+        d.btree = pos;
+        if pos + 3 * num_syms > data.len() {
+            return None;
+        }
+        d.symlen = vec![0; num_syms];
+        let mut visited = vec![false; num_syms];
+        for sym in 0..num_syms {
+            if !visited[sym] {
+                let len = set_symlen(data, d, sym, &mut visited)?;
+                d.symlen[sym] = len;
+            }
+        }
+        Some(pos + 3 * num_syms + (num_syms & 1))
+    }
+
+    /// Returns the left child of a symbol in the pairing tree, or its value if it is a leaf.
+    #[inline]
+    fn sym_left(&self, d: &PairsData, sym: usize) -> usize {
+        let p = d.btree + 3 * sym;
+        ((self.data[p + 1] as usize & 0xF) << 8) | self.data[p] as usize
+    }
+
+    #[inline]
+    fn sym_right(&self, d: &PairsData, sym: usize) -> usize {
+        let p = d.btree + 3 * sym;
+        ((self.data[p + 2] as usize) << 4) | (self.data[p + 1] as usize >> 4)
+    }
+
+    /// Returns the value stored at an index of a sub-table.
+    fn decompress_pairs(&self, d: &PairsData, idx: u64) -> i32 {
+        if d.flags & FLAG_SINGLE_VALUE != 0 {
+            return i32::from(d.min_sym_len);
+        }
+        let data = &self.data;
+
+        // The sparse index points into the block lengths at every `span` values, at the
+        // index `k * span + span / 2`. Start from the nearest entry and walk the blocks.
+        let k = (idx / d.span) as usize;
+        let entry = d.sparse_index + 6 * k;
+        let mut block = read_u32_le(data, entry) as usize;
+        let mut offset = i64::from(read_u16_le(data, entry + 4));
+        offset += (idx % d.span) as i64 - (d.span / 2) as i64;
+
+        let block_length = |b: usize| i64::from(read_u16_le(data, d.block_length + 2 * b));
+        while offset < 0 {
+            block -= 1;
+            offset += block_length(block) + 1;
+        }
+        while offset > block_length(block) {
+            offset -= block_length(block) + 1;
+            block += 1;
+        }
+
+        // The block is a sequence of Huffman codes, starting at the beginning of the block.
+        let mut ptr = d.data + block * d.block_size;
+        let mut buf64: u64 = (u64::from(read_u32_be(data, ptr)) << 32) | u64::from(read_u32_be(data, ptr + 4));
+        ptr += 8;
+        let mut buf64_size = 64;
+        let mut sym;
+
+        loop {
+            let mut len = 0;
+            while len + 1 < d.base64.len() && buf64 < d.base64[len] {
+                len += 1;
+            }
+            let shift = 64 - len as u32 - u32::from(d.min_sym_len);
+            sym = (buf64 - d.base64[len]).checked_shr(shift).unwrap_or(0) as usize;
+            sym += read_u16_le(data, d.lowest_sym + 2 * len) as usize;
+
+            if sym >= d.symlen.len() {
+                return 0;
+            }
+            if offset < i64::from(d.symlen[sym]) + 1 {
+                break;
+            }
+            offset -= i64::from(d.symlen[sym]) + 1;
+            let bits = len as u32 + u32::from(d.min_sym_len);
+            buf64 = buf64.checked_shl(bits).unwrap_or(0);
+            buf64_size -= bits as i32;
+            if buf64_size <= 32 {
+                buf64_size += 32;
+                buf64 |= u64::from(read_u32_be(data, ptr)) << (64 - buf64_size);
+                ptr += 4;
+            }
+        }
+
+        // Expand the symbol through the pairing tree until reaching the leaf holding the value.
+        while d.symlen[sym] != 0 {
+            let left = self.sym_left(d, sym);
+            if offset < i64::from(d.symlen[left]) + 1 {
+                sym = left;
+            } else {
+                offset -= i64::from(d.symlen[left]) + 1;
+                sym = self.sym_right(d, sym);
+            }
+        }
+        self.sym_left(d, sym) as i32
+    }
+
+    /// Maps a value read from a DTZ table back to a distance in plies.
+    fn map_score(&self, file: usize, value: i32, wdl: i32) -> i32 {
+        if self.table_type == TableType::Wdl {
+            return value - 2;
+        }
+        const WDL_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+        let d = self.item(0, file);
+        let mut value = value;
+        if d.flags & FLAG_MAPPED != 0 {
+            let idx = d.map_idx[WDL_MAP[(wdl + 2) as usize]] + value as usize;
+            value = if d.flags & FLAG_WIDE != 0 {
+                i32::from(read_u16_le(&self.data, self.map + 2 * idx))
+            } else {
+                i32::from(self.data[self.map + idx])
+            };
+        }
+
+        // Tables store distances either in moves or in plies.
+        if (wdl == 2 && d.flags & FLAG_WIN_PLIES == 0)
+            || (wdl == -2 && d.flags & FLAG_LOSS_PLIES == 0)
+            || wdl == 1
+            || wdl == -1 {
+            value *= 2;
+        }
+        value + 1
+    }
+
+    /// Looks up a position. `wdl` is the WDL value of the position, needed to decode DTZ
+    /// values, and ignored for WDL tables.
+    pub fn probe(&self, board: &Board, info: &TableInfo, wdl: i32) -> TableValue {
+        let enc: &Encoding = &ENCODING;
+        let mut squares = [0u8; TB_PIECES];
+        let mut pieces = [0u8; TB_PIECES];
+        let mut size = 0;
+        let mut lead_pawns_cnt = 0;
+        let mut lead_pawns = 0u64;
+        let mut tb_file = 0;
+
+        // Tables are stored with white as the first side of the name. If black has it, or if
+        // the table is symmetric and only stores white to move, colors and squares are flipped.
+        let symmetric_black_to_move = info.symmetric() && board.turn() == Player::Black;
+        let black_stronger = material_key(&board_counts(board, false)) != info.key;
+        let flip = symmetric_black_to_move || black_stronger;
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 0o70 } else { 0 };
+        let stm = flip as usize ^ board.turn() as usize;
+
+        if info.has_pawns {
+            // The leading pawns come first in every sub-table, so the first piece has their color.
+            let lead_color = if self.item(0, 0).pieces[0] ^ flip_color < 8 { Player::White } else { Player::Black };
+            let mut b = board.piece_bb(lead_color, PieceType::P);
+            lead_pawns = b.0;
+            while let Some(sq) = b.pop_some_lsb() {
+                squares[size] = sq.0 ^ flip_squares;
+                size += 1;
+            }
+            lead_pawns_cnt = size;
+
+            let mut lead = 0;
+            for i in 1..lead_pawns_cnt {
+                if enc.map_pawns[squares[i] as usize] > enc.map_pawns[squares[lead] as usize] {
+                    lead = i;
+                }
+            }
+            squares.swap(0, lead);
+            tb_file = (squares[0] & 7) as usize;
+            if tb_file > 3 {
+                tb_file = ((squares[0] ^ 7) & 7) as usize;
+            }
+        }
+
+        if self.table_type == TableType::Dtz {
+            let flags = self.item(0, tb_file).flags;
+            if (flags & FLAG_STM) as usize != stm && !(info.symmetric() && !info.has_pawns) {
+                return TableValue::ChangeStm;
+            }
+        }
+
+        let mut b = board.get_occupied();
+        b.0 ^= lead_pawns;
+        while let Some(sq) = b.pop_some_lsb() {
+            squares[size] = sq.0 ^ flip_squares;
+            let player = board.player_at_sq(sq).unwrap();
+            let piece = board.piece_at_sq(sq).unwrap();
+            pieces[size] = piece_code(player, piece) ^ flip_color;
+            size += 1;
+        }
+
+        let d = self.item(stm, tb_file);
+
+        // Order the pieces the same way as the sub-table.
+        for i in lead_pawns_cnt..size {
+            for j in i..size {
+                if d.pieces[i] == pieces[j] {
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        // Flip so the first piece is on files a to d.
+        if squares[0] & 7 > 3 {
+            for sq in squares[..size].iter_mut() {
+                *sq ^= 7;
+            }
+        }
+
+        let mut idx: u64;
+        if info.has_pawns {
+            idx = enc.lead_pawn_idx[lead_pawns_cnt][squares[0] as usize];
+            squares[1..lead_pawns_cnt].sort_by_key(|s| enc.map_pawns[*s as usize]);
+            for i in 1..lead_pawns_cnt {
+                idx += enc.binomial[i][enc.map_pawns[squares[i] as usize]];
+            }
+        } else {
+            // Without pawns, also flip so the first piece is on ranks 1 to 4, and below the
+            // a1-h8 diagonal as much as possible.
+            if squares[0] >> 3 > 3 {
+                for sq in squares[..size].iter_mut() {
+                    *sq ^= 0o70;
+                }
+            }
+            for i in 0..d.group_len[0] {
+                let off = off_a1h8(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+                if off > 0 {
+                    for sq in squares[i..size].iter_mut() {
+                        *sq = ((*sq >> 3) | (*sq << 3)) & 63;
+                    }
+                }
+                break;
+            }
+
+            let s = |i: usize| squares[i] as u64;
+            let rank = |i: usize| (squares[i] >> 3) as u64;
+            if info.has_unique_pieces {
+                // The first three pieces are encoded together.
+                let adjust1 = (squares[1] > squares[0]) as u64;
+                let adjust2 = (squares[2] > squares[0]) as u64 + (squares[2] > squares[1]) as u64;
+
+                idx = if off_a1h8(squares[0]) != 0 {
+                    (enc.map_a1d1d4[squares[0] as usize] as u64 * 63 + (s(1) - adjust1)) * 62 + s(2) - adjust2
+                } else if off_a1h8(squares[1]) != 0 {
+                    (6 * 63 + rank(0) * 28 + enc.map_b1h1h7[squares[1] as usize] as u64) * 62 + s(2) - adjust2
+                } else if off_a1h8(squares[2]) != 0 {
+                    6 * 63 * 62 + 4 * 28 * 62
+                        + rank(0) * 7 * 28
+                        + (rank(1) - adjust1) * 28
+                        + enc.map_b1h1h7[squares[2] as usize] as u64
+                } else {
+                    6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28
+                        + rank(0) * 7 * 6
+                        + (rank(1) - adjust1) * 6
+                        + (rank(2) - adjust2)
+                };
+            } else {
+                // Otherwise, only the kings are encoded together.
+                idx = enc.map_kk[enc.map_a1d1d4[squares[0] as usize]][squares[1] as usize] as u64;
+            }
+        }
+
+        idx *= d.group_idx[0];
+
+        // Encode the remaining groups, each with its squares in ascending order and skipping
+        // the squares taken by earlier groups.
+        let mut group_start = d.group_len[0];
+        let mut remaining_pawns = info.has_pawns && info.pawn_count[1] > 0;
+        let mut next = 1;
+        while d.group_len[next] != 0 {
+            let len = d.group_len[next];
+            squares[group_start..group_start + len].sort();
+            let mut n = 0;
+            for i in 0..len {
+                let sq = squares[group_start + i];
+                let adjust = squares[..group_start].iter().filter(|s| sq > **s).count();
+                let pawn_adjust = if remaining_pawns { 8 } else { 0 };
+                n += enc.binomial[i + 1][sq as usize - adjust - pawn_adjust];
+            }
+            remaining_pawns = false;
+            idx += n * d.group_idx[next];
+            group_start += len;
+            next += 1;
+        }
+        debug_assert!(idx < d.group_idx[next]);
+
+        TableValue::Value(self.map_score(tb_file, self.decompress_pairs(d, idx), wdl))
+    }
+}
+
+/// Splits the pieces of a sub-table into the groups encoded together, and computes the start
+/// index of each group.
+///
+/// Pieces of the same type and color form a group. The leading group is instead made of the
+/// leading pawns, or without pawns, either the first three pieces or the two kings when no
+/// piece is unique. For example KRvKN is grouped as KRK + N, and KNNvK as KK + NN.
+fn set_groups(info: &TableInfo, d: &mut PairsData, order: &[u8; 2], file: usize) {
+    let enc: &Encoding = &ENCODING;
+    let mut n = 0;
+    let mut first_len: i32 = if info.has_pawns { 0 } else if info.has_unique_pieces { 3 } else { 2 };
+    d.group_len[n] = 1;
+
+    for i in 1..info.piece_count {
+        first_len -= 1;
+        if first_len > 0 || d.pieces[i] == d.pieces[i - 1] {
+            d.group_len[n] += 1;
+        } else {
+            n += 1;
+            d.group_len[n] = 1;
+        }
+    }
+    n += 1;
+    d.group_len[n] = 0;
+
+    // The groups are not necessarily encoded in order: the leading group is at position
+    // `order[0]` and the remaining pawns, if any, are at `order[1]`.
+    let pp = info.has_pawns && info.pawn_count[1] > 0;
+    let mut next = if pp { 2 } else { 1 };
+    let mut free_squares = 64 - d.group_len[0] - if pp { d.group_len[1] } else { 0 };
+    let mut idx: u64 = 1;
+
+    let mut k = 0;
+    while next < n || k == order[0] as usize || k == order[1] as usize {
+        if k == order[0] as usize {
+            d.group_idx[0] = idx;
+            idx *= if info.has_pawns {
+                enc.lead_pawns_size[d.group_len[0]][file]
+            } else if info.has_unique_pieces {
+                31332
+            } else {
+                462
+            };
+        } else if k == order[1] as usize {
+            d.group_idx[1] = idx;
+            idx *= enc.binomial[d.group_len[1]][48 - d.group_len[0]];
+        } else {
+            d.group_idx[next] = idx;
+            idx *= enc.binomial[d.group_len[next]][free_squares];
+            free_squares -= d.group_len[next];
+            next += 1;
+        }
+        k += 1;
+    }
+    d.group_idx[n] = idx;
+}
+
+/// Computes the number of values, minus one, that a symbol expands to.
+fn set_symlen(data: &[u8], d: &mut PairsData, sym: usize, visited: &mut Vec<bool>) -> Option<u8> {
+    visited[sym] = true;
+    let p = d.btree + 3 * sym;
+    let right = ((data[p + 2] as usize) << 4) | (data[p + 1] as usize >> 4);
+    if right == 0xFFF {
+        return Some(0);
+    }
+    let left = ((data[p + 1] as usize & 0xF) << 8) | data[p] as usize;
+    if left >= visited.len() || right >= visited.len() {
+        return None;
+    }
+    if !visited[left] {
+        let len = set_symlen(data, d, left, visited)?;
+        d.symlen[left] = len;
+    }
+    if !visited[right] {
+        let len = set_symlen(data, d, right, visited)?;
+        d.symlen[right] = len;
+    }
+    Some(d.symlen[left].wrapping_add(d.symlen[right]).wrapping_add(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_info() {
+        let krk = TableInfo::from_name("KRvK").unwrap();
+        assert_eq!(krk.piece_count, 3);
+        assert!(krk.has_unique_pieces && !krk.has_pawns && !krk.symmetric());
+
+        let knnk = TableInfo::from_name("KNNvK").unwrap();
+        assert!(!knnk.has_unique_pieces);
+
+        let kpkp = TableInfo::from_name("KPvKP").unwrap();
+        assert!(kpkp.symmetric() && kpkp.has_pawns);
+        assert_eq!(kpkp.pawn_count, [1, 1]);
+
+        // The side with fewer pawns leads.
+        let kppkp = TableInfo::from_name("KPPvKP").unwrap();
+        assert_eq!(kppkp.pawn_count, [1, 2]);
+
+        for name in ["KRK", "KRvKvK", "KXvK", "RvK", "KKvK"].iter() {
+            assert!(TableInfo::from_name(name).is_none());
+        }
+    }
+
+    #[test]
+    fn king_encoding() {
+        let enc: &Encoding = &ENCODING;
+        let mut seen = vec![false; 462];
+        for s1 in 0..64u8 {
+            let idx = enc.map_a1d1d4[s1 as usize];
+            if s1 & 7 > 3 || s1 >> 3 > 3 || off_a1h8(s1) > 0 {
+                continue;
+            }
+            for s2 in 0..64u8 {
+                if distance(s1, s2) <= 1 || (off_a1h8(s1) == 0 && off_a1h8(s2) > 0) {
+                    continue;
+                }
+                let code = enc.map_kk[idx][s2 as usize];
+                assert!(!seen[code]);
+                seen[code] = true;
+            }
+        }
+        assert!(seen.iter().all(|s| *s));
+    }
+
+    #[test]
+    fn pawn_encoding() {
+        let enc: &Encoding = &ENCODING;
+        let mut seen = vec![false; 48];
+        for sq in 8..56 {
+            seen[enc.map_pawns[sq]] = true;
+        }
+        assert!(seen.iter().all(|s| *s));
+        assert_eq!(enc.lead_pawns_size[1], [6; 4]);
+        assert_eq!(enc.binomial[2][5], 10);
+    }
+}
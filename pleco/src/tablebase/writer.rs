@@ -0,0 +1,1168 @@
+//! Writing Syzygy tables, used to create the tables the tests probe.
+//!
+//! The value of every position is found by retrograde analysis. Positions are first decided
+//! by their captures, pawn moves and mates, then by their other moves, one ply further at a
+//! time. The values are written in the same format as real tables: paired up into symbols,
+//! which are then Huffman coded into blocks.
+//!
+//! Nothing here is shared with the reader: positions are indexed and values compressed
+//! independently, so that reading the written tables back checks both sides against each
+//! other. This is only a stand-in for the official tables, which can be checked instead by
+//! pointing `PLECO_SYZYGY_PATH` at them when running the tests.
+//!
+//! En-passant captures are not searched, so only tables where at most one side has pawns can
+//! be written. The tables in `tests/syzygy` are written by the ignored `generate_fixtures`
+//! test:
+//!
+//! ```text
+//! cargo test --lib generate_fixtures -- --ignored
+//! ```
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use super::table::TableType;
+
+const PAWN: u8 = 1;
+const KNIGHT: u8 = 2;
+const BISHOP: u8 = 3;
+const ROOK: u8 = 4;
+const QUEEN: u8 = 5;
+const KING: u8 = 6;
+const BLACK: u8 = 8;
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+// Flags of a sub-table.
+const FLAG_STM: u8 = 1;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+/// The most pieces of a table that can be solved.
+const MAX_PIECES: usize = 4;
+
+/// The value of a position for the side to move: the distance to zeroing in plies, positive
+/// if it wins and negative if it loses, or zero for a draw.
+type Value = i16;
+const UNKNOWN: Value = i16::MAX;
+const ILLEGAL: Value = i16::MIN;
+/// Returned for mated positions while solving, which are then stored as a loss in one ply.
+const MATED: Value = i16::MIN + 1;
+
+const KNIGHT_STEPS: [(i8, i8); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+const KING_STEPS: [(i8, i8); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+const ROOK_STEPS: [(i8, i8); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const BISHOP_STEPS: [(i8, i8); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+// Blocks are 64 bytes, with an entry in the sparse index every 16384 values.
+const BLOCK_BITS: u8 = 6;
+const SPAN_BITS: u8 = 14;
+const MAX_BLOCK_VALUES: usize = 1 << 15;
+/// Symbols are numbered with 12 bits, with 0xFFF marking the values.
+const MAX_SYMBOLS: usize = 0xFFF;
+/// Pairs seen fewer times than this are not worth a symbol.
+const MIN_PAIRS: usize = 8;
+
+#[inline]
+fn color(code: u8) -> usize {
+    (code >> 3) as usize
+}
+
+#[inline]
+fn step(sq: u8, (df, dr): (i8, i8)) -> Option<u8> {
+    let file = (sq & 7) as i8 + df;
+    let rank = (sq >> 3) as i8 + dr;
+    if file < 0 || file > 7 || rank < 0 || rank > 7 {
+        None
+    } else {
+        Some((rank * 8 + file) as u8)
+    }
+}
+
+#[inline]
+fn occupancy(squares: &[u8]) -> u64 {
+    squares.iter().fold(0, |b, sq| b | 1 << sq)
+}
+
+/// Returns if a piece attacks a square.
+fn attacks(code: u8, from: u8, to: u8, occupied: u64) -> bool {
+    let df = (to & 7) as i8 - (from & 7) as i8;
+    let dr = (to >> 3) as i8 - (from >> 3) as i8;
+    let (straight, diagonal) = match code & 7 {
+        PAWN => return df.abs() == 1 && dr == if color(code) == 0 { 1 } else { -1 },
+        KNIGHT => return df.abs() * dr.abs() == 2,
+        KING => return df.abs().max(dr.abs()) == 1,
+        BISHOP => (false, true),
+        ROOK => (true, false),
+        _ => (true, true),
+    };
+    let on_line = (df == 0) != (dr == 0);
+    let on_diagonal = df != 0 && df.abs() == dr.abs();
+    if !(straight && on_line || diagonal && on_diagonal) {
+        return false;
+    }
+    let dir = (df.signum(), dr.signum());
+    let mut sq = step(from, dir).unwrap();
+    while sq != to {
+        if occupied & 1 << sq != 0 {
+            return false;
+        }
+        sq = step(sq, dir).unwrap();
+    }
+    true
+}
+
+/// Adds the squares a piece other than a pawn moves to, including the squares of the pieces
+/// it would capture.
+fn targets(code: u8, from: u8, occupied: u64, targets: &mut Vec<u8>) {
+    let (steps, slides): (&[(i8, i8)], bool) = match code & 7 {
+        KNIGHT => (&KNIGHT_STEPS, false),
+        BISHOP => (&BISHOP_STEPS, true),
+        ROOK => (&ROOK_STEPS, true),
+        QUEEN => (&KING_STEPS, true),
+        _ => (&KING_STEPS, false),
+    };
+    for s in steps {
+        let mut sq = from;
+        while let Some(next) = step(sq, *s) {
+            targets.push(next);
+            if !slides || occupied & 1 << next != 0 {
+                break;
+            }
+            sq = next;
+        }
+    }
+}
+
+/// Returns if the king of a player is attacked.
+fn in_check(pieces: &[u8], squares: &[u8], player: usize) -> bool {
+    let occupied = occupancy(squares);
+    let king = squares[pieces.iter().position(|c| *c == KING | player as u8 * BLACK).unwrap()];
+    pieces.iter()
+        .zip(squares)
+        .any(|(c, sq)| color(*c) != player && attacks(*c, *sq, king, occupied))
+}
+
+/// Returns the index of a position into the values of its material.
+#[inline]
+fn index(squares: &[u8], stm: usize) -> usize {
+    squares.iter().fold(stm, |idx, sq| idx * 64 + *sq as usize)
+}
+
+/// Returns the squares and side to move of an index into the values of a material.
+#[inline]
+fn position(mut idx: usize, squares: &mut [u8]) -> usize {
+    for sq in squares.iter_mut().rev() {
+        *sq = (idx & 63) as u8;
+        idx >>= 6;
+    }
+    idx
+}
+
+/// Returns how far the pawns of a position have advanced, or `None` if two pieces share a
+/// square or a pawn is on the first or last rank.
+fn pawn_progress(pieces: &[u8], squares: &[u8]) -> Option<usize> {
+    if occupancy(squares).count_ones() as usize != squares.len() {
+        return None;
+    }
+    let mut progress = 0;
+    for (code, sq) in pieces.iter().zip(squares).filter(|&(c, _)| c & 7 == PAWN) {
+        let rank = (sq >> 3) as usize;
+        if rank == 0 || rank == 7 {
+            return None;
+        }
+        progress += if color(*code) == 0 { rank - 1 } else { 6 - rank };
+    }
+    Some(progress)
+}
+
+/// Returns the sorted piece codes of a table name, such as "KRvK".
+fn material(name: &str) -> Vec<u8> {
+    let mut pieces = Vec::new();
+    for (player, side) in name.split('v').enumerate() {
+        for c in side.chars() {
+            let piece = match c {
+                'P' => PAWN,
+                'N' => KNIGHT,
+                'B' => BISHOP,
+                'R' => ROOK,
+                'Q' => QUEEN,
+                _ => KING,
+            };
+            pieces.push(piece | player as u8 * BLACK);
+        }
+    }
+    pieces.sort();
+    pieces
+}
+
+/// Returns the material a capture or promotion can lead to.
+fn sub_materials(pieces: &[u8]) -> Vec<Vec<u8>> {
+    let mut subs = Vec::new();
+    let without = |pieces: &[u8], i: usize| -> Vec<u8> {
+        let mut sub = pieces.to_vec();
+        sub.remove(i);
+        sub
+    };
+    for (i, code) in pieces.iter().enumerate().filter(|&(_, c)| c & 7 != KING) {
+        subs.push(without(pieces, i));
+        if code & 7 == PAWN {
+            for promotion in KNIGHT..KING {
+                let mut promoted = pieces.to_vec();
+                promoted[i] = promotion | code & BLACK;
+                for (j, other) in pieces.iter().enumerate() {
+                    if color(*other) != color(*code) && other & 7 != KING {
+                        subs.push(without(&promoted, j));
+                    }
+                }
+                subs.push(promoted);
+            }
+        }
+    }
+    for sub in subs.iter_mut() {
+        sub.sort();
+    }
+    subs
+}
+
+// What the captures and pawn moves of a position tell about it. The lowest two bits hold
+// the best result of a capture, if any, from 1 for a loss to 3 for a win.
+const CAPTURE_RESULT: u8 = 3;
+/// A capture or pawn move wins.
+const ZEROING_WINS: u8 = 4;
+/// Every move is a capture or pawn move.
+const ONLY_ZEROING: u8 = 8;
+/// Every move is a capture.
+const ONLY_CAPTURES: u8 = 16;
+
+/// The values of every position of some material, indexed by the squares of its pieces in
+/// the order of their piece codes, and by the side to move.
+pub struct Solution {
+    pub values: Vec<Value>,
+    /// What the captures and pawn moves of each position tell about it, as a set of the
+    /// `CAPTURE_RESULT`, `ZEROING_WINS`, `ONLY_ZEROING` and `ONLY_CAPTURES` flags.
+    pub zeroing: Vec<u8>,
+}
+
+/// Solves every position of a material, along with the material it can turn into.
+#[derive(Default)]
+pub struct Solver {
+    solved: HashMap<Vec<u8>, Solution>,
+}
+
+impl Solver {
+    pub fn new() -> Solver {
+        Solver::default()
+    }
+
+    /// Returns the solution of the material of a table, such as "KRvK".
+    pub fn solve(&mut self, name: &str) -> &Solution {
+        let pieces = material(name);
+        self.solve_material(&pieces);
+        &self.solved[&pieces]
+    }
+
+    fn solve_material(&mut self, pieces: &[u8]) {
+        if self.solved.contains_key(pieces) {
+            return;
+        }
+        assert!(pieces.len() <= MAX_PIECES);
+        for sub in sub_materials(pieces) {
+            self.solve_material(&sub);
+        }
+        let solution = self.retrograde(pieces);
+        self.solved.insert(pieces.to_vec(), solution);
+    }
+
+    fn retrograde(&self, pieces: &[u8]) -> Solution {
+        let n = pieces.len();
+        let placements = 1 << (6 * n);
+        let mut values = vec![ILLEGAL; 2 * placements];
+        // The number of moves that are neither captures nor pawn moves, and not known to
+        // lose yet. The top bit is set if a capture or pawn move draws.
+        let mut counts = vec![0u8; 2 * placements];
+        let mut zeroing = vec![0u8; 2 * placements];
+        let mut squares = [0u8; MAX_PIECES];
+        let mut prev = [0u8; MAX_PIECES];
+        let mut targets_buf = Vec::new();
+
+        // Pawn moves can't be undone, so positions are solved from the most advanced pawns.
+        let pawns = pieces.iter().filter(|c| *c & 7 == PAWN).count();
+        for progress in (0..6 * pawns + 1).rev() {
+            // Mates are found one ply before the other decided positions, as mating needs
+            // no capture or pawn move.
+            let mut mated = Vec::new();
+            let mut decided = Vec::new();
+            for placement in 0..placements {
+                position(placement, &mut squares[..n]);
+                if pawn_progress(pieces, &squares[..n]) != Some(progress) {
+                    continue;
+                }
+                for stm in 0..2 {
+                    if in_check(pieces, &squares[..n], 1 - stm) {
+                        continue;
+                    }
+                    let idx = stm * placements + placement;
+                    let (value, count, flags) = self.decide(pieces, &squares[..n], stm, &values);
+                    values[idx] = value;
+                    counts[idx] = count;
+                    zeroing[idx] = flags;
+                    if value == MATED {
+                        values[idx] = -1;
+                        mated.push(idx);
+                    } else if value != UNKNOWN && value != 0 {
+                        decided.push(idx);
+                    }
+                }
+            }
+
+            // Positions one ply before a loss win, and positions with every move leading
+            // to a win lose.
+            let mut frontier = mated;
+            let mut dtz: Value = 0;
+            while !frontier.is_empty() || dtz == 0 {
+                assert!(dtz < 100, "cursed wins are not supported");
+                let mut next = if dtz == 0 { decided.split_off(0) } else { Vec::new() };
+                for &idx in &frontier {
+                    let stm = position(idx, &mut squares[..n]);
+                    let mover = 1 - stm;
+                    let lost = values[idx] < 0;
+                    let occupied = occupancy(&squares[..n]);
+                    for i in (0..n).filter(|i| color(pieces[*i]) == mover && pieces[*i] & 7 != PAWN) {
+                        targets_buf.clear();
+                        targets(pieces[i], squares[i], occupied, &mut targets_buf);
+                        for &from in targets_buf.iter().filter(|sq| occupied & 1 << **sq == 0) {
+                            prev[..n].copy_from_slice(&squares[..n]);
+                            prev[i] = from;
+                            if in_check(pieces, &prev[..n], stm) {
+                                continue;
+                            }
+                            let prev_idx = index(&prev[..n], mover);
+                            if values[prev_idx] != UNKNOWN {
+                                continue;
+                            }
+                            if lost {
+                                values[prev_idx] = dtz + 1;
+                                next.push(prev_idx);
+                            } else {
+                                counts[prev_idx] -= 1;
+                                if counts[prev_idx] == 0 {
+                                    values[prev_idx] = -(dtz + 1);
+                                    next.push(prev_idx);
+                                }
+                            }
+                        }
+                    }
+                }
+                frontier = next;
+                dtz += 1;
+            }
+        }
+
+        for value in values.iter_mut().filter(|v| **v == UNKNOWN) {
+            *value = 0;
+        }
+        Solution { values, zeroing }
+    }
+
+    /// Returns the value of a position if its captures, pawn moves or lack of moves decide
+    /// it, and `UNKNOWN` otherwise, along with the count of its other moves and what its
+    /// captures and pawn moves tell about it.
+    fn decide(&self, pieces: &[u8], squares: &[u8], stm: usize, values: &[Value]) -> (Value, u8, u8) {
+        let n = pieces.len();
+        let occupied = occupancy(squares);
+        let mut moves: Vec<(usize, u8)> = Vec::new();
+        let mut targets_buf = Vec::new();
+        for i in (0..n).filter(|i| color(pieces[*i]) == stm) {
+            let from = squares[i];
+            if pieces[i] & 7 == PAWN {
+                let forward = if stm == 0 { 1 } else { -1 };
+                let one = step(from, (0, forward)).unwrap();
+                if occupied & 1 << one == 0 {
+                    moves.push((i, one));
+                    let start_rank = if stm == 0 { 1 } else { 6 };
+                    let two = step(one, (0, forward));
+                    if from >> 3 == start_rank && occupied & 1 << two.unwrap() == 0 {
+                        moves.push((i, two.unwrap()));
+                    }
+                }
+                for df in [-1, 1].iter() {
+                    if let Some(to) = step(from, (*df, forward)) {
+                        if occupied & 1 << to != 0 {
+                            moves.push((i, to));
+                        }
+                    }
+                }
+            } else {
+                targets_buf.clear();
+                targets(pieces[i], from, occupied, &mut targets_buf);
+                moves.extend(targets_buf.iter().map(|to| (i, *to)));
+            }
+        }
+
+        let mut legal = 0;
+        let mut others = 0;
+        let mut captures = 0;
+        let mut best_capture = 0;
+        let mut best_zeroing = -1;
+        let mut child = [0u8; MAX_PIECES];
+        for (i, to) in moves {
+            let captured = squares.iter().position(|sq| *sq == to);
+            if captured.map_or(false, |j| color(pieces[j]) == stm) {
+                continue;
+            }
+            let pawn = pieces[i] & 7 == PAWN;
+            if captured.is_none() && !pawn {
+                child[..n].copy_from_slice(squares);
+                child[i] = to;
+                if !in_check(pieces, &child[..n], stm) {
+                    legal += 1;
+                    others += 1;
+                }
+                continue;
+            }
+
+            let promotions: &[u8] = if pawn && (to >> 3 == 0 || to >> 3 == 7) {
+                &[QUEEN, ROOK, BISHOP, KNIGHT]
+            } else {
+                &[0]
+            };
+            for promotion in promotions {
+                let mut after: Vec<(u8, u8)> = (0..n)
+                    .filter(|j| Some(*j) != captured)
+                    .map(|j| if j == i { (pieces[j], to) } else { (pieces[j], squares[j]) })
+                    .collect();
+                if *promotion != 0 {
+                    let moved = after.iter().position(|p| p.1 == to).unwrap();
+                    after[moved].0 = promotion | pieces[i] & BLACK;
+                }
+                after.sort_by_key(|p| p.0);
+                let after_pieces: Vec<u8> = after.iter().map(|p| p.0).collect();
+                let after_squares: Vec<u8> = after.iter().map(|p| p.1).collect();
+                if in_check(&after_pieces, &after_squares, stm) {
+                    continue;
+                }
+                legal += 1;
+                let idx = index(&after_squares, 1 - stm);
+                let value = if after_pieces[..] == pieces[..] {
+                    values[idx]
+                } else {
+                    self.solved[&after_pieces].values[idx]
+                };
+                let result = -value.signum();
+                best_zeroing = best_zeroing.max(result);
+                if captured.is_some() {
+                    captures += 1;
+                    best_capture = best_capture.max(result as u8 + 2);
+                }
+            }
+        }
+
+        let mut flags = best_capture;
+        if best_zeroing > 0 {
+            flags |= ZEROING_WINS;
+        }
+        if legal > 0 && others == 0 {
+            flags |= ONLY_ZEROING;
+        }
+        if legal > 0 && captures == legal {
+            flags |= ONLY_CAPTURES;
+        }
+        if legal == 0 {
+            (if in_check(pieces, squares, stm) { MATED } else { 0 }, 0, flags)
+        } else if best_zeroing > 0 {
+            (1, 0, flags)
+        } else if others == 0 {
+            (best_zeroing, 0, flags)
+        } else {
+            (UNKNOWN, others | if best_zeroing == 0 { 0x80 } else { 0 }, flags)
+        }
+    }
+}
+
+#[inline]
+fn push_u16(data: &mut Vec<u8>, value: u16) {
+    data.push(value as u8);
+    data.push((value >> 8) as u8);
+}
+
+#[inline]
+fn push_u32(data: &mut Vec<u8>, value: u32) {
+    push_u16(data, value as u16);
+    push_u16(data, (value >> 16) as u16);
+}
+
+/// A sub-table, compressed into blocks.
+struct Compressed {
+    header: Vec<u8>,
+    sparse_index: Vec<u8>,
+    block_lengths: Vec<u8>,
+    blocks: Vec<u8>,
+}
+
+impl Compressed {
+    fn len(&self) -> usize {
+        self.header.len() + self.sparse_index.len() + self.block_lengths.len() + self.blocks.len()
+    }
+}
+
+/// Returns the length of the Huffman code of each symbol, given how often they are used.
+fn code_lengths(freq: &[u64]) -> Vec<u8> {
+    let mut weights = freq.to_vec();
+    loop {
+        let mut heap: BinaryHeap<Reverse<(u64, usize)>> = weights.iter()
+            .enumerate()
+            .map(|(i, w)| Reverse((*w, i)))
+            .collect();
+        let mut parent = vec![usize::max_value(); weights.len()];
+        while heap.len() > 1 {
+            let Reverse((w1, a)) = heap.pop().unwrap();
+            let Reverse((w2, b)) = heap.pop().unwrap();
+            parent[a] = parent.len();
+            parent[b] = parent.len();
+            heap.push(Reverse((w1 + w2, parent.len())));
+            parent.push(usize::max_value());
+        }
+        let lengths: Vec<u8> = (0..weights.len())
+            .map(|mut node| {
+                let mut len = 0;
+                while parent[node] != usize::max_value() {
+                    node = parent[node];
+                    len += 1;
+                }
+                len
+            })
+            .collect();
+        // Codes are read 32 bits at a time, so flatten the weights until they fit.
+        if lengths.iter().all(|l| *l <= 32) {
+            return lengths;
+        }
+        for w in weights.iter_mut() {
+            *w = (*w + 1) / 2;
+        }
+    }
+}
+
+/// Compresses the values of a sub-table.
+fn compress(values: &[u16], flags: u8) -> Compressed {
+    if values.iter().all(|v| *v == values[0]) {
+        assert!(values[0] < 256);
+        return Compressed {
+            header: vec![flags | FLAG_SINGLE_VALUE, values[0] as u8],
+            sparse_index: Vec::new(),
+            block_lengths: Vec::new(),
+            blocks: Vec::new(),
+        };
+    }
+
+    // Every symbol is either a value, or a pair of symbols.
+    let mut symbols: Vec<(u16, u16)> = Vec::new();
+    let mut lengths: Vec<usize> = Vec::new();
+    let mut leaves: HashMap<u16, u16> = HashMap::new();
+    let mut seq: Vec<u16> = Vec::with_capacity(values.len());
+    for value in values {
+        assert!(*value < 0xFFF);
+        if !leaves.contains_key(value) {
+            leaves.insert(*value, symbols.len() as u16);
+            symbols.push((*value, 0xFFF));
+            lengths.push(1);
+        }
+        seq.push(leaves[value]);
+    }
+
+    // Replace the most frequent pairs of symbols, many at a time, as long as a symbol
+    // expands to at most 256 values.
+    while symbols.len() < MAX_SYMBOLS {
+        let mut counts: HashMap<(u16, u16), usize> = HashMap::new();
+        for pair in seq.windows(2) {
+            *counts.entry((pair[0], pair[1])).or_insert(0) += 1;
+        }
+        let mut pairs: Vec<((u16, u16), usize)> = counts.into_iter()
+            .filter(|&((a, b), count)| count >= MIN_PAIRS && lengths[a as usize] + lengths[b as usize] <= 256)
+            .collect();
+        if pairs.is_empty() {
+            break;
+        }
+        pairs.sort_by(|x, y| y.1.cmp(&x.1).then(x.0.cmp(&y.0)));
+        pairs.truncate((MAX_SYMBOLS - symbols.len()).min(64));
+
+        let mut new_symbols: HashMap<(u16, u16), u16> = HashMap::new();
+        for &((a, b), _) in &pairs {
+            new_symbols.insert((a, b), symbols.len() as u16);
+            symbols.push((a, b));
+            lengths.push(lengths[a as usize] + lengths[b as usize]);
+        }
+        let mut paired = Vec::with_capacity(seq.len());
+        let mut i = 0;
+        while i < seq.len() {
+            match seq.get(i + 1).and_then(|b| new_symbols.get(&(seq[i], *b))) {
+                Some(s) => {
+                    paired.push(*s);
+                    i += 2;
+                },
+                None => {
+                    paired.push(seq[i]);
+                    i += 1;
+                }
+            }
+        }
+        seq = paired;
+    }
+
+    // Build a canonical Huffman code over the symbols used, where longer codes have lower
+    // values and the symbols are numbered from the longest code.
+    let mut freq = vec![0u64; symbols.len()];
+    for s in &seq {
+        freq[*s as usize] += 1;
+    }
+    let mut used: Vec<usize> = (0..symbols.len()).filter(|s| freq[*s] > 0).collect();
+    if used.len() == 1 {
+        used.push((0..symbols.len()).find(|s| freq[*s] == 0).unwrap());
+    }
+    let used_lengths = code_lengths(&used.iter().map(|s| freq[*s].max(1)).collect::<Vec<u64>>());
+    let mut coded: Vec<(u8, usize)> = used.iter().zip(&used_lengths).map(|(s, l)| (*l, *s)).collect();
+    coded.sort_by_key(|&(l, s)| (Reverse(l), s));
+    let min_len = coded.last().unwrap().0;
+    let max_len = coded[0].0;
+
+    let mut number = vec![usize::max_value(); symbols.len()];
+    for (i, &(_, s)) in coded.iter().enumerate() {
+        number[s] = i;
+    }
+    let mut next_number = coded.len();
+    for n in number.iter_mut().filter(|n| **n == usize::max_value()) {
+        *n = next_number;
+        next_number += 1;
+    }
+
+    let len_count = |l: u8| coded.iter().filter(|c| c.0 == l).count() as u64;
+    let mut lowest = vec![0u64; (max_len - min_len) as usize + 1];
+    let mut first_code = vec![0u64; lowest.len()];
+    for l in (min_len..max_len).rev() {
+        let i = (l - min_len) as usize;
+        lowest[i] = lowest[i + 1] + len_count(l + 1);
+        assert_eq!((first_code[i + 1] + len_count(l + 1)) % 2, 0);
+        first_code[i] = (first_code[i + 1] + len_count(l + 1)) / 2;
+    }
+    assert_eq!(first_code[0] + len_count(min_len), 1 << min_len);
+    let mut codes = vec![(0u64, 0u8); symbols.len()];
+    for (i, &(l, s)) in coded.iter().enumerate() {
+        let j = (l - min_len) as usize;
+        codes[s] = (first_code[j] + i as u64 - lowest[j], l);
+    }
+
+    // Fill the blocks with whole symbols.
+    let block_size = 1usize << BLOCK_BITS;
+    let mut blocks = Vec::new();
+    let mut block_values = Vec::new();
+    let mut block = vec![0u8; block_size];
+    let mut bits = 0;
+    let mut count = 0;
+    for s in &seq {
+        let (code, len) = codes[*s as usize];
+        let len = len as usize;
+        let n = lengths[*s as usize];
+        if bits + len > block_size * 8 || count + n > MAX_BLOCK_VALUES {
+            blocks.extend_from_slice(&block);
+            block_values.push(count);
+            block = vec![0u8; block_size];
+            bits = 0;
+            count = 0;
+        }
+        for b in (0..len).rev() {
+            if code >> b & 1 != 0 {
+                block[bits / 8] |= 0x80 >> (bits % 8);
+            }
+            bits += 1;
+        }
+        count += n;
+    }
+    blocks.extend_from_slice(&block);
+    block_values.push(count);
+
+    // Each entry of the sparse index points to the block holding the value in the middle of
+    // its span.
+    let mut starts = vec![0u64];
+    for count in &block_values {
+        let last = *starts.last().unwrap();
+        starts.push(last + *count as u64);
+    }
+    let size = values.len() as u64;
+    let span = 1u64 << SPAN_BITS;
+    let mut sparse_index = Vec::new();
+    for k in 0..(size + span - 1) / span {
+        let mid = k * span + span / 2;
+        let b = match starts[..block_values.len()].binary_search(&mid.min(size - 1)) {
+            Ok(b) => b,
+            Err(b) => b - 1,
+        };
+        let offset = mid - starts[b];
+        assert!(offset <= 0xFFFF);
+        push_u32(&mut sparse_index, b as u32);
+        push_u16(&mut sparse_index, offset as u16);
+    }
+    let mut block_lengths = Vec::new();
+    for count in &block_values {
+        push_u16(&mut block_lengths, *count as u16 - 1);
+    }
+
+    let mut header = vec![flags, BLOCK_BITS, SPAN_BITS, 0];
+    push_u32(&mut header, block_values.len() as u32);
+    header.push(max_len);
+    header.push(min_len);
+    for l in &lowest {
+        push_u16(&mut header, *l as u16);
+    }
+    push_u16(&mut header, symbols.len() as u16);
+    let mut tree = vec![(0u16, 0u16); symbols.len()];
+    for (s, &(left, right)) in symbols.iter().enumerate() {
+        tree[number[s]] = if right == 0xFFF {
+            (left, right)
+        } else {
+            (number[left as usize] as u16, number[right as usize] as u16)
+        };
+    }
+    for (left, right) in tree {
+        header.push(left as u8);
+        header.push((left >> 8) as u8 | (right << 4) as u8);
+        header.push((right >> 4) as u8);
+    }
+    if symbols.len() & 1 != 0 {
+        header.push(0);
+    }
+
+    Compressed { header, sparse_index, block_lengths, blocks }
+}
+
+/// The squares of the a1-d1-d4 triangle, below the a1-h8 diagonal first.
+const TRIANGLE: [u8; 10] = [1, 2, 3, 10, 11, 19, 0, 9, 18, 27];
+
+/// Returns how far a square is above the a1-h8 diagonal, negative if below it.
+#[inline]
+fn above_diagonal(sq: u8) -> i8 {
+    (sq >> 3) as i8 - (sq & 7) as i8
+}
+
+/// Returns the number of ways to choose `k` elements from `n`.
+fn binomial(k: usize, n: usize) -> u64 {
+    if k > n {
+        return 0;
+    }
+    (0..k as u64).fold(1, |b, i| b * (n as u64 - i) / (i + 1))
+}
+
+/// Returns the number of the squares a2-h7 used to order pawns, from 47 on a2 down to 0 on
+/// e7, going up the files a to d and their mirrored files together.
+fn pawn_number(sq: u8) -> usize {
+    let (file, rank) = ((sq & 7) as usize, (sq >> 3) as usize);
+    let edge_file = file.min(7 - file);
+    47 - 2 * (edge_file * 6 + rank - 1) - (file > 3) as usize
+}
+
+/// Returns the number of placements of `count` leading pawns whose lead pawn is on a file
+/// before reaching a rank, or the whole file for rank 7.
+fn lead_pawn_placements(count: usize, file: u8, rank: u8) -> u64 {
+    (1..rank).map(|r| binomial(count - 1, pawn_number(r * 8 + file))).sum()
+}
+
+/// How the pieces of a table are ordered and encoded.
+///
+/// The first group of pieces is encoded together: the leading pawns if there are pawns,
+/// otherwise the first three pieces if any piece has no twin, or else the two kings. Every
+/// following group is a run of identical pieces, encoded as a combination of the squares left.
+struct Layout {
+    order: Vec<u8>,
+    groups: Vec<usize>,
+    pawns: bool,
+    unique: bool,
+    /// Indexes the second king from each triangle square of the first.
+    kings: Vec<[u64; 64]>,
+}
+
+impl Layout {
+    fn new(pieces: &[u8]) -> Layout {
+        let count = |code: u8| pieces.iter().filter(|c| **c == code).count();
+        let pawns = count(PAWN) + count(PAWN | BLACK) > 0;
+        assert!(count(PAWN) == 0 || count(PAWN | BLACK) == 0, "only one side may have pawns");
+        let unique = pieces.iter().any(|c| *c & 7 != KING && count(*c) == 1);
+
+        // The leading pawns first, then the pieces without a twin.
+        let mut order = pieces.to_vec();
+        order.sort_by_key(|c| (*c & 7 != PAWN, count(*c) > 1, *c));
+
+        let mut groups = vec![if pawns { count(order[0]) } else if unique { 3 } else { 2 }];
+        let mut i = groups[0];
+        while i < order.len() {
+            let len = count(order[i]);
+            groups.push(len);
+            i += len;
+        }
+
+        // Both kings on the diagonal come last, and the second king never goes above the
+        // diagonal when the first is on it.
+        let mut kings = vec![[0u64; 64]; 10];
+        let mut code = 0;
+        let mut both_on_diagonal = Vec::new();
+        for (t, &k1) in TRIANGLE.iter().enumerate() {
+            for k2 in 0..64u8 {
+                let adjacent = ((k1 & 7) as i8 - (k2 & 7) as i8).abs() <= 1
+                    && ((k1 >> 3) as i8 - (k2 >> 3) as i8).abs() <= 1;
+                if adjacent || (above_diagonal(k1) == 0 && above_diagonal(k2) > 0) {
+                    continue;
+                }
+                if above_diagonal(k1) == 0 && above_diagonal(k2) == 0 {
+                    both_on_diagonal.push((t, k2));
+                } else {
+                    kings[t][k2 as usize] = code;
+                    code += 1;
+                }
+            }
+        }
+        for (t, k2) in both_on_diagonal {
+            kings[t][k2 as usize] = code;
+            code += 1;
+        }
+        assert_eq!(code, 462);
+
+        Layout { order, groups, pawns, unique, kings }
+    }
+
+    /// Returns the index factor of every group of a file's sub-table, and its size.
+    fn factors(&self, file: u8) -> (Vec<u64>, u64) {
+        let mut factors = vec![1];
+        let mut size = if self.pawns {
+            lead_pawn_placements(self.groups[0], file, 7)
+        } else if self.unique {
+            31332
+        } else {
+            462
+        };
+        let mut free = 64 - self.groups[0];
+        for len in &self.groups[1..] {
+            factors.push(size);
+            size *= binomial(*len, free);
+            free -= len;
+        }
+        (factors, size)
+    }
+
+    /// Returns the file of the sub-table holding a position, along with its index there. The
+    /// squares are those of the pieces in the order of the layout.
+    fn encode(&self, squares: &mut [u8]) -> (usize, u64) {
+        let lead = self.groups[0];
+        let mut idx;
+        let mut file = 0;
+        if self.pawns {
+            // The leading pawn is the one with the highest number, and decides the file.
+            let first = (0..lead).max_by_key(|i| pawn_number(squares[*i])).unwrap();
+            squares.swap(0, first);
+            if squares[0] & 7 > 3 {
+                for sq in squares.iter_mut() {
+                    *sq ^= 7;
+                }
+            }
+            file = squares[0] & 7;
+            squares[1..lead].sort_by_key(|sq| pawn_number(*sq));
+            idx = lead_pawn_placements(lead, file, squares[0] >> 3);
+            for i in 1..lead {
+                idx += binomial(i, pawn_number(squares[i]));
+            }
+        } else {
+            // Mirror the first piece into the a1-d1-d4 triangle, and the first piece off the
+            // diagonal below it.
+            if squares[0] & 7 > 3 {
+                for sq in squares.iter_mut() {
+                    *sq ^= 7;
+                }
+            }
+            if squares[0] >> 3 > 3 {
+                for sq in squares.iter_mut() {
+                    *sq ^= 0o70;
+                }
+            }
+            if let Some(off) = squares[..lead].iter().map(|sq| above_diagonal(*sq)).find(|off| *off != 0) {
+                if off > 0 {
+                    for sq in squares.iter_mut() {
+                        *sq = (*sq >> 3) | (*sq & 7) << 3;
+                    }
+                }
+            }
+
+            let triangle = |sq: u8| TRIANGLE.iter().position(|t| *t == sq).unwrap() as u64;
+            let below = |sq: u8| (0..sq).filter(|s| above_diagonal(*s) < 0).count() as u64;
+            let rank = |sq: u8| u64::from(sq >> 3);
+            if self.unique {
+                let (a, b, c) = (squares[0], squares[1], squares[2]);
+                let skip_b = (b > a) as u64;
+                let skip_c = (c > a) as u64 + (c > b) as u64;
+                idx = if above_diagonal(a) != 0 {
+                    (triangle(a) * 63 + u64::from(b) - skip_b) * 62 + u64::from(c) - skip_c
+                } else if above_diagonal(b) != 0 {
+                    (6 * 63 + rank(a) * 28 + below(b)) * 62 + u64::from(c) - skip_c
+                } else if above_diagonal(c) != 0 {
+                    6 * 63 * 62 + 4 * 28 * 62 + rank(a) * 7 * 28 + (rank(b) - skip_b) * 28 + below(c)
+                } else {
+                    6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + rank(a) * 7 * 6 + (rank(b) - skip_b) * 6 + rank(c) - skip_c
+                };
+            } else {
+                idx = self.kings[triangle(squares[0]) as usize][squares[1] as usize];
+            }
+        }
+
+        let (factors, _) = self.factors(file);
+        let mut start = lead;
+        for (len, factor) in self.groups[1..].iter().zip(factors[1..].iter()) {
+            squares[start..start + len].sort();
+            let mut n = 0;
+            for i in 0..*len {
+                let sq = squares[start + i];
+                let taken = squares[..start].iter().filter(|s| **s < sq).count();
+                n += binomial(i + 1, sq as usize - taken);
+            }
+            idx += n * factor;
+            start += len;
+        }
+        (file as usize, idx)
+    }
+}
+
+/// Returns the values a table may store for a position, from the lowest to the highest.
+///
+/// The reader searches the captures of a position before trusting its WDL value, and its
+/// captures and pawn moves before reading its DTZ value, so whatever they decide is left
+/// free.
+fn stored_range(value: Value, zeroing: u8, table_type: TableType) -> (u16, u16) {
+    match table_type {
+        TableType::Wdl => {
+            let wdl = (value.signum() * 2 + 2) as u16;
+            let capture = zeroing & CAPTURE_RESULT;
+            if capture == 3 || zeroing & ONLY_CAPTURES != 0 {
+                (0, 4)
+            } else if capture != 0 && (u16::from(capture) - 1) * 2 == wdl {
+                (0, wdl)
+            } else {
+                (wdl, wdl)
+            }
+        },
+        TableType::Dtz => {
+            if value == 0 || zeroing & ZEROING_WINS != 0 || (value < 0 && zeroing & ONLY_ZEROING != 0) {
+                (0, u16::max_value())
+            } else {
+                let dtz = (value.abs() - 1).max(0) as u16;
+                (dtz, dtz)
+            }
+        },
+    }
+}
+
+/// Returns the contents of the WDL or DTZ file of a table.
+fn table_data(pieces: &[u8], solution: &Solution, table_type: TableType) -> Vec<u8> {
+    let layout = Layout::new(pieces);
+    let mut mirrored: Vec<u8> = pieces.iter().map(|c| c ^ BLACK).collect();
+    mirrored.sort();
+    assert!(mirrored[..] != pieces[..], "symmetric tables are not supported");
+    let sides = if table_type == TableType::Wdl { 2 } else { 1 };
+    let files = if layout.pawns { 4 } else { 1 };
+    let flags = if table_type == TableType::Wdl { 0 } else { FLAG_WIN_PLIES | FLAG_LOSS_PLIES };
+
+    let mut data = match table_type {
+        TableType::Wdl => WDL_MAGIC,
+        TableType::Dtz => DTZ_MAGIC,
+    }.to_vec();
+    data.push(1 | (layout.pawns as u8) << 1);
+    for _ in 0..files {
+        data.push(0x00);
+        data.extend(layout.order.iter().map(|c| c | c << 4));
+    }
+    if data.len() & 1 != 0 {
+        data.push(0);
+    }
+
+    // DTZ tables store a single side to move, so try both and keep whichever is smaller.
+    let stored_sides: &[usize] = if table_type == TableType::Dtz { &[0, 1] } else { &[0] };
+    let mut compressed: Vec<Option<Compressed>> = (0..files * sides).map(|_| None).collect();
+    for &stored_side in stored_sides {
+        let mut ranges: Vec<Vec<(u16, u16)>> = (0..sides * files)
+            .map(|i| vec![(0, u16::max_value()); layout.factors((i % files) as u8).1 as usize])
+            .collect();
+        let mut squares = [0u8; MAX_PIECES];
+        let mut ordered = [0u8; MAX_PIECES];
+        for (idx, value) in solution.values.iter().enumerate().filter(|&(_, v)| *v != ILLEGAL) {
+            let stm = position(idx, &mut squares[..pieces.len()]);
+            if sides == 1 && stm != stored_side {
+                continue;
+            }
+            // Pieces are solved in the order of their codes, and twins take the squares in
+            // either order, so placing them in the layout order needs no care.
+            let mut used = [false; MAX_PIECES];
+            for (i, code) in layout.order.iter().enumerate() {
+                let j = (0..pieces.len()).find(|j| !used[*j] && pieces[*j] == *code).unwrap();
+                used[j] = true;
+                ordered[i] = squares[j];
+            }
+            let (file, i) = layout.encode(&mut ordered[..pieces.len()]);
+            let (lo, hi) = stored_range(*value, solution.zeroing[idx], table_type);
+            let range = &mut ranges[(stm % sides) * files + file][i as usize];
+            *range = (range.0.max(lo), range.1.min(hi));
+            assert!(range.0 <= range.1, "positions disagree");
+        }
+
+        // Positions free to store anything keep the previous value, which compresses best.
+        for f in 0..files {
+            for side in 0..sides {
+                let mut last = 0;
+                let values: Vec<u16> = ranges[side * files + f].iter()
+                    .map(|&(lo, hi)| {
+                        last = last.max(lo).min(hi);
+                        last
+                    })
+                    .collect();
+                let c = compress(&values, flags | stored_side as u8 * FLAG_STM);
+                let slot = &mut compressed[f * sides + side];
+                if slot.as_ref().map_or(true, |s| c.len() < s.len()) {
+                    *slot = Some(c);
+                }
+            }
+        }
+    }
+    let compressed: Vec<Compressed> = compressed.into_iter().map(Option::unwrap).collect();
+
+    for c in &compressed {
+        data.extend_from_slice(&c.header);
+    }
+    if table_type == TableType::Dtz && data.len() & 1 != 0 {
+        data.push(0);
+    }
+    for c in &compressed {
+        data.extend_from_slice(&c.sparse_index);
+    }
+    for c in &compressed {
+        data.extend_from_slice(&c.block_lengths);
+    }
+    for c in &compressed {
+        while data.len() % 64 != 0 {
+            data.push(0);
+        }
+        data.extend_from_slice(&c.blocks);
+    }
+    while data.len() % 64 != 0 {
+        data.push(0);
+    }
+    data.extend_from_slice(&[0; 16]);
+    data
+}
+
+/// Solves a table and writes its WDL and DTZ files into a directory.
+pub fn write_table(solver: &mut Solver, name: &str, dir: &Path) -> io::Result<()> {
+    let pieces = material(name);
+    let solution = solver.solve(name);
+    for table_type in [TableType::Wdl, TableType::Dtz].iter() {
+        let data = table_data(&pieces, solution, *table_type);
+        File::create(dir.join(name.to_string() + table_type.extension()))?.write_all(&data)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use board::Board;
+    use tablebase::{Tablebases, Wdl};
+    use tablebase::bitbase::Bitbase;
+
+    /// The tables kept in `tests/syzygy`.
+    const FIXTURES: [&str; 6] = ["KQvK", "KRvK", "KBvK", "KNvK", "KPvK", "KNNvK"];
+
+    fn board(pieces: &[u8], squares: &[u8], stm: usize) -> Board {
+        let mut fen = String::new();
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match squares.iter().position(|sq| *sq == rank * 8 + file) {
+                    Some(i) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        let c = b"?PNBRQK"[(pieces[i] & 7) as usize] as char;
+                        fen.push(if color(pieces[i]) == 0 { c } else { c.to_ascii_lowercase() });
+                    },
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            fen.push(if rank == 0 { ' ' } else { '/' });
+        }
+        fen.push_str(if stm == 0 { "w - - 0 1" } else { "b - - 0 1" });
+        Board::new_from_fen(&fen).unwrap()
+    }
+
+    #[test]
+    fn solved_values() {
+        let mut solver = Solver::new();
+        // The longest mates with a queen and with a rook take 10 and 16 moves.
+        assert_eq!(solver.solve("KQvK").values.iter().cloned().max(), Some(19));
+        assert_eq!(solver.solve("KRvK").values.iter().cloned().max(), Some(31));
+        assert_eq!(solver.solve("KBvK").values.iter().cloned().filter(|v| *v != ILLEGAL).max(), Some(0));
+
+        // KPvK agrees with its bitbase.
+        let bitbase = Bitbase::generate("KPK").unwrap();
+        let mut squares = [0u8; 3];
+        for (idx, value) in solver.solve("KPvK").values.iter().enumerate().filter(|&(_, v)| *v != ILLEGAL).step_by(7) {
+            let stm = position(idx, &mut squares);
+            let board = board(&material("KPvK"), &squares, stm);
+            assert_eq!(bitbase.probe(&board), Some(Wdl::from_i32(i32::from(value.signum()) * 2)), "{}", board.get_fen());
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = env::temp_dir().join(format!("pleco_syzygy_writer_{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut solver = Solver::new();
+        for name in ["KQvK", "KRvK", "KBvK", "KNvK", "KPvK"].iter() {
+            write_table(&mut solver, name, &dir).unwrap();
+        }
+        let tablebases = Tablebases::new(dir.to_str().unwrap());
+        assert_eq!(tablebases.len(), 5);
+
+        // The writer indexes positions on its own, so the reader must find the same value,
+        // also with the colors swapped.
+        let mut squares = [0u8; 3];
+        for name in ["KRvK", "KPvK"].iter() {
+            let pieces = material(name);
+            let swapped: Vec<u8> = pieces.iter().map(|c| c ^ BLACK).collect();
+            for (idx, value) in solver.solve(name).values.iter().enumerate().filter(|&(_, v)| *v != ILLEGAL).step_by(11) {
+                let stm = position(idx, &mut squares);
+                let mirrored: Vec<u8> = squares.iter().map(|sq| sq ^ 0o70).collect();
+                for board in [board(&pieces, &squares, stm), board(&swapped, &mirrored, 1 - stm)].iter() {
+                    let wdl = Wdl::from_i32(i32::from(value.signum()) * 2);
+                    assert_eq!(tablebases.probe_wdl(board), Some(wdl), "{}", board.get_fen());
+                    assert_eq!(tablebases.probe_dtz(board), Some(i32::from(*value)), "{}", board.get_fen());
+                }
+            }
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn generate_fixtures() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("syzygy");
+        fs::create_dir_all(&dir).unwrap();
+        let mut solver = Solver::new();
+        for name in FIXTURES.iter() {
+            write_table(&mut solver, name, &dir).unwrap();
+        }
+    }
+}
@@ -3,6 +3,8 @@ extern crate rand;
 
 use pleco::board::Board;
 use pleco::core::piece_move::BitMove;
+use pleco::core::sq::NO_SQ;
+use pleco::{Player, SQ};
 use std::*;
 
 
@@ -31,12 +33,18 @@ fn apply_null_moves() {
             chess_board.apply_move(moves[rand::random::<usize>() % moves.len()]);
             moves = chess_board.generate_moves();
             assert!(!chess_board.legal_move(null_move));
-            unsafe {
-                if !chess_board.in_check() {
-                    chess_board.apply_null_move();
-                    chess_board.undo_null_move();
-                }
+            let fen = chess_board.get_fen();
+            let zobrist = chess_board.zobrist();
+            if chess_board.in_check() {
+                assert!(!chess_board.apply_null_move());
+            } else {
+                assert!(chess_board.apply_null_move());
+                assert_eq!(chess_board.ep_square(), NO_SQ);
+                assert_ne!(chess_board.zobrist(), zobrist);
+                chess_board.undo_null_move();
             }
+            assert_eq!(chess_board.get_fen(), fen);
+            assert_eq!(chess_board.zobrist(), zobrist);
 
             i += 1;
         }
@@ -44,3 +52,50 @@ fn apply_null_moves() {
     }
 }
 
+#[test]
+fn null_move_state() {
+    // After a null move, the pinned knight on d7 and the check squares belong to black.
+    let mut board = Board::new_from_fen("3k4/3n4/8/8/8/8/3Q4/4K3 w - - 0 1").unwrap();
+    assert!(board.apply_null_move());
+    assert!(!board.in_check());
+    assert_eq!(board.pieces_pinned(Player::Black).count_bits(), 1);
+    assert!(board.generate_moves().iter().all(|m| m.get_src() != SQ::D7));
+
+    // The side to move after a null move can never be in check, but may give check.
+    let mut board = Board::new_from_fen("4k3/8/8/8/8/8/3P4/R3K3 b Q - 0 1").unwrap();
+    assert!(board.apply_null_move());
+    let check = *board.generate_moves().iter().find(|m| m.stringify() == "a1a8").unwrap();
+    assert!(board.gives_check(check));
+    board.undo_null_move();
+    assert_eq!(board.get_fen(), "4k3/8/8/8/8/8/3P4/R3K3 b Q - 0 1");
+
+    // Positions before a null move are not repetitions of those after it.
+    let mut board = Board::default();
+    assert!(board.apply_null_move());
+    assert!(board.apply_null_move());
+    assert_eq!(board.zobrist(), Board::default().zobrist());
+    assert!(!board.is_repetition(2));
+}
+
+#[test]
+fn keys_match_fen() {
+    fn check_keys(board: &mut Board, depth: u16) {
+        let fen_board = Board::new_from_fen(&board.get_fen()).unwrap();
+        assert_eq!(board.zobrist(), fen_board.zobrist(), "{}", board.get_fen());
+        assert_eq!(board.pawn_key(), fen_board.pawn_key(), "{}", board.get_fen());
+        assert_eq!(board.material_key(), fen_board.material_key(), "{}", board.get_fen());
+        if depth == 0 {
+            return;
+        }
+        for mov in board.generate_moves().iter() {
+            board.apply_move(*mov);
+            check_keys(board, depth - 1);
+            board.undo_move();
+        }
+    }
+
+    // Promotions with and without captures, and castling rights lost one at a time
+    // or all at once.
+    let mut board = Board::new_from_fen("r3k2r/1P6/8/3pP3/8/8/6p1/R3K2R w KQkq d6 0 1").unwrap();
+    check_keys(&mut board, 3);
+}
@@ -0,0 +1,22 @@
+extern crate pleco;
+
+use std::env;
+
+use pleco::board::perft::{parse_perft_suite, check_perft_suite};
+
+/// The deepest depth checked, unless overridden by the `PLECO_PERFT_DEPTH` environment
+/// variable. The deeper counts of the suite take minutes to check.
+const DEFAULT_MAX_DEPTH: u16 = 4;
+
+#[test]
+fn perft_suite() {
+    let max_depth: u16 = env::var("PLECO_PERFT_DEPTH").ok()
+        .and_then(|depth| depth.parse().ok())
+        .unwrap_or(DEFAULT_MAX_DEPTH);
+
+    let suite = parse_perft_suite(include_str!("perft_suite.epd")).unwrap();
+    match check_perft_suite(&suite, max_depth) {
+        Ok(checked) => assert_eq!(checked, suite.len()),
+        Err(mismatch) => panic!("{:?}", mismatch),
+    }
+}
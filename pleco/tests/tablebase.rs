@@ -0,0 +1,84 @@
+extern crate pleco;
+
+use std::env;
+
+use pleco::Board;
+use pleco::tablebase::{Tablebases, Wdl};
+
+/// Returns the tables in `tests/syzygy`, or in the directories of the `PLECO_SYZYGY_PATH`
+/// environment variable.
+///
+/// The tables in `tests/syzygy` are written by `tablebase::writer`, not downloaded. Every
+/// expected value here is a known fact about the position rather than read from them, so the
+/// tests also pass against the official tables.
+fn tablebases() -> Tablebases {
+    let path = env::var("PLECO_SYZYGY_PATH")
+        .unwrap_or_else(|_| concat!(env!("CARGO_MANIFEST_DIR"), "/tests/syzygy").to_string());
+    let tablebases = Tablebases::new(&path);
+    assert!(tablebases.max_pieces() >= 4, "no 4-men tablebases found in {}", path);
+    tablebases
+}
+
+fn board(fen: &str) -> Board {
+    Board::new_from_fen(fen).unwrap()
+}
+
+#[test]
+fn probe_wdl_3_men() {
+    let tablebases = tablebases();
+    assert_eq!(tablebases.probe_wdl(&board("4k3/8/8/8/8/8/8/3QK3 w - - 0 1")), Some(Wdl::Win));
+    assert_eq!(tablebases.probe_wdl(&board("4k3/8/8/8/8/8/8/3QK3 b - - 0 1")), Some(Wdl::Loss));
+    assert_eq!(tablebases.probe_wdl(&board("4k3/8/8/8/8/8/8/3RK3 b - - 0 1")), Some(Wdl::Loss));
+    assert_eq!(tablebases.probe_wdl(&board("4k3/8/8/8/8/8/8/3BK3 w - - 0 1")), Some(Wdl::Draw));
+    assert_eq!(tablebases.probe_wdl(&board("4k3/8/8/8/8/8/8/3NK3 b - - 0 1")), Some(Wdl::Draw));
+    assert_eq!(tablebases.probe_wdl(&board("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1")), Some(Wdl::Win));
+    assert_eq!(tablebases.probe_wdl(&board("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1")), Some(Wdl::Loss));
+    // Colors flipped.
+    assert_eq!(tablebases.probe_wdl(&board("3qk3/8/8/8/8/8/8/4K3 b - - 0 1")), Some(Wdl::Win));
+    // The queen hangs.
+    assert_eq!(tablebases.probe_wdl(&board("4k3/8/8/8/8/8/3q4/4K3 w - - 0 1")), Some(Wdl::Draw));
+}
+
+#[test]
+fn probe_dtz_3_men() {
+    let tablebases = tablebases();
+    // Mate in one, and mated.
+    assert_eq!(tablebases.probe_dtz(&board("k7/7Q/1K6/8/8/8/8/8 w - - 0 1")), Some(1));
+    assert_eq!(tablebases.probe_dtz(&board("4k3/8/8/8/8/8/8/3BK3 w - - 0 1")), Some(0));
+    // Promoting zeroes the counter.
+    assert_eq!(tablebases.probe_dtz(&board("8/4P3/8/8/8/8/k7/4K3 w - - 0 1")), Some(1));
+}
+
+#[test]
+fn probe_4_men() {
+    let tablebases = tablebases();
+    // Mated, mate in one, and the same with colors flipped.
+    assert_eq!(tablebases.probe_wdl(&board("5N1k/5K2/6N1/8/8/8/8/8 b - - 0 1")), Some(Wdl::Loss));
+    assert_eq!(tablebases.probe_dtz(&board("5N1k/5K2/6N1/8/8/8/8/8 b - - 0 1")), Some(-1));
+    assert_eq!(tablebases.probe_wdl(&board("5N1k/5K2/8/4N3/8/8/8/8 w - - 0 1")), Some(Wdl::Win));
+    assert_eq!(tablebases.probe_dtz(&board("5N1k/5K2/8/4N3/8/8/8/8 w - - 0 1")), Some(1));
+    assert_eq!(tablebases.probe_wdl(&board("8/8/8/8/8/6n1/5k2/5n1K w - - 0 1")), Some(Wdl::Loss));
+    // Two knights can't force mate.
+    assert_eq!(tablebases.probe_wdl(&board("8/8/4k3/8/8/8/3NNK2/8 w - - 0 1")), Some(Wdl::Draw));
+    assert_eq!(tablebases.probe_dtz(&board("8/8/4k3/8/8/8/3NNK2/8 b - - 0 1")), Some(0));
+}
+
+#[test]
+fn probe_consistent() {
+    let tablebases = tablebases();
+    let fens = [
+        "8/8/8/8/4k3/8/3QK3/8 w - - 0 1",
+        "8/8/8/4k3/8/8/2R1K3/8 b - - 0 1",
+        "8/8/8/4k3/8/8/2P1K3/8 w - - 0 1",
+        "8/8/8/2p1k3/8/8/4K3/8 b - - 0 1",
+        "8/8/4k3/8/8/8/3NNK2/8 b - - 0 1",
+        "7k/5K2/8/4N3/8/8/8/5N2 w - - 0 1",
+        "8/8/8/8/4k3/8/4K3/2B5 w - - 0 1",
+    ];
+    for fen in fens.iter() {
+        let board = board(fen);
+        let wdl = tablebases.probe_wdl(&board).unwrap();
+        let dtz = tablebases.probe_dtz(&board).unwrap();
+        assert_eq!(wdl.signum(), dtz.signum(), "{}", fen);
+    }
+}
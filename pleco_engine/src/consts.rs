@@ -4,6 +4,8 @@ use lazy_static;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::sync::{ONCE_INIT,Once};
+#[cfg(test)]
+use std::sync::Mutex;
 use std::sync::atomic::compiler_fence;
 
 use pleco::tools::tt::TranspositionTable;
@@ -12,12 +14,16 @@ use pleco::helper::prelude;
 
 
 use threadpool;
+use tables::material::KPK;
 
 pub const MAX_PLY: u16 = 126;
 pub const THREAD_STACK_SIZE: usize = MAX_PLY as usize + 7;
 pub const MAX_THREADS: usize = 256;
 
 pub const DEFAULT_TT_SIZE: usize = 256;
+pub const DEFAULT_BOOK_FILE: &str = "book.bin";
+pub const DEFAULT_SYZYGY_PATH: &str = "<empty>";
+pub const DEFAULT_CONTEMPT: i32 = 0;
 
 pub const PAWN_TABLE_SIZE: usize = 16384;
 pub const MATERIAL_TABLE_SIZE: usize = 8192;
@@ -32,11 +38,19 @@ lazy_static! {
     pub static ref TT_TABLE: TranspositionTable = TranspositionTable::new(DEFAULT_TT_SIZE);
 }
 
+#[cfg(test)]
+lazy_static! {
+    /// Held by the tests using the global thread pool or tablebases, so they don't run at the
+    /// same time.
+    pub static ref TEST_LOCK: Mutex<()> = Mutex::new(());
+}
+
 pub fn init_globals() {
     INITALIZED.call_once(|| {
         prelude::init_statics();
         compiler_fence(Ordering::SeqCst);
         lazy_static::initialize(&TT_TABLE);
+        lazy_static::initialize(&KPK);
         threadpool::init_threadpool();
 
     });
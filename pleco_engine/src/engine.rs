@@ -5,6 +5,8 @@ use std::sync::atomic::Ordering;
 
 use pleco::Board;
 use pleco::BitMove;
+use pleco::book::PolyglotBook;
+use pleco::tablebase;
 
 use time::uci_timer::{PreLimits};
 use uci::options::{OptionsMap,OptionWork};
@@ -12,6 +14,7 @@ use uci::parse;
 use TT_TABLE;
 use consts::*;
 use threadpool::threadpool;
+use search::params::SearchParams;
 
 use num_cpus;
 
@@ -31,7 +34,11 @@ enum SearchType {
 pub struct PlecoSearcher {
     options: OptionsMap,
     search_mode: SearchType,
-    board: Board
+    board: Board,
+    chess960: bool,
+    own_book: bool,
+    book_file: String,
+    book: Option<PolyglotBook>
 }
 
 impl PlecoSearcher {
@@ -42,7 +49,11 @@ impl PlecoSearcher {
         PlecoSearcher {
             options: OptionsMap::new(),
             search_mode: SearchType::None,
-            board: Board::default()
+            board: Board::default(),
+            chess960: false,
+            own_book: false,
+            book_file: DEFAULT_BOOK_FILE.to_string(),
+            book: None
         }
     }
 
@@ -62,7 +73,7 @@ impl PlecoSearcher {
                 "ucinewgame" => self.clear_search(),
                 "isready" => println!("readyok"),
                 "position" => {
-                    if let Some(b) = parse::position_parse_board(&args[1..]) {
+                    if let Some(b) = parse::position_parse_board(&args[1..], self.chess960) {
                         self.board = b;
                     } else {
                         println!("unable to parse board");
@@ -90,13 +101,40 @@ impl PlecoSearcher {
 
     pub fn clear_search(&mut self) {
         self.clear_tt();
+        threadpool().clear_all();
     }
 
     fn uci_go(&mut self, args: &[&str]) {
         let limit = parse::parse_time(&args);
+        if !limit.infinite && !limit.ponder {
+            if let Some(mov) = self.book_move() {
+                println!("bestmove {}", self.board.move_to_uci(mov));
+                return;
+            }
+        }
         threadpool().uci_search(&self.board, &limit.create())
     }
 
+    /// Returns a move from the opening book for the current position, if the book is in use
+    /// and has one.
+    fn book_move(&self) -> Option<BitMove> {
+        if !self.own_book {
+            return None;
+        }
+        self.book.as_ref().and_then(|book| book.weighted_move(&self.board))
+    }
+
+    /// Reads the opening book from the book file, if the book is in use and not yet read.
+    fn load_book(&mut self) {
+        if !self.own_book || self.book.is_some() {
+            return;
+        }
+        match PolyglotBook::open(&self.book_file) {
+            Ok(book) => self.book = Some(book),
+            Err(e) => println!("info string Unable to read book file {}: {}", self.book_file, e)
+        }
+    }
+
     fn apply_option(&mut self, full_command: &str) {
         let mut args  = full_command.split_whitespace();
         args.next().unwrap();  // setoption
@@ -153,7 +191,22 @@ impl PlecoSearcher {
                 match work {
                     OptionWork::ClearTT => {self.clear_tt()},
                     OptionWork::ResizeTT(mb) => {self.resize_tt(mb)},
-                    OptionWork::Threads(num) => {threadpool().set_thread_count(num)}
+                    OptionWork::Threads(num) => {threadpool().set_thread_count(num)},
+                    OptionWork::Chess960(chess960) => {self.chess960 = chess960},
+                    OptionWork::OwnBook(own_book) => {
+                        self.own_book = own_book;
+                        self.load_book();
+                    },
+                    OptionWork::BookFile(file) => {
+                        self.book_file = file;
+                        self.book = None;
+                        self.load_book();
+                    },
+                    OptionWork::SyzygyPath(path) => {
+                        let found = tablebase::init(&path);
+                        println!("info string Found {} tablebases", found);
+                    },
+                    OptionWork::Contempt(contempt) => {self.set_contempt(contempt)}
                 }
             }
         }
@@ -223,6 +276,16 @@ impl PlecoSearcher {
         threadpool().stdout(stdout);
     }
 
+    /// Sets the selective search techniques used by the following searches.
+    pub fn set_search_params(&mut self, params: SearchParams) {
+        threadpool().search_params = params;
+    }
+
+    /// Sets how much worse than even a draw is for the side the following searches are for.
+    pub fn set_contempt(&mut self, contempt: i32) {
+        threadpool().contempt = contempt;
+    }
+
 
 }
 
@@ -230,5 +293,69 @@ impl PlecoSearcher {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use std::sync::MutexGuard;
+
+    /// Returns a searcher with the default settings, along with a guard that must be held
+    /// while searching, as every searcher shares the global thread pool and tablebases.
+    fn searcher() -> (MutexGuard<'static, ()>, PlecoSearcher) {
+        let guard = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut searcher = PlecoSearcher::init(false);
+        searcher.set_search_params(SearchParams::default());
+        searcher.set_contempt(DEFAULT_CONTEMPT);
+        tablebase::init(DEFAULT_SYZYGY_PATH);
+        (guard, searcher)
+    }
+
+    #[test]
+    fn quiescence_sees_recapture() {
+        let (_guard, mut searcher) = searcher();
+        // The pawn on d5 is defended, so taking it loses the queen.
+        let board = Board::new_from_fen("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let mut limit = PreLimits::blank();
+        limit.depth = Some(2);
+        searcher.search(&board, &limit);
+        let mov = searcher.await_move();
+        assert!(!mov.is_null());
+        assert_ne!(mov.stringify(), "d1d5");
+    }
 
+    #[test]
+    fn selective_search_finds_mate() {
+        let (_guard, mut searcher) = searcher();
+        // Back rank mate, with the other moves losing nothing either.
+        let board = Board::new_from_fen("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1").unwrap();
+        let mut limit = PreLimits::blank();
+        limit.depth = Some(6);
+        for params in [SearchParams::default(), SearchParams::none()].iter() {
+            searcher.set_search_params(*params);
+            searcher.search(&board, &limit);
+            assert_eq!(searcher.await_move().stringify(), "d1d8");
+        }
+    }
+
+    #[test]
+    fn check_extension_finds_mate() {
+        let (_guard, mut searcher) = searcher();
+        // Qd5+ or Qb3+ drive the king to a corner or f8, where a quiet queen move mates.
+        let board = Board::new_from_fen("6k1/3p4/6K1/8/8/8/8/3Q4 w - - 0 1").unwrap();
+        let mut limit = PreLimits::blank();
+        limit.depth = Some(3);
+        searcher.search(&board, &limit);
+        let mov = searcher.await_move().stringify();
+        assert!(mov == "d1d5" || mov == "d1b3", "{}", mov);
+    }
+
+    #[test]
+    fn repetition_saves_lost_position() {
+        let (_guard, mut searcher) = searcher();
+        // A knight against a queen, but Nf3 repeats the position a third time.
+        let b_str = "position fen kq6/8/8/8/8/8/8/6NK w - - 0 1 moves g1f3 a8b7 f3g1 b7a8 g1f3 a8b7 f3g1 b7a8";
+        let args: Vec<&str> = b_str.split_whitespace().collect();
+        let board = parse::position_parse_board(&args[1..], false).unwrap();
+        let mut limit = PreLimits::blank();
+        limit.depth = Some(4);
+        searcher.search(&board, &limit);
+        assert_eq!(searcher.await_move().stringify(), "g1f3");
+    }
 }
\ No newline at end of file
@@ -56,5 +56,6 @@ pub mod root_moves;
 pub mod tables;
 pub mod engine;
 pub mod search;
+pub mod movepick;
 
 pub use consts::*;
\ No newline at end of file
@@ -0,0 +1,396 @@
+//! Staged move ordering for the search.
+//!
+//! A `MovePicker` hands out the pseudo-legal moves of a position one at a time, best first,
+//! only generating each kind of move once the moves before it are used up. A node that is cut
+//! off by the transposition table move or a capture never generates its quiet moves.
+//!
+//! The main search tries, in order:
+//!
+//! 1. The transposition table move.
+//! 2. Captures and promotions that do not lose material, most valuable victim first.
+//! 3. The two killer moves of the ply, then the countermove of the previous move.
+//! 4. The other quiet moves, by their butterfly history.
+//! 5. The captures that lose material.
+//!
+//! When in check, every evasion is tried after the transposition table move, captures first.
+//! The quiescence search only tries captures and promotions, and ProbCut only the captures
+//! winning at least a given amount of material.
+//!
+//! The picker does not check the legality of moves, `Board::legal_move()` must be used on
+//! each of them.
+
+use pleco::{Board, BitMove, MoveList, PieceType};
+use pleco::core::GenTypes;
+use pleco::core::move_list::ScoringMoveList;
+use pleco::helper::prelude::piece_value;
+
+use tables::butterfly::ButterflyHistory;
+
+// Keeps captures ahead of quiet moves when ordering evasions.
+const EVASION_CAPTURE_BONUS: i32 = 1 << 14;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Stage {
+    MainTT,
+    CapturesInit,
+    GoodCaptures,
+    Killer0,
+    Killer1,
+    CounterMove,
+    QuietsInit,
+    Quiets,
+    BadCaptures,
+    EvasionTT,
+    EvasionsInit,
+    Evasions,
+    QSearchTT,
+    QCapturesInit,
+    QCaptures,
+    ProbCutTT,
+    ProbCutInit,
+    ProbCut,
+    Done,
+}
+
+pub struct MovePicker {
+    stage: Stage,
+    tt_move: BitMove,
+    killers: [BitMove; 2],
+    counter_move: BitMove,
+    moves: ScoringMoveList,
+    cur: usize,
+    bad_captures: MoveList,
+    bad_cur: usize,
+    threshold: i32,
+    skip_quiets: bool,
+}
+
+impl MovePicker {
+    /// Creates a picker for the main search. The transposition table move, killers and
+    /// countermove may be null, or moves that are not valid for the position.
+    pub fn main_search(board: &Board, tt_move: BitMove, killers: [BitMove; 2], counter_move: BitMove) -> Self {
+        let in_check = board.in_check();
+        let tt_move = if board.pseudo_legal_move(tt_move) { tt_move } else { BitMove::null() };
+        let mut stage = if in_check { Stage::EvasionTT } else { Stage::MainTT };
+        if tt_move.is_null() {
+            stage = next_stage(stage);
+        }
+        MovePicker::with_stage(stage, tt_move, killers, counter_move)
+    }
+
+    /// Creates a picker for the quiescence search. Unless in check, the transposition table
+    /// move is only tried if it is a capture or promotion.
+    pub fn qsearch(board: &Board, tt_move: BitMove) -> Self {
+        let in_check = board.in_check();
+        let tt_move = if board.pseudo_legal_move(tt_move)
+                && (in_check || tt_move.is_capture() || tt_move.is_promo()) {
+            tt_move
+        } else {
+            BitMove::null()
+        };
+        let mut stage = if in_check { Stage::EvasionTT } else { Stage::QSearchTT };
+        if tt_move.is_null() {
+            stage = next_stage(stage);
+        }
+        MovePicker::with_stage(stage, tt_move, [BitMove::null(); 2], BitMove::null())
+    }
+
+    /// Creates a picker for ProbCut, returning the captures whose static exchange evaluation
+    /// is at least `threshold`. Must not be used when in check.
+    pub fn probcut(board: &Board, tt_move: BitMove, threshold: i32) -> Self {
+        assert!(!board.in_check());
+        let tt_move = if board.pseudo_legal_move(tt_move) && tt_move.is_capture()
+                && board.see_ge(tt_move, threshold) {
+            tt_move
+        } else {
+            BitMove::null()
+        };
+        let stage = if tt_move.is_null() { Stage::ProbCutInit } else { Stage::ProbCutTT };
+        let mut picker = MovePicker::with_stage(stage, tt_move, [BitMove::null(); 2], BitMove::null());
+        picker.threshold = threshold;
+        picker
+    }
+
+    fn with_stage(stage: Stage, tt_move: BitMove, killers: [BitMove; 2], counter_move: BitMove) -> Self {
+        MovePicker {
+            stage,
+            tt_move,
+            killers,
+            counter_move,
+            moves: ScoringMoveList::default(),
+            cur: 0,
+            bad_captures: MoveList::default(),
+            bad_cur: 0,
+            threshold: 0,
+            skip_quiets: false,
+        }
+    }
+
+    /// Stops returning quiet moves, other than those already returned. Captures, including
+    /// the ones losing material, are still returned.
+    pub fn skip_quiets(&mut self) {
+        self.skip_quiets = true;
+    }
+
+    /// Returns the next move to search, or `None` once every move was returned.
+    pub fn next(&mut self, board: &Board, history: &ButterflyHistory) -> Option<BitMove> {
+        loop {
+            match self.stage {
+                Stage::MainTT | Stage::EvasionTT | Stage::QSearchTT | Stage::ProbCutTT => {
+                    self.stage = next_stage(self.stage);
+                    return Some(self.tt_move);
+                },
+                Stage::CapturesInit | Stage::QCapturesInit | Stage::ProbCutInit => {
+                    self.generate(board, GenTypes::Captures);
+                    self.score_captures(board);
+                    self.stage = next_stage(self.stage);
+                },
+                Stage::GoodCaptures => {
+                    while let Some(mov) = self.pick_best() {
+                        if mov == self.tt_move {
+                            continue;
+                        }
+                        if board.see_ge(mov, 0) {
+                            return Some(mov);
+                        }
+                        self.bad_captures.push(mov);
+                    }
+                    self.stage = Stage::Killer0;
+                },
+                Stage::Killer0 | Stage::Killer1 | Stage::CounterMove => {
+                    let mov = match self.stage {
+                        Stage::Killer0 => self.killers[0],
+                        Stage::Killer1 => self.killers[1],
+                        _ => self.counter_move,
+                    };
+                    let repeated = self.stage == Stage::CounterMove
+                        && (mov == self.killers[0] || mov == self.killers[1]);
+                    self.stage = next_stage(self.stage);
+                    if !self.skip_quiets && !repeated && mov != self.tt_move && self.is_quiet_candidate(board, mov) {
+                        return Some(mov);
+                    }
+                },
+                Stage::QuietsInit => {
+                    if self.skip_quiets {
+                        self.moves = ScoringMoveList::default();
+                        self.cur = 0;
+                        self.stage = Stage::Quiets;
+                        continue;
+                    }
+                    self.generate(board, GenTypes::Quiets);
+                    for scoring in self.moves.iter_mut() {
+                        scoring.score = history.get(board.turn(), scoring.bit_move) as i16;
+                    }
+                    self.moves.sort_by_key(|m| -m.score);
+                    self.stage = Stage::Quiets;
+                },
+                Stage::Quiets => {
+                    while !self.skip_quiets && self.cur < self.moves.len() {
+                        let mov = self.moves[self.cur].bit_move;
+                        self.cur += 1;
+                        // Promotions were already returned along with the captures.
+                        if mov != self.tt_move && !mov.is_promo() && !self.is_refutation(mov) {
+                            return Some(mov);
+                        }
+                    }
+                    self.stage = Stage::BadCaptures;
+                },
+                Stage::BadCaptures => {
+                    if self.bad_cur < self.bad_captures.len() {
+                        self.bad_cur += 1;
+                        return Some(self.bad_captures[self.bad_cur - 1]);
+                    }
+                    self.stage = Stage::Done;
+                },
+                Stage::EvasionsInit => {
+                    self.generate(board, GenTypes::Evasions);
+                    for scoring in self.moves.iter_mut() {
+                        let mov = scoring.bit_move;
+                        scoring.score = if mov.is_capture() {
+                            (capture_score(board, mov) + EVASION_CAPTURE_BONUS) as i16
+                        } else {
+                            history.get(board.turn(), mov) as i16
+                        };
+                    }
+                    self.stage = Stage::Evasions;
+                },
+                Stage::Evasions | Stage::QCaptures => {
+                    while let Some(mov) = self.pick_best() {
+                        if mov != self.tt_move {
+                            return Some(mov);
+                        }
+                    }
+                    self.stage = Stage::Done;
+                },
+                Stage::ProbCut => {
+                    while let Some(mov) = self.pick_best() {
+                        if mov != self.tt_move && board.see_ge(mov, self.threshold) {
+                            return Some(mov);
+                        }
+                    }
+                    self.stage = Stage::Done;
+                },
+                Stage::Done => {
+                    return None;
+                }
+            }
+        }
+    }
+
+    fn generate(&mut self, board: &Board, gen_type: GenTypes) {
+        self.moves = ScoringMoveList::from(board.generate_pseudolegal_moves_of_type(gen_type));
+        self.cur = 0;
+    }
+
+    fn score_captures(&mut self, board: &Board) {
+        for scoring in self.moves.iter_mut() {
+            scoring.score = capture_score(board, scoring.bit_move) as i16;
+        }
+    }
+
+    /// Returns the best scoring move left, moving it to the front of the moves left.
+    fn pick_best(&mut self) -> Option<BitMove> {
+        if self.cur >= self.moves.len() {
+            return None;
+        }
+        let mut best = self.cur;
+        for i in (self.cur + 1)..self.moves.len() {
+            if self.moves[i].score > self.moves[best].score {
+                best = i;
+            }
+        }
+        self.moves.swap(self.cur, best);
+        self.cur += 1;
+        Some(self.moves[self.cur - 1].bit_move)
+    }
+
+    fn is_refutation(&self, mov: BitMove) -> bool {
+        mov == self.killers[0] || mov == self.killers[1] || mov == self.counter_move
+    }
+
+    fn is_quiet_candidate(&self, board: &Board, mov: BitMove) -> bool {
+        !mov.is_null() && !mov.is_capture() && !mov.is_promo() && board.pseudo_legal_move(mov)
+    }
+}
+
+fn next_stage(stage: Stage) -> Stage {
+    match stage {
+        Stage::MainTT => Stage::CapturesInit,
+        Stage::CapturesInit => Stage::GoodCaptures,
+        Stage::GoodCaptures => Stage::Killer0,
+        Stage::Killer0 => Stage::Killer1,
+        Stage::Killer1 => Stage::CounterMove,
+        Stage::CounterMove => Stage::QuietsInit,
+        Stage::QuietsInit => Stage::Quiets,
+        Stage::Quiets => Stage::BadCaptures,
+        Stage::BadCaptures => Stage::Done,
+        Stage::EvasionTT => Stage::EvasionsInit,
+        Stage::EvasionsInit => Stage::Evasions,
+        Stage::Evasions => Stage::Done,
+        Stage::QSearchTT => Stage::QCapturesInit,
+        Stage::QCapturesInit => Stage::QCaptures,
+        Stage::QCaptures => Stage::Done,
+        Stage::ProbCutTT => Stage::ProbCutInit,
+        Stage::ProbCutInit => Stage::ProbCut,
+        Stage::ProbCut => Stage::Done,
+        Stage::Done => Stage::Done,
+    }
+}
+
+/// Scores a capture by the value of the captured piece, then by the least valuable attacker.
+/// Promotions also count the value gained by the promotion.
+fn capture_score(board: &Board, mov: BitMove) -> i32 {
+    let mut score = match board.captured_piece(mov) {
+        Some(piece) => piece_value(piece, false),
+        None => 0
+    };
+    if mov.is_promo() {
+        score += piece_value(mov.promo_piece(), false) - piece_value(PieceType::P, false);
+    }
+    score - board.moved_piece(mov) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_moves(board: &Board, mut picker: MovePicker, history: &ButterflyHistory) -> Vec<BitMove> {
+        let mut moves = Vec::new();
+        while let Some(mov) = picker.next(board, history) {
+            moves.push(mov);
+        }
+        moves
+    }
+
+    #[test]
+    fn picks_every_move_once() {
+        let history = ButterflyHistory::new();
+        for fen in ::pleco::board::fen::ALL_FENS.iter() {
+            let board = Board::new_from_fen(fen).unwrap();
+            let expected = board.generate_pseudolegal_moves();
+            let legal = board.generate_moves();
+            let tt_move = legal.last().cloned().unwrap_or_else(BitMove::null);
+            let killers = [legal.first().cloned().unwrap_or_else(BitMove::null), BitMove::null()];
+            let picker = MovePicker::main_search(&board, tt_move, killers, tt_move);
+
+            let mut picked = all_moves(&board, picker, &history);
+            if let Some(tt_move) = legal.last() {
+                assert_eq!(picked[0], *tt_move, "{}", fen);
+            }
+            assert_eq!(picked.len(), expected.len(), "{}", fen);
+            picked.sort_by_key(|m| m.get_raw());
+            picked.dedup();
+            assert_eq!(picked.len(), expected.len(), "{}", fen);
+            assert!(expected.iter().all(|m| picked.contains(m)), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn stage_order() {
+        // White can take a pawn with the queen, or a knight with a pawn. Both are defended by
+        // the pawn on c6, so only taking the knight wins material.
+        let board = Board::new_from_fen("4k3/8/2p5/1p1n4/4P3/8/8/1Q2K3 w - - 0 1").unwrap();
+        let capture = *board.generate_moves().iter().find(|m| m.stringify() == "e4d5").unwrap();
+        assert_eq!(board.see(capture), piece_value(PieceType::N, false) - piece_value(PieceType::P, false));
+        let mut history = ButterflyHistory::new();
+        let quiet = *board.generate_moves().iter().find(|m| m.stringify() == "e1f2").unwrap();
+        let killer = *board.generate_moves().iter().find(|m| m.stringify() == "b1c2").unwrap();
+        history.update(board.turn(), quiet, 300);
+
+        let picker = MovePicker::main_search(&board, BitMove::null(), [killer, BitMove::null()], BitMove::null());
+        let picked: Vec<String> = all_moves(&board, picker, &history).iter().map(|m| m.stringify()).collect();
+        assert_eq!(picked[0], "e4d5");
+        assert_eq!(picked[1], "b1c2");
+        assert_eq!(picked[2], "e1f2");
+        assert_eq!(picked.last().unwrap(), "b1b5");
+    }
+
+    #[test]
+    fn qsearch_captures_only() {
+        let board = Board::new_from_fen("4k3/8/2p5/1p1n4/4P3/8/8/1Q2K3 w - - 0 1").unwrap();
+        let history = ButterflyHistory::new();
+        let picker = MovePicker::qsearch(&board, BitMove::null());
+        let picked: Vec<String> = all_moves(&board, picker, &history).iter().map(|m| m.stringify()).collect();
+        assert_eq!(picked, vec!["e4d5", "b1b5"]);
+    }
+
+    #[test]
+    fn probcut_winning_captures() {
+        let board = Board::new_from_fen("4k3/8/2p5/1p1n4/4P3/8/8/1Q2K3 w - - 0 1").unwrap();
+        let history = ButterflyHistory::new();
+        let picker = MovePicker::probcut(&board, BitMove::null(), 1);
+        let picked: Vec<String> = all_moves(&board, picker, &history).iter().map(|m| m.stringify()).collect();
+        assert_eq!(picked, vec!["e4d5"]);
+    }
+
+    #[test]
+    fn skip_quiets() {
+        let board = Board::new_from_fen("4k3/8/2p5/1p1n4/4P3/8/8/1Q2K3 w - - 0 1").unwrap();
+        let history = ButterflyHistory::new();
+        let mut picker = MovePicker::main_search(&board, BitMove::null(), [BitMove::null(); 2], BitMove::null());
+        assert_eq!(picker.next(&board, &history).unwrap().stringify(), "e4d5");
+        picker.skip_quiets();
+        assert_eq!(picker.next(&board, &history).unwrap().stringify(), "b1b5");
+        assert!(picker.next(&board, &history).is_none());
+    }
+}
@@ -12,6 +12,7 @@ use rand;
 use rand::Rng;
 
 use pleco::{MoveList, Board, PieceType, BitMove};
+use pleco::tablebase::{self, Wdl};
 use super::{RootMove, MAX_MOVES};
 
 
@@ -137,6 +138,93 @@ impl RootMoveList {
             rm.score = score;
         }
     }
+
+    /// Uses the Syzygy tablebases to remove the moves that do not keep the best result of
+    /// the position. Returns if the position was found in the tablebases.
+    ///
+    /// The DTZ tables are used if available, keeping winning moves that stay within the
+    /// fifty-move rule. Otherwise, the WDL tables are used.
+    pub fn tablebase_filter(&mut self, board: &Board) -> bool {
+        if self.len() == 0 || board.count_all_pieces() as usize > tablebase::max_pieces() {
+            return false;
+        }
+        self.tablebase_filter_dtz(board) || self.tablebase_filter_wdl(board)
+    }
+
+    fn tablebase_filter_dtz(&mut self, board: &Board) -> bool {
+        let root_dtz = match tablebase::probe_dtz(board) {
+            Some(dtz) => dtz,
+            None => return false
+        };
+        let mut board = board.shallow_clone();
+        let mut scores: Vec<i32> = Vec::with_capacity(self.len());
+        for mov in self.iter().map(|m| m.bit_move) {
+            board.apply_move(mov);
+            let dtz = if root_dtz > 0 && board.in_check() && board.generate_moves().is_empty() {
+                Some(1)
+            } else if board.rule_50() == 0 {
+                tablebase::probe_wdl(&board).map(|wdl| tablebase::dtz_before_zeroing(-wdl))
+            } else {
+                tablebase::probe_dtz(&board).map(|dtz| -dtz + (-dtz).signum())
+            };
+            board.undo_move();
+            match dtz {
+                Some(dtz) => scores.push(dtz),
+                None => return false
+            }
+        }
+
+        let cnt50 = board.rule_50() as i32;
+        let keep: Vec<bool> = if root_dtz > 0 {
+            // Play the quickest win, unless the fifty-move rule leaves room for any winning
+            // move and the position has not repeated.
+            let best = scores.iter().cloned().filter(|s| *s > 0).min().unwrap_or(0xFFFF);
+            let max = if !board.is_repetition(2) && best + cnt50 <= 99 { 99 - cnt50 } else { best };
+            scores.iter().map(|s| *s > 0 && *s <= max).collect()
+        } else if root_dtz < 0 {
+            // Every move loses. Keep all of them, unless a fifty-move draw is in sight.
+            let best = scores.iter().cloned().min().unwrap_or(0);
+            if -best * 2 + cnt50 < 100 {
+                return true;
+            }
+            scores.iter().map(|s| *s == best).collect()
+        } else {
+            scores.iter().map(|s| *s == 0).collect()
+        };
+        self.retain_moves(&keep);
+        true
+    }
+
+    fn tablebase_filter_wdl(&mut self, board: &Board) -> bool {
+        let mut board = board.shallow_clone();
+        let mut scores: Vec<Wdl> = Vec::with_capacity(self.len());
+        for mov in self.iter().map(|m| m.bit_move) {
+            board.apply_move(mov);
+            let wdl = tablebase::probe_wdl(&board);
+            board.undo_move();
+            match wdl {
+                Some(wdl) => scores.push(-wdl),
+                None => return false
+            }
+        }
+        let best = scores.iter().cloned().max().unwrap_or(Wdl::Loss);
+        let keep: Vec<bool> = scores.iter().map(|s| *s == best).collect();
+        self.retain_moves(&keep);
+        true
+    }
+
+    /// Keeps only the moves at the indexes where `keep` is true, as long as any are kept.
+    fn retain_moves(&mut self, keep: &[bool]) {
+        if !keep.iter().any(|k| *k) {
+            return;
+        }
+        let moves: Vec<BitMove> = self.iter()
+            .zip(keep.iter())
+            .filter(|&(_, k)| *k)
+            .map(|(m, _)| m.bit_move)
+            .collect();
+        self.replace(&MoveList::from(moves));
+    }
 }
 
 impl Deref for RootMoveList {
@@ -224,4 +312,126 @@ impl<'a> ExactSizeIterator for MoveIter<'a> {}
 
 impl<'a> FusedIterator for MoveIter<'a> {}
 
-unsafe impl<'a> TrustedLen for MoveIter<'a> {}
\ No newline at end of file
+unsafe impl<'a> TrustedLen for MoveIter<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::sync::MutexGuard;
+
+    use consts::TEST_LOCK;
+
+    const SYZYGY_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../pleco/tests/syzygy");
+
+    /// Loads the global tablebases from a directory, returning a guard that must be held while
+    /// they are used.
+    fn tablebases(path: &str) -> MutexGuard<'static, ()> {
+        let guard = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        assert!(tablebase::init(path) > 0, "no tablebases found in {}", path);
+        guard
+    }
+
+    fn moves(board: &Board) -> RootMoveList {
+        let mut moves = RootMoveList::new();
+        moves.replace(&board.generate_moves());
+        moves
+    }
+
+    /// Returns the moves kept by `tablebase_filter`, sorted.
+    fn filtered(fen: &str) -> Vec<String> {
+        let board = Board::new_from_fen(fen).unwrap();
+        let mut moves = moves(&board);
+        assert!(moves.tablebase_filter(&board), "{}", fen);
+        let mut kept: Vec<String> = moves.iter().map(|m| m.bit_move.stringify()).collect();
+        kept.sort();
+        kept
+    }
+
+    /// Returns every legal move, sorted.
+    fn all_moves(fen: &str) -> Vec<String> {
+        let board = Board::new_from_fen(fen).unwrap();
+        let mut all: Vec<String> = board.generate_moves().iter().map(|m| m.stringify()).collect();
+        all.sort();
+        all
+    }
+
+    /// Returns the distance to zeroing of a position after a move.
+    fn dtz_after(fen: &str, mov: &str) -> i32 {
+        let mut board = Board::new_from_fen(fen).unwrap();
+        assert!(board.apply_uci_move(mov));
+        tablebase::probe_dtz(&board).unwrap()
+    }
+
+    #[test]
+    fn tablebase_filter_won() {
+        let _guard = tablebases(SYZYGY_PATH);
+        // Every winning move is kept, but not the stalemates.
+        let kept = filtered("k7/7Q/1K6/8/8/8/8/8 w - - 0 1");
+        let mut expected = all_moves("k7/7Q/1K6/8/8/8/8/8 w - - 0 1");
+        expected.retain(|m| m != "h7c7" && m != "h7h2");
+        assert_eq!(kept, expected);
+
+        // With two plies left before the fifty-move rule, only mating moves win.
+        let kept = filtered("k7/7Q/1K6/8/8/8/8/8 w - - 98 80");
+        assert_eq!(kept, vec!["h7a7", "h7b7", "h7g8", "h7h8"]);
+
+        // Once the fifty-move rule can't be beaten, only the quickest wins are kept.
+        let fen = "8/8/8/4k3/8/8/8/1Q2K3 w - - 95 80";
+        let kept = filtered(fen);
+        let quickest = all_moves(fen).iter().map(|m| dtz_after(fen, m)).filter(|d| *d < 0).max().unwrap();
+        assert!(!kept.is_empty() && kept.len() < filtered("8/8/8/4k3/8/8/8/1Q2K3 w - - 0 1").len());
+        assert!(kept.iter().all(|m| dtz_after(fen, m) == quickest));
+    }
+
+    #[test]
+    fn tablebase_filter_drawn() {
+        let _guard = tablebases(SYZYGY_PATH);
+        // Only taking the rook draws.
+        assert_eq!(filtered("8/8/8/8/8/3k4/2R5/4K3 b - - 0 1"), vec!["d3c2"]);
+        // Every move draws.
+        assert_eq!(filtered("8/8/4k3/8/8/8/3NNK2/8 w - - 0 1"), all_moves("8/8/4k3/8/8/8/3NNK2/8 w - - 0 1"));
+    }
+
+    #[test]
+    fn tablebase_filter_lost() {
+        let _guard = tablebases(SYZYGY_PATH);
+        // Far from the fifty-move rule, every move is kept.
+        let fen = "8/8/8/4k3/8/8/8/1Q2K3 b - - 0 1";
+        assert_eq!(filtered(fen), all_moves(fen));
+
+        // Closer to it, only the moves losing the slowest are kept.
+        let fen = "8/8/8/4k3/8/8/8/1Q2K3 b - - 80 1";
+        let kept = filtered(fen);
+        let longest = all_moves(fen).iter().map(|m| dtz_after(fen, m)).max().unwrap();
+        assert!(!kept.is_empty() && kept.len() < all_moves(fen).len());
+        assert!(kept.iter().all(|m| dtz_after(fen, m) == longest));
+    }
+
+    #[test]
+    fn tablebase_filter_wdl() {
+        // Without the DTZ tables, the WDL tables are used.
+        let dir = env::temp_dir().join(format!("pleco_engine_wdl_{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for entry in fs::read_dir(SYZYGY_PATH).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().map_or(false, |e| e == "rtbw") {
+                fs::copy(&path, dir.join(path.file_name().unwrap())).unwrap();
+            }
+        }
+        let _guard = tablebases(dir.to_str().unwrap());
+        assert_eq!(filtered("8/8/8/8/8/3k4/2R5/4K3 b - - 0 1"), vec!["d3c2"]);
+        let kept = filtered("k7/7Q/1K6/8/8/8/8/8 w - - 98 80");
+        assert!(!kept.contains(&"h7c7".to_string()));
+        assert!(kept.contains(&"b6c6".to_string()));
+
+        // Positions with too many pieces are left alone.
+        let board = Board::new_from_fen("k7/7Q/1K6/8/8/8/8/7R w - - 0 1").unwrap();
+        let mut moves = moves(&board);
+        assert!(!moves.tablebase_filter(&board));
+        assert_eq!(moves.len(), board.generate_moves().len());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -245,9 +245,12 @@ impl <'a> Evaluation <'a> {
 
         score += self.evaluate_initiative(score.eg());
 
+        let strong_side = if score.eg() > 0 { Player::White } else { Player::Black };
+        let sf = self.material_entry.scale_factor(self.board, strong_side) as i32;
+
         let phase = self.material_entry.phase as i32;
         v =   score.mg() * phase
-            + score.eg() * (PHASE_MID_GAME as i32 - phase);
+            + score.eg() * (PHASE_MID_GAME as i32 - phase) * sf / SCALE_FACTOR_NORMAL as i32;
 
         v /= PHASE_MID_GAME as i32;
 
@@ -362,10 +365,14 @@ impl <'a> Evaluation <'a> {
         score +=  self.board.psq();
         println!("all: mg: {}, eg: {}",score.mg(), score.eg());
 
+        let strong_side = if score.eg() > 0 { Player::White } else { Player::Black };
+        let sf = self.material_entry.scale_factor(self.board, strong_side) as i32;
+        println!("scale factor: {}", sf);
+
         let phase = self.material_entry.phase as i32;
         println!("phase: {}", phase);
         let mut v: i32 =   score.mg() * phase
-            + score.eg() * (PHASE_MID_GAME as i32 - phase);
+            + score.eg() * (PHASE_MID_GAME as i32 - phase) * sf / SCALE_FACTOR_NORMAL as i32;
 
         v /= PHASE_MID_GAME as i32;
 
@@ -535,7 +542,7 @@ impl <'a> Evaluation <'a> {
 
             // Analyse the safe enemy's checks which are possible on next move
             safe_b =  !self.board.get_occupied_player(them);
-            safe_b &= !self.attacked_by_all[us as usize] | (weak * self.attacked_by2[them as usize]);
+            safe_b &= !self.attacked_by_all[us as usize] | (weak & self.attacked_by2[them as usize]);
 
             let us_queen: BitBoard = self.board.piece_bb(us, PieceType::Q);
             b1 = rook_moves(self.board.get_occupied() ^ us_queen, ksq_us);
@@ -859,6 +866,7 @@ impl <'a> Evaluation <'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use pleco::board::fen::ALL_FENS;
 
 //    #[test]
 //    fn eval_stuff() {
@@ -908,5 +916,34 @@ mod tests {
         Evaluation::trace(&board);
     }
 
+    #[test]
+    fn eval_symmetry() {
+        let mut pawn_table = PawnTable::new(1 << 10);
+        let mut material = Material::new(1 << 10);
+        for fen in ALL_FENS.iter() {
+            let mut board = Board::new_from_fen(fen).unwrap();
+            // The tables are shared, so every position after a move also checks that the
+            // cached entries match the flipped position.
+            for mov in board.generate_moves().iter() {
+                board.apply_move(*mov);
+                if !board.in_check() {
+                    let score = Evaluation::evaluate(&board, &mut pawn_table, &mut material);
+                    let flipped = Evaluation::evaluate(&board.flip_colors(), &mut pawn_table, &mut material);
+                    assert_eq!(score, flipped, "{}", board.get_fen());
+                }
+                board.undo_move();
+            }
+        }
+    }
 
+    #[test]
+    fn eval_kpk() {
+        let mut pawn_table = PawnTable::new(1 << 4);
+        let mut material = Material::new(1 << 4);
+        // A rook pawn with the defending king in front of it is a draw.
+        let board = Board::new_from_fen("k7/8/8/8/8/8/P7/7K w - - 0 1").unwrap();
+        assert_eq!(Evaluation::evaluate(&board, &mut pawn_table, &mut material), 0);
+        let board = Board::new_from_fen("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1").unwrap();
+        assert!(Evaluation::evaluate(&board, &mut pawn_table, &mut material) < 0);
+    }
 }
@@ -1,6 +1,7 @@
 //! The main searching function.
 
 pub mod eval;
+pub mod params;
 
 use std::cmp::{min,max};
 use std::sync::atomic::{Ordering,AtomicBool};
@@ -9,10 +10,12 @@ use std::cell::UnsafeCell;
 use rand;
 use rand::Rng;
 
-use pleco::{MoveList,Board,BitMove};
+use pleco::{MoveList,Board,BitMove,SQ,Player};
 use pleco::core::*;
 use pleco::tools::tt::*;
 use pleco::core::score::*;
+use pleco::helper::prelude::piece_value;
+use pleco::tablebase::{self, Wdl};
 use pleco::tools::pleco_arc::Arc;
 
 use MAX_PLY;
@@ -27,17 +30,65 @@ use root_moves::RootMove;
 use root_moves::root_moves_list::RootMoveList;
 use tables::material::Material;
 use tables::pawn_table::PawnTable;
+use tables::butterfly::{ButterflyHistory, stat_bonus};
+use tables::counter_move::CounterMoveHistory;
+use movepick::MovePicker;
 use consts::*;
+use self::params::SearchParams;
 
 const THREAD_DIST: usize = 20;
 
+// Added to the static evaluation for delta pruning in the quiescence search.
+const QS_DELTA_MARGIN: Value = 128;
+
+// Scores beyond this are mates, or tablebase wins.
+const MATE_IN_MAX_PLY: Value = MATE - 2 * MAX_PLY as Value;
+
+const RAZOR_MARGIN: Value = 600;
+const PROBCUT_MARGIN: Value = 216;
+const PROBCUT_MIN_DEPTH: u16 = 5;
+const LMP_MAX_DEPTH: u16 = 8;
+
+lazy_static! {
+    // Late move reductions, indexed by the remaining depth and the number of moves played.
+    static ref REDUCTIONS: [[u16; 64]; 64] = {
+        let mut reductions = [[0; 64]; 64];
+        for d in 1..64 {
+            for m in 1..64 {
+                let r = (d as f64).ln() * (m as f64).ln() / 1.95;
+                reductions[d][m] = r.round() as u16;
+            }
+        }
+        reductions
+    };
+}
+
 //                                      1  2  3  4  5  6  7  8  9 10 11 12 13 14 15 16 17 18 19 20
 static SKIP_SIZE: [u16; THREAD_DIST] = [1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4];
 static START_PLY: [u16; THREAD_DIST] = [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1, 2, 3, 4, 5, 6, 7];
 
+const SINGULAR_MIN_DEPTH: u16 = 8;
+
+/// Information kept for each ply of the current search line.
+#[derive(Copy, Clone)]
 pub struct ThreadStack {
     pv: BitMove,
     ply: u16,
+    /// The move skipped by a singular extension search at this ply.
+    excluded_move: BitMove,
+    /// The number of plies the line leading to this ply was extended by.
+    extensions: u16,
+}
+
+impl ThreadStack {
+    pub fn new(ply: u16) -> Self {
+        ThreadStack {
+            pv: BitMove::null(),
+            ply,
+            excluded_move: BitMove::null(),
+            extensions: 0,
+        }
+    }
 }
 
 pub struct Searcher {
@@ -56,6 +107,20 @@ pub struct Searcher {
     pub pawns: PawnTable,
     pub material: Material,
     pub root_moves: UnsafeCell<RootMoveList>,
+    pub root_in_tb: bool,
+    pub root_depth: u16,
+    pub root_player: Player,
+    // how much worse than even a draw is for the root player
+    pub contempt: Value,
+    pub params: SearchParams,
+    // null moves are not tried before this ply, while verifying a null move cutoff
+    pub nmp_min_ply: u16,
+    pub stack: [ThreadStack; THREAD_STACK_SIZE],
+
+    // move ordering, kept between searches
+    pub history: ButterflyHistory,
+    pub counter_moves: CounterMoveHistory,
+    pub killers: [[BitMove; 2]; MAX_PLY as usize],
 
     // MainThread Information
     pub previous_score: Value,
@@ -80,10 +145,28 @@ impl Searcher {
             pawns: PawnTable::new(16384),
             material: Material::new(8192),
             root_moves: UnsafeCell::new(RootMoveList::new()),
+            root_in_tb: false,
+            root_depth: 0,
+            root_player: Player::White,
+            contempt: DEFAULT_CONTEMPT,
+            params: SearchParams::default(),
+            nmp_min_ply: 0,
+            stack: [ThreadStack::new(0); THREAD_STACK_SIZE],
+            history: ButterflyHistory::new(),
+            counter_moves: CounterMoveHistory::new(),
+            killers: [[BitMove::null(); 2]; MAX_PLY as usize],
             previous_score: 0
         }
     }
 
+    /// Clears the move ordering tables, for when a new game is started.
+    pub fn clear(&mut self) {
+        self.history.clear();
+        self.counter_moves.clear();
+        self.killers = [[BitMove::null(); 2]; MAX_PLY as usize];
+        self.previous_score = 0;
+    }
+
     pub fn idle_loop(&mut self) {
         self.searching.set(false);
         loop {
@@ -139,11 +222,11 @@ impl Searcher {
         self.previous_score = best_score;
 
         if self.use_stdout() && best_move != self.root_moves().first().bit_move {
-            println!("info id 0 pv {}",self.root_moves().first().bit_move);
+            println!("info id 0 pv {}", self.board.move_to_uci(self.root_moves().first().bit_move));
         }
 
         if self.use_stdout() {
-            println!("bestmove {}", best_move.to_string());
+            println!("bestmove {}", self.board.move_to_uci(best_move));
         }
     }
 
@@ -172,6 +255,10 @@ impl Searcher {
         let start_ply: u16 = START_PLY[self.id % THREAD_DIST];
         let skip_size: u16 = SKIP_SIZE[self.id % THREAD_DIST];
         let mut depth: u16 = start_ply;
+        self.nmp_min_ply = 0;
+        for (ply, entry) in self.stack.iter_mut().enumerate() {
+            *entry = ThreadStack::new(ply as u16);
+        }
 
         let mut delta: i32 = NEG_INFINITE as i32;
         #[allow(unused_assignments)]
@@ -199,6 +286,7 @@ impl Searcher {
 
             'aspiration_window: loop {
 
+                self.root_depth = depth;
                 best_value = self.search::<PV>(alpha, beta, depth) as i32;
                 self.root_moves().sort();
 
@@ -224,7 +312,7 @@ impl Searcher {
                 println!("info depth {} score {} pv {}",
                          depth,
                          best_value,
-                         self.root_moves().first().bit_move.to_string());
+                         self.board.move_to_uci(self.root_moves().first().bit_move));
             }
             if !self.stop() {
                 self.depth_completed = depth;
@@ -266,13 +354,19 @@ impl Searcher {
     fn search<N: PVNode>(&mut self, mut alpha: i32, beta: i32, max_depth: u16) -> i32 {
         let is_pv: bool = N::is_pv();
         let at_root: bool = self.board.depth() == 0;
-        let zob: u64 = self.board.zobrist();
+        let ply: u16 = self.board.depth();
+        let excluded_move: BitMove = self.stack[ply as usize].excluded_move;
+        // A search excluding a move gets its own transposition table entry.
+        let zob: u64 = self.board.zobrist() ^ ((excluded_move.get_raw() as u64) << 16);
         let (tt_hit, tt_entry): (bool, &mut Entry) = TT_TABLE.probe(zob);
         let tt_value: Value = if tt_hit {tt_entry.score as i32} else {0};
+        let tt_move: BitMove = if tt_hit {tt_entry.best_move} else {BitMove::null()};
+        let tt_depth: u16 = if tt_hit {tt_entry.depth as u16} else {0};
+        let tt_bound: NodeBound = if tt_hit {tt_entry.node_type()} else {NodeBound::NoBound};
         let in_check: bool = self.board.in_check();
-        let ply: u16 = self.board.depth();
 
         let mut best_move = BitMove::null();
+        let mut quiets_searched: MoveList = MoveList::default();
 
         let mut value: Value = NEG_INFINITE;
         let mut best_value: Value = NEG_INFINITE;
@@ -284,10 +378,18 @@ impl Searcher {
             self.check_time();
         }
 
-        if ply >= max_depth || self.stop() {
+        if self.stop() {
             return self.eval();
         }
 
+        if ply >= max_depth {
+            return self.qsearch::<N>(alpha, beta);
+        }
+
+        if !at_root && self.is_draw() {
+            return self.draw_value();
+        }
+
         let plys_to_zero = max_depth - ply;
 
         if !at_root {
@@ -306,13 +408,34 @@ impl Searcher {
             return tt_value;
         }
 
+        // Probe the tablebases after captures and pawn moves, unless the root moves
+        // were already filtered by them.
+        if !at_root
+            && !self.root_in_tb
+            && excluded_move.is_null()
+            && self.board.rule_50() == 0
+            && self.board.count_all_pieces() as usize <= tablebase::max_pieces() {
+            if let Some(wdl) = tablebase::probe_wdl(&self.board) {
+                let (value, node_bound) = match wdl {
+                    Wdl::Win => (MATE - MAX_PLY as i32 - ply as i32 - 1, NodeBound::LowerBound),
+                    Wdl::Loss => (-MATE + MAX_PLY as i32 + ply as i32 + 1, NodeBound::UpperBound),
+                    _ => (DRAW + 2 * wdl.to_i32(), NodeBound::Exact),
+                };
+                if node_bound == NodeBound::Exact
+                    || (node_bound == NodeBound::LowerBound && value >= beta)
+                    || (node_bound == NodeBound::UpperBound && value <= alpha) {
+                    tt_entry.place(zob, BitMove::null(), value as i16, 0,
+                                   min(plys_to_zero + 6, MAX_PLY - 1) as u8, node_bound);
+                    return value;
+                }
+            }
+        }
+
         if in_check {
             pos_eval = 0;
         } else {
             if tt_hit {
-                if tt_entry.eval == 0 {
-                    pos_eval = self.eval();
-                }
+                pos_eval = if tt_entry.eval == 0 { self.eval() } else { tt_entry.eval as i32 };
                 if tt_value != 0 && correct_bound(tt_value, pos_eval, tt_entry.node_type()) {
                     pos_eval = tt_value;
                 }
@@ -322,97 +445,280 @@ impl Searcher {
             }
         }
 
-        if !in_check {
-            if ply > 3
-                && ply < 7
-                && pos_eval - futility_margin(ply) >= beta && pos_eval < 10000 {
+        let us: Player = self.board.turn();
+
+        if !is_pv && !in_check {
+            // Razoring: at depth one, a position far below alpha is unlikely to be saved by
+            // a quiet move.
+            if self.params.razoring
+                && plys_to_zero < 2
+                && pos_eval + RAZOR_MARGIN <= alpha {
+                return self.qsearch::<NonPV>(alpha, beta);
+            }
+
+            // Reverse futility pruning: the static evaluation beats beta by enough that
+            // the opponent is unlikely to catch up in the remaining depth.
+            if self.params.reverse_futility
+                && plys_to_zero < 7
+                && pos_eval - futility_margin(plys_to_zero) >= beta
+                && pos_eval < MATE_IN_MAX_PLY {
                 return pos_eval;
             }
-        }
 
-        #[allow(unused_mut)]
-        let mut moves: MoveList = if at_root {
-            self.root_moves().iter().map(|r| r.bit_move).collect()
-        } else {
-            self.board.generate_pseudolegal_moves()
-        };
+            // Null move pruning: if passing still fails high at a reduced depth, so will
+            // a real move. Positions with little material are prone to zugzwang, so the
+            // cutoff is verified by a reduced search without null moves.
+            if self.params.null_move
+                && excluded_move.is_null()
+                && pos_eval >= beta
+                && ply >= self.nmp_min_ply
+                && self.board.last_move().is_some()
+                && self.board.non_pawn_material(us) > 0 {
+                let r: u16 = min((823 + 67 * plys_to_zero as i32) / 256
+                                     + min((pos_eval - beta) / 200, 3),
+                                 plys_to_zero as i32) as u16;
+                self.board.apply_null_move();
+                self.tt.prefetch(self.board.zobrist());
+                let mut null_value: Value = -self.search::<NonPV>(-beta, -beta + 1, max_depth - r);
+                self.board.undo_null_move();
 
-        if moves.is_empty() {
-            if self.board.in_check() {
-                return MATE as i32 - (ply as i32);
-            } else {
-                return DRAW as i32;
+                if self.stop() {
+                    return 0;
+                }
+
+                if null_value >= beta {
+                    if null_value >= MATE_IN_MAX_PLY {
+                        null_value = beta;
+                    }
+                    let zugzwang_prone: bool = self.board.non_pawn_material(us) <= ROOK_MG;
+                    if self.nmp_min_ply != 0 || (plys_to_zero < 12 && !zugzwang_prone) {
+                        return null_value;
+                    }
+
+                    self.nmp_min_ply = ply + 3 * (plys_to_zero - r) / 4;
+                    let value: Value = self.search::<NonPV>(beta - 1, beta, max_depth - r);
+                    self.nmp_min_ply = 0;
+                    if value >= beta {
+                        return null_value;
+                    }
+                }
+            }
+
+            // ProbCut: a good capture that beats beta by a margin at a much reduced depth
+            // will most likely beat beta at full depth.
+            if self.params.probcut
+                && plys_to_zero >= PROBCUT_MIN_DEPTH
+                && beta.abs() < MATE_IN_MAX_PLY {
+                let raised_beta: Value = min(beta + PROBCUT_MARGIN, INFINITE);
+                let mut picker = MovePicker::probcut(&self.board, tt_move, raised_beta - pos_eval);
+                while let Some(mov) = picker.next(&self.board, &self.history) {
+                    if !self.board.legal_move(mov) {
+                        continue;
+                    }
+                    let gives_check: bool = self.board.gives_check(mov);
+                    self.board.apply_unknown_move(mov, gives_check);
+                    self.tt.prefetch(self.board.zobrist());
+                    let mut value: Value = -self.qsearch::<NonPV>(-raised_beta, -raised_beta + 1);
+                    if value >= raised_beta {
+                        value = -self.search::<NonPV>(-raised_beta, -raised_beta + 1, max_depth - 3);
+                    }
+                    self.board.undo_move();
+                    if self.stop() {
+                        return 0;
+                    }
+                    if value >= raised_beta {
+                        return value;
+                    }
+                }
             }
         }
 
-        if !at_root {
-            mvv_lva_sort(&mut moves, &self.board);
+        // The previous move, and the piece that made it, for the countermove heuristic.
+        let prev_move: Option<(PieceType, SQ)> = self.board.last_move()
+            .and_then(|m| self.board.piece_at_sq(m.get_dest()).map(|p| (p, m.get_dest())));
+        let counter_move: BitMove = match prev_move {
+            Some((piece, sq)) => self.counter_moves.get(self.board.turn().other_player(), piece, sq),
+            None => BitMove::null()
+        };
+
+        if (ply as usize + 2) < MAX_PLY as usize {
+            self.killers[ply as usize + 2] = [BitMove::null(); 2];
         }
 
+        let mut picker = MovePicker::main_search(&self.board, tt_move, self.killers[ply as usize], counter_move);
+        let mut root_index: usize = 0;
 
-        for (i, mov) in moves.iter().enumerate() {
-            if at_root || self.board.legal_move(*mov) {
-                moves_played += 1;
-                let gives_check: bool = self.board.gives_check(*mov);
-                self.board.apply_unknown_move(*mov, gives_check);
-                self.tt.prefetch(self.board.zobrist());
-                let do_full_depth: bool = if max_depth >= 3 && moves_played > 1 && ply >= 2 {
-                    if in_check || gives_check {
-                        value = -self.search::<NonPV>(-(alpha+1), -alpha, max_depth - 1);
-                    } else {
-                        value = -self.search::<NonPV>(-(alpha+1), -alpha, max_depth - 2);
-                    }
-                    value > alpha
-                } else {
-                    !is_pv || moves_played > 1
-                };
-                if do_full_depth {
-                    value = -self.search::<NonPV>(-(alpha+1), -alpha, max_depth);
+        loop {
+            let mov: BitMove = if at_root {
+                if root_index >= self.root_moves().len() {
+                    break;
                 }
-                if is_pv && (moves_played == 1 || (value > alpha && (at_root || value < beta))) {
-                    value = -self.search::<PV>(-beta, -alpha, max_depth);
+                root_index += 1;
+                self.root_moves()[root_index - 1].bit_move
+            } else {
+                match picker.next(&self.board, &self.history) {
+                    Some(m) => m,
+                    None => break
                 }
-                self.board.undo_move();
-                assert!(value > NEG_INFINITE);
-                assert!(value < INFINITE );
+            };
+
+            if mov == excluded_move || (!at_root && !self.board.legal_move(mov)) {
+                continue;
+            }
+
+            moves_played += 1;
+            let is_quiet: bool = !mov.is_capture() && !mov.is_promo();
+            let gives_check: bool = self.board.gives_check(mov);
+
+            let mut extension: u16 = 0;
+            let can_extend: bool = max_depth + 1 < MAX_PLY
+                && self.stack[ply as usize].extensions < self.root_depth;
+
+            // Singular extension: if every other move fails low against a reduced search
+            // below the value of the transposition table move, that move is the only good
+            // one and is extended. If even the other moves beat beta, the node is cut.
+            if self.params.singular_extensions
+                && !at_root
+                && excluded_move.is_null()
+                && mov == tt_move
+                && plys_to_zero >= SINGULAR_MIN_DEPTH
+                && tt_value.abs() < MATE_IN_MAX_PLY
+                && tt_bound as u8 & NodeBound::LowerBound as u8 != 0
+                && tt_depth + 3 >= plys_to_zero {
+                let singular_beta: Value = tt_value - 2 * plys_to_zero as Value;
+                self.stack[ply as usize].excluded_move = mov;
+                let value: Value = self.search::<NonPV>(singular_beta - 1, singular_beta, ply + plys_to_zero / 2);
+                self.stack[ply as usize].excluded_move = BitMove::null();
+
                 if self.stop() {
                     return 0;
                 }
-                if at_root {
-                    let rm: &mut RootMove = unsafe { self.root_moves().get_unchecked_mut(i) };
 
-                    if moves_played == 1 || value > alpha {
-                        rm.depth_reached = max_depth;
-                        rm.score = value;
-
-                    } else {
-                        rm.score = NEG_INFINITE;
+                if value < singular_beta {
+                    if can_extend {
+                        extension = 1;
                     }
+                } else if singular_beta >= beta {
+                    // Multi-cut: more than one move beats beta.
+                    return singular_beta;
                 }
+            }
 
-                if value > best_value {
-                    best_value = value;
+            if extension == 0 && can_extend {
+                let prev_capture: Option<BitMove> = self.board.last_move().filter(|m| m.is_capture());
+                // Check extension, for checks not losing material.
+                if self.params.check_extensions && gives_check && self.board.see_ge(mov, 0) {
+                    extension = 1;
+                // Recapture extension.
+                } else if self.params.recapture_extensions
+                    && mov.is_capture()
+                    && prev_capture.map_or(false, |m| m.get_dest() == mov.get_dest()) {
+                    extension = 1;
+                // Passed pawn push extension, to the seventh rank.
+                } else if self.params.passed_pawn_extensions
+                    && self.board.moved_piece(mov) == PieceType::P
+                    && us.relative_rank_of_sq(mov.get_dest()) == Rank::R7
+                    && self.board.pawn_passed(us, mov.get_dest()) {
+                    extension = 1;
+                }
+            }
+
+            let new_max_depth: u16 = max_depth + extension;
+            self.stack[ply as usize + 1].extensions = self.stack[ply as usize].extensions + extension;
+
+            // Late move pruning: at low depth, quiet moves this late are unlikely to be good.
+            if self.params.late_move_pruning
+                && !at_root
+                && !in_check
+                && !gives_check
+                && is_quiet
+                && best_value > -MATE_IN_MAX_PLY
+                && plys_to_zero < LMP_MAX_DEPTH
+                && moves_played >= late_move_count(plys_to_zero)
+                && self.board.non_pawn_material(us) > 0 {
+                picker.skip_quiets();
+                continue;
+            }
 
-                    if value > alpha {
-                        best_move = *mov;
-                        if is_pv && value < beta {
-                            alpha = value;
-                        } else {
-                            break;
-                        }
+            let reduction: u16 = if self.params.late_move_reductions
+                && plys_to_zero >= 3
+                && moves_played > 1
+                && is_quiet
+                && !in_check
+                && !gives_check {
+                let r = REDUCTIONS[min(plys_to_zero, 63) as usize][min(moves_played, 63) as usize];
+                let r = if is_pv { r.saturating_sub(1) } else { r };
+                min(r, plys_to_zero - 2)
+            } else {
+                0
+            };
+
+            self.board.apply_unknown_move(mov, gives_check);
+            self.tt.prefetch(self.board.zobrist());
+            let do_full_depth: bool = if reduction > 0 {
+                value = -self.search::<NonPV>(-(alpha+1), -alpha, new_max_depth - reduction);
+                value > alpha
+            } else {
+                !is_pv || moves_played > 1
+            };
+            if do_full_depth {
+                value = -self.search::<NonPV>(-(alpha+1), -alpha, new_max_depth);
+            }
+            if is_pv && (moves_played == 1 || (value > alpha && (at_root || value < beta))) {
+                value = -self.search::<PV>(-beta, -alpha, new_max_depth);
+            }
+            self.board.undo_move();
+            assert!(value > NEG_INFINITE);
+            assert!(value < INFINITE );
+            if self.stop() {
+                return 0;
+            }
+            if at_root {
+                let rm: &mut RootMove = unsafe { self.root_moves().get_unchecked_mut(root_index - 1) };
+
+                if moves_played == 1 || value > alpha {
+                    rm.depth_reached = max_depth;
+                    rm.score = value;
+
+                } else {
+                    rm.score = NEG_INFINITE;
+                }
+            }
+
+            if value > best_value {
+                best_value = value;
+
+                if value > alpha {
+                    best_move = mov;
+                    if is_pv && value < beta {
+                        alpha = value;
+                    } else {
+                        break;
                     }
                 }
             }
+
+            if is_quiet && mov != best_move {
+                quiets_searched.push(mov);
+            }
         }
 
         if moves_played == 0 {
+            // Only the excluded move was legal.
+            if !excluded_move.is_null() {
+                return alpha;
+            }
             if self.board.in_check() {
-                return MATE as i32 - (ply as i32);
+                return -MATE as i32 + (ply as i32);
             } else {
-                return DRAW as i32;
+                return self.draw_value();
             }
         }
 
+        if best_value >= beta && !best_move.is_capture() && !best_move.is_promo() {
+            self.update_quiet_stats(best_move, prev_move, &quiets_searched, stat_bonus(plys_to_zero));
+        }
+
         let node_bound = if best_value as i32 >= beta {NodeBound::LowerBound}
             else if is_pv && !best_move.is_null() {NodeBound::Exact}
                 else {NodeBound::UpperBound};
@@ -423,7 +729,167 @@ impl Searcher {
         best_value
     }
 
-    // TODO: Qscience search
+    /// Searches the captures and promotions of a position, or every evasion when in check,
+    /// until the position is quiet enough to trust the static evaluation.
+    ///
+    /// Unless in check, the side to move may "stand pat" on the static evaluation instead of
+    /// capturing. Captures that cannot raise the score above alpha, even after winning the
+    /// captured piece, and captures that lose material by SEE, are skipped.
+    fn qsearch<N: PVNode>(&mut self, mut alpha: i32, beta: i32) -> i32 {
+        let is_pv: bool = N::is_pv();
+        let old_alpha: i32 = alpha;
+        let zob: u64 = self.board.zobrist();
+        let (tt_hit, tt_entry): (bool, &mut Entry) = TT_TABLE.probe(zob);
+        let tt_value: Value = if tt_hit {tt_entry.score as i32} else {0};
+        let in_check: bool = self.board.in_check();
+        let ply: u16 = self.board.depth();
+
+        if ply >= MAX_PLY - 1 {
+            return if in_check { self.draw_value() } else { self.eval() };
+        }
+
+        if self.is_draw() {
+            return self.draw_value();
+        }
+
+        if !is_pv
+            && tt_hit
+            && correct_bound_eq(tt_value, beta, tt_entry.node_type()) {
+            return tt_value;
+        }
+
+        let mut best_move = BitMove::null();
+        let mut best_value: Value;
+        let mut pos_eval: Value = 0;
+        let futility_base: Value;
+
+        if in_check {
+            best_value = NEG_INFINITE;
+            futility_base = NEG_INFINITE;
+        } else {
+            pos_eval = if tt_hit && tt_entry.eval != 0 { tt_entry.eval as i32 } else { self.eval() };
+            best_value = pos_eval;
+            if tt_hit && correct_bound(tt_value, best_value, tt_entry.node_type()) {
+                best_value = tt_value;
+            }
+
+            // Stand pat.
+            if best_value >= beta {
+                if !tt_hit {
+                    tt_entry.place(zob, BitMove::null(), best_value as i16, pos_eval as i16, 0, NodeBound::LowerBound);
+                }
+                return best_value;
+            }
+
+            if is_pv && best_value > alpha {
+                alpha = best_value;
+            }
+
+            futility_base = best_value + QS_DELTA_MARGIN;
+        }
+
+        let tt_move: BitMove = if tt_hit {tt_entry.best_move} else {BitMove::null()};
+        let mut picker = MovePicker::qsearch(&self.board, tt_move);
+
+        while let Some(mov) = picker.next(&self.board, &self.history) {
+            if !self.board.legal_move(mov) {
+                continue;
+            }
+            let gives_check: bool = self.board.gives_check(mov);
+
+            if !in_check && !gives_check && !mov.is_promo() {
+                // Delta pruning: even winning the captured piece for free does not raise alpha.
+                let captured: PieceType = self.board.captured_piece(mov).unwrap();
+                let futility_value: Value = futility_base + piece_value(captured, true);
+                if futility_value <= alpha {
+                    best_value = max(best_value, futility_value);
+                    continue;
+                }
+                if futility_base <= alpha && !self.board.see_ge(mov, 1) {
+                    best_value = max(best_value, futility_base);
+                    continue;
+                }
+            }
+
+            // Captures losing material are not worth searching.
+            if !in_check && !mov.is_promo() && !self.board.see_ge(mov, 0) {
+                continue;
+            }
+
+            self.board.apply_unknown_move(mov, gives_check);
+            self.tt.prefetch(self.board.zobrist());
+            let value: Value = -self.qsearch::<N>(-beta, -alpha);
+            self.board.undo_move();
+
+            if self.stop() {
+                return 0;
+            }
+
+            if value > best_value {
+                best_value = value;
+                if value > alpha {
+                    best_move = mov;
+                    if is_pv && value < beta {
+                        alpha = value;
+                    } else {
+                        tt_entry.place(zob, best_move, best_value as i16, pos_eval as i16, 0, NodeBound::LowerBound);
+                        return best_value;
+                    }
+                }
+            }
+        }
+
+        // Checkmate.
+        if in_check && best_value == NEG_INFINITE {
+            return -MATE as i32 + (ply as i32);
+        }
+
+        let node_bound = if is_pv && best_value > old_alpha {NodeBound::Exact} else {NodeBound::UpperBound};
+        tt_entry.place(zob, best_move, best_value as i16, pos_eval as i16, 0, node_bound);
+
+        best_value
+    }
+
+    /// Rewards the quiet move causing a beta cutoff, making it a killer and the countermove
+    /// of the previous move, and penalizes the quiet moves searched before it.
+    fn update_quiet_stats(&mut self, mov: BitMove, prev_move: Option<(PieceType, SQ)>,
+                          quiets: &MoveList, bonus: i32) {
+        let ply = self.board.depth() as usize;
+        let turn = self.board.turn();
+        if self.killers[ply][0] != mov {
+            self.killers[ply][1] = self.killers[ply][0];
+            self.killers[ply][0] = mov;
+        }
+        if let Some((piece, sq)) = prev_move {
+            self.counter_moves.set(turn.other_player(), piece, sq, mov);
+        }
+        self.history.update(turn, mov, bonus);
+        for quiet in quiets.iter() {
+            self.history.update(turn, *quiet, -bonus);
+        }
+    }
+
+    /// Returns if the position is drawn by repetition, the fifty-move rule or insufficient
+    /// material.
+    ///
+    /// Any repetition since the last capture or pawn move counts as a draw, whether the earlier
+    /// position was reached in the game or in the current search line. The side repeating
+    /// could have repeated again, so it cannot be doing better than a draw.
+    fn is_draw(&self) -> bool {
+        (self.board.rule_50() >= 4 && self.board.is_repetition(2))
+            || self.board.is_fifty_move_draw()
+            || self.board.is_insufficient_material()
+    }
+
+    /// Returns the value of a draw for the side to move, accounting for contempt.
+    #[inline]
+    fn draw_value(&self) -> Value {
+        if self.board.turn() == self.root_player {
+            DRAW - self.contempt
+        } else {
+            DRAW + self.contempt
+        }
+    }
 
     pub fn eval(&mut self) -> Value {
         let pawns = &mut self.pawns;
@@ -510,26 +976,6 @@ impl Drop for Searcher {
     }
 }
 
-fn mvv_lva_sort(moves: &mut MoveList, board: &Board) {
-    moves.sort_by_key(|a| {
-        let piece = board.piece_at_sq((*a).get_src()).unwrap();
-
-        if a.is_capture() {
-            piece.value() - board.captured_piece(*a).unwrap().value()
-        } else if a.is_castle() {
-            1
-        } else if piece == PieceType::P {
-            if a.is_double_push().0 {
-                2
-            } else {
-                3
-            }
-        } else {
-            4
-        }
-    })
-}
-
 fn correct_bound_eq(tt_value: i32, beta: i32, bound: NodeBound) -> bool {
     if tt_value as i32 >= beta {
         bound as u8 & NodeBound::LowerBound as u8 != 0
@@ -549,4 +995,10 @@ fn correct_bound(tt_value: i32, val: i32, bound: NodeBound) -> bool {
 #[inline]
 fn futility_margin(depth: u16) -> i32 {
     depth as i32 * 150
+}
+
+/// Returns the number of moves after which quiet moves are pruned, given the remaining depth.
+#[inline]
+fn late_move_count(depth: u16) -> u32 {
+    (5 + depth as u32 * depth as u32) / 2
 }
\ No newline at end of file
@@ -0,0 +1,67 @@
+//! Switches for the selective search techniques of the main search.
+//!
+//! Every technique is enabled by default, apart from the recapture and passed pawn push
+//! extensions. Disabling one makes the search slower, but is useful to measure how much it
+//! gains, or to check that it does not miss tactics.
+
+/// Which of the selective search techniques and extensions `Searcher::search` may use.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SearchParams {
+    /// Searches a null move at reduced depth, and prunes the node if it still fails high.
+    pub null_move: bool,
+    /// Searches late quiet moves at a reduced depth first.
+    pub late_move_reductions: bool,
+    /// Prunes nodes at low depth whose static evaluation is far above beta.
+    pub reverse_futility: bool,
+    /// Drops into the quiescence search at depth one when the static evaluation is far
+    /// below alpha.
+    pub razoring: bool,
+    /// Skips the late quiet moves of nodes at low depth.
+    pub late_move_pruning: bool,
+    /// Prunes nodes where a good capture beats beta by a margin at a much reduced depth.
+    pub probcut: bool,
+    /// Extends checks that do not lose material.
+    pub check_extensions: bool,
+    /// Extends the transposition table move when every other move fails low against a
+    /// reduced search.
+    pub singular_extensions: bool,
+    /// Extends recaptures on the square of the previous capture.
+    pub recapture_extensions: bool,
+    /// Extends pushes of passed pawns to the seventh rank.
+    pub passed_pawn_extensions: bool,
+}
+
+impl SearchParams {
+    /// Returns parameters with every selective search technique and extension disabled.
+    pub fn none() -> Self {
+        SearchParams {
+            null_move: false,
+            late_move_reductions: false,
+            reverse_futility: false,
+            razoring: false,
+            late_move_pruning: false,
+            probcut: false,
+            check_extensions: false,
+            singular_extensions: false,
+            recapture_extensions: false,
+            passed_pawn_extensions: false,
+        }
+    }
+}
+
+impl Default for SearchParams {
+    fn default() -> Self {
+        SearchParams {
+            null_move: true,
+            late_move_reductions: true,
+            reverse_futility: true,
+            razoring: true,
+            late_move_pruning: true,
+            probcut: true,
+            check_extensions: true,
+            singular_extensions: true,
+            recapture_extensions: false,
+            passed_pawn_extensions: false,
+        }
+    }
+}
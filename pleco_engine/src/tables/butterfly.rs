@@ -0,0 +1,56 @@
+//! The history of quiet moves, indexed by the side to move and the from and to squares
+//! of the move.
+//!
+//! Quiet moves that cause a beta cutoff get a bonus, and the quiet moves searched before them
+//! get a malus. Moves with a higher history are tried first.
+
+use pleco::{Player, BitMove};
+use pleco::core::masks::PLAYER_CNT;
+
+/// The largest bonus or malus given by a single update.
+pub const HISTORY_BONUS_MAX: i32 = 324;
+
+pub struct ButterflyHistory {
+    table: [[i16; 64 * 64]; PLAYER_CNT],
+}
+
+impl ButterflyHistory {
+    /// Creates an empty history.
+    pub fn new() -> Self {
+        ButterflyHistory {
+            table: [[0; 64 * 64]; PLAYER_CNT],
+        }
+    }
+
+    /// Resets the history of every move.
+    pub fn clear(&mut self) {
+        self.table = [[0; 64 * 64]; PLAYER_CNT];
+    }
+
+    /// Returns the history of a move played by `player`.
+    #[inline]
+    pub fn get(&self, player: Player, mov: BitMove) -> i32 {
+        self.table[player as usize][index(mov)] as i32
+    }
+
+    /// Adds a bonus, or a malus if negative, to the history of a move. Entries saturate as they
+    /// grow, staying within `32 * HISTORY_BONUS_MAX` of zero.
+    pub fn update(&mut self, player: Player, mov: BitMove, bonus: i32) {
+        let bonus = bonus.max(-HISTORY_BONUS_MAX).min(HISTORY_BONUS_MAX);
+        let entry: &mut i16 = &mut self.table[player as usize][index(mov)];
+        let value = *entry as i32;
+        *entry = (value + bonus * 32 - value * bonus.abs() / HISTORY_BONUS_MAX) as i16;
+    }
+}
+
+#[inline(always)]
+fn index(mov: BitMove) -> usize {
+    mov.get_src_u8() as usize * 64 + mov.get_dest_u8() as usize
+}
+
+/// Returns the history bonus of a move that causes a cutoff, given the remaining depth.
+#[inline]
+pub fn stat_bonus(depth: u16) -> i32 {
+    let d = depth as i32;
+    if d > 17 { 0 } else { d * d + 2 * d - 2 }
+}
@@ -0,0 +1,37 @@
+//! The countermove table, holding for each previous move the quiet move that last refuted it.
+//!
+//! The previous move is identified by the piece that moved, along with its color, and the
+//! square it moved to.
+
+use pleco::{Player, PieceType, SQ, BitMove};
+use pleco::core::masks::{PLAYER_CNT, PIECE_TYPE_CNT};
+
+pub struct CounterMoveHistory {
+    table: [[[BitMove; 64]; PIECE_TYPE_CNT]; PLAYER_CNT],
+}
+
+impl CounterMoveHistory {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        CounterMoveHistory {
+            table: [[[BitMove::null(); 64]; PIECE_TYPE_CNT]; PLAYER_CNT],
+        }
+    }
+
+    /// Removes every countermove.
+    pub fn clear(&mut self) {
+        self.table = [[[BitMove::null(); 64]; PIECE_TYPE_CNT]; PLAYER_CNT];
+    }
+
+    /// Returns the countermove to a `piece` of `player` moving to `sq`, or a null move.
+    #[inline]
+    pub fn get(&self, player: Player, piece: PieceType, sq: SQ) -> BitMove {
+        self.table[player as usize][piece as usize][sq.0 as usize]
+    }
+
+    /// Sets the countermove to a `piece` of `player` moving to `sq`.
+    #[inline]
+    pub fn set(&mut self, player: Player, piece: PieceType, sq: SQ, mov: BitMove) {
+        self.table[player as usize][piece as usize][sq.0 as usize] = mov;
+    }
+}
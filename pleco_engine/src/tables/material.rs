@@ -4,6 +4,8 @@ use pleco::{Player, Board, PieceType};
 use pleco::core::masks::{PLAYER_CNT,PIECE_TYPE_CNT};
 use pleco::core::score::*;
 use pleco::core::mono_traits::*;
+use pleco::tablebase::Wdl;
+use pleco::tablebase::bitbase::Bitbase;
 
 use super::TableBase;
 
@@ -38,6 +40,11 @@ const QUADRATIC_THEIRS: [[i32; PIECE_TYPE_CNT]; PIECE_TYPE_CNT] = [
 ]; // pair pawn knight bishop rook queen
    //           THEIR PIECES
 
+lazy_static! {
+    /// Bitbase of a king and pawn against a king, generated by `consts::init_globals`.
+    pub static ref KPK: Bitbase = Bitbase::generate("KPK").unwrap();
+}
+
 pub struct MaterialEntry {
     key: u64,
     pub value: Value,
@@ -51,6 +58,20 @@ impl MaterialEntry {
         Score(self.value, self.value)
     }
 
+    /// Returns the factor to scale the endgame score of the `strong` player by, out of
+    /// `SCALE_FACTOR_NORMAL`.
+    ///
+    /// A lone pawn against a bare king is looked up in the KPK bitbase, so drawn positions
+    /// are scaled down to nothing.
+    pub fn scale_factor(&self, board: &Board, strong: Player) -> u8 {
+        if board.count_all_pieces() == 3 && board.count_piece(strong, PieceType::P) == 1 {
+            return match KPK.probe(board) {
+                Some(Wdl::Draw) => SCALE_FACTOR_DRAW,
+                _ => SCALE_FACTOR_NORMAL
+            };
+        }
+        self.factor[strong as usize]
+    }
 }
 
 
@@ -86,7 +107,7 @@ impl Material {
         }
 
         entry.key = key;
-        entry.factor = [0; PLAYER_CNT];
+        entry.factor = [SCALE_FACTOR_NORMAL; PLAYER_CNT];
 
         let npm_w: Value = board.non_pawn_material(Player::White);
         let npm_b: Value = board.non_pawn_material(Player::Black);
@@ -1,6 +1,8 @@
 
 pub mod pawn_table;
 pub mod material;
+pub mod butterfly;
+pub mod counter_move;
 
 use std::ptr::NonNull;
 use std::heap::{Alloc, Layout, Heap};
@@ -10,16 +10,17 @@ use std::cell::UnsafeCell;
 
 use crossbeam_utils::scoped;
 
-use pleco::MoveList;
 use pleco::tools::pleco_arc::Arc;
 use pleco::board::*;
 use pleco::core::piece_move::BitMove;
 
 use root_moves::RootMove;
+use root_moves::root_moves_list::RootMoveList;
 use sync::LockLatch;
 use time::uci_timer::*;
 use time::time_management::TimeManager;
 use search::Searcher;
+use search::params::SearchParams;
 
 use consts::*;
 
@@ -71,7 +72,9 @@ pub struct ThreadPool {
     handles: Vec<JoinHandle<()>>,
     pub main_cond: Arc<LockLatch>,
     pub thread_cond: Arc<LockLatch>,
-    pub stop: AtomicBool
+    pub stop: AtomicBool,
+    pub search_params: SearchParams,
+    pub contempt: i32,
 }
 
 // Okay, this all looks like madness, but there is some reason to it all.
@@ -92,7 +95,9 @@ impl ThreadPool {
             handles: Vec::with_capacity(256),
             main_cond: Arc::new(LockLatch::new()),
             thread_cond: Arc::new(LockLatch::new()),
-            stop: AtomicBool::new(true)
+            stop: AtomicBool::new(true),
+            search_params: SearchParams::default(),
+            contempt: DEFAULT_CONTEMPT,
         };
         pool.main_cond.lock();
         pool.thread_cond.lock();
@@ -201,6 +206,16 @@ impl ThreadPool {
         self.stop.store(stop, Ordering::Relaxed);
     }
 
+    /// Clears the move ordering tables of every thread, waiting for any search to finish.
+    pub fn clear_all(&mut self) {
+        self.wait_for_finish();
+        unsafe {
+            self.threads.iter()
+                .map(|t| &mut **t.get())
+                .for_each(|t| t.clear());
+        }
+    }
+
     /// Waits for all the threads to finish
     pub fn wait_for_finish(&self) {
         unsafe {
@@ -235,9 +250,11 @@ impl ThreadPool {
     /// Starts a UCI search. The result will be printed to stdout if the stdout setting
     /// is true.
     pub fn uci_search(&mut self, board: &Board, limits: &Limits) {
-        let root_moves: MoveList = board.generate_moves();
+        let mut root_moves = RootMoveList::new();
+        root_moves.replace(&board.generate_moves());
 
-        assert!(!root_moves.is_empty());
+        assert!(root_moves.len() > 0);
+        let root_in_tb = root_moves.tablebase_filter(board);
         self.wait_for_finish();
         self.stop.store(false, Ordering::Relaxed);
 
@@ -246,7 +263,11 @@ impl ThreadPool {
             thread.depth_completed = 0;
             thread.board = board.shallow_clone();
             thread.limit = limits.clone();
-            thread.root_moves().replace(&root_moves);
+            thread.root_in_tb = root_in_tb;
+            thread.params = self.search_params;
+            thread.contempt = self.contempt;
+            thread.root_player = board.turn();
+            thread.root_moves().clone_from_other(&root_moves);
         }
 
         self.main_cond.set();
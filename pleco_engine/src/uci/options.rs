@@ -1,6 +1,6 @@
 //! Houses any UCI compatible options, as well as the framework for parsing and applying them.
 
-use consts::{MAX_THREADS,DEFAULT_TT_SIZE};
+use consts::{MAX_THREADS,DEFAULT_TT_SIZE,DEFAULT_BOOK_FILE,DEFAULT_SYZYGY_PATH,DEFAULT_CONTEMPT};
 
 use std::option::Option;
 use std::collections::VecDeque;
@@ -11,7 +11,12 @@ use num_cpus;
 pub enum OptionWork {
     ClearTT,
     ResizeTT(usize),
-    Threads(usize)
+    Threads(usize),
+    Chess960(bool),
+    OwnBook(bool),
+    BookFile(String),
+    SyzygyPath(String),
+    Contempt(i32)
 }
 
 impl OptionWork {
@@ -19,7 +24,12 @@ impl OptionWork {
         match *self {
             OptionWork::ClearTT => false,
             OptionWork::ResizeTT(_) => false,
-            OptionWork::Threads(_) => false
+            OptionWork::Threads(_) => false,
+            OptionWork::Chess960(_) => true,
+            OptionWork::OwnBook(_) => true,
+            OptionWork::BookFile(_) => true,
+            OptionWork::SyzygyPath(_) => false,
+            OptionWork::Contempt(_) => true
         }
     }
 }
@@ -38,6 +48,11 @@ impl OptionsMap {
         map.push(OptionsMap::clear_hash());
         map.push(OptionsMap::resize_hash());
         map.push(OptionsMap::threads());
+        map.push(OptionsMap::chess960());
+        map.push(OptionsMap::own_book());
+        map.push(OptionsMap::book_file());
+        map.push(OptionsMap::syzygy_path());
+        map.push(OptionsMap::contempt());
         map.sort_by(|a, b|
             a.option_name().cmp(b.option_name()));
 
@@ -105,6 +120,63 @@ impl OptionsMap {
             mutator
         })
     }
+
+    fn chess960() -> Box<UCIOption> {
+        let mutator: fn(bool) -> Option<OptionWork> = |x: bool| {
+            Some(OptionWork::Chess960(x))
+        };
+        Box::new(UCICheck {
+            option_name: "UCI_Chess960",
+            default: false,
+            mutator
+        })
+    }
+
+    fn own_book() -> Box<UCIOption> {
+        let mutator: fn(bool) -> Option<OptionWork> = |x: bool| {
+            Some(OptionWork::OwnBook(x))
+        };
+        Box::new(UCICheck {
+            option_name: "OwnBook",
+            default: false,
+            mutator
+        })
+    }
+
+    fn book_file() -> Box<UCIOption> {
+        let mutator: fn(&str) -> Option<OptionWork> = |x: &str| {
+            Some(OptionWork::BookFile(x.to_string()))
+        };
+        Box::new(UCIText {
+            option_name: "BookFile",
+            default: DEFAULT_BOOK_FILE,
+            mutator
+        })
+    }
+
+    fn syzygy_path() -> Box<UCIOption> {
+        let mutator: fn(&str) -> Option<OptionWork> = |x: &str| {
+            Some(OptionWork::SyzygyPath(x.to_string()))
+        };
+        Box::new(UCIText {
+            option_name: "SyzygyPath",
+            default: DEFAULT_SYZYGY_PATH,
+            mutator
+        })
+    }
+
+    fn contempt() -> Box<UCIOption> {
+        let mutator: fn(i32) -> Option<OptionWork> = |x: i32| {
+            Some(OptionWork::Contempt(x))
+        };
+        Box::new(UCISpin {
+            option_name: "Contempt",
+            default: DEFAULT_CONTEMPT,
+            min: -100,
+            max: 100,
+            mutator
+        })
+    }
 }
 
 
@@ -133,7 +133,7 @@ pub fn parse_time(args: &[&str]) -> PreLimits {
 
 fn valid_move(board: &mut Board, mov: &str) -> bool {
     let all_moves = board.generate_moves().iter()
-                         .map(|m| m.stringify())
+                         .map(|m| board.move_to_uci(*m))
                          .collect::<Vec<String>>();
 
     if all_moves.contains(&mov.to_string()) {
@@ -156,17 +156,25 @@ pub fn setboard_parse_board(args: &[&str]) -> Option<Board> {
     Board::new_from_fen(&fen_string).ok()
 }
 
-pub fn position_parse_board(args: &[&str]) -> Option<Board> {
+pub fn position_parse_board(args: &[&str], chess960: bool) -> Option<Board> {
     let start: &str = args[0];
     let mut board = if start == "startpos" {
-        Some(Board::default())
+        if chess960 {
+            Some(Board::new_chess960(518))
+        } else {
+            Some(Board::default())
+        }
     } else if start == "fen" {
         let fen_string: String = args[1..].iter()
                                           .take_while(|p: &&&str| **p != "moves")
                                           .map(|p| (*p).to_string())
                                           .collect::<Vec<String>>()
                                           .join(" ");
-        Board::new_from_fen(&fen_string).ok()
+        if chess960 {
+            Board::new_from_fen_chess960(&fen_string).ok()
+        } else {
+            Board::new_from_fen(&fen_string).ok()
+        }
     } else {
         None
     };
@@ -204,16 +212,25 @@ mod tests {
     fn board_parse() {
         let b_str = "position startpos moves e2e4 e7e5";
         let args: Vec<&str> = b_str.split_whitespace().collect();
-        let board = position_parse_board(&args[1..]).unwrap();
+        let board = position_parse_board(&args[1..], false).unwrap();
         assert_eq!(board.moves_played(), 2);
         assert_eq!(board.turn(), Player::White);
 
         let b_str = "position startpos";
         let args: Vec<&str> = b_str.split_whitespace().collect();
-        let board = position_parse_board(&args[1..]).unwrap();
+        let board = position_parse_board(&args[1..], false).unwrap();
         assert_eq!(board.moves_played(), 0);
     }
 
+    #[test]
+    fn board_parse_keeps_history() {
+        let b_str = "position startpos moves g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8";
+        let args: Vec<&str> = b_str.split_whitespace().collect();
+        let board = position_parse_board(&args[1..], false).unwrap();
+        assert!(board.is_repetition(3));
+        assert!(board.is_draw());
+    }
+
     #[test]
     fn time_parse() {
         let t_str = "go infinite searchmoves e2e4 d2d4";
@@ -228,6 +245,21 @@ mod tests {
         let old_str = "position startpos moves e2e4 d7d5 e4d5 d8d5 g1f3 d5e4 f1e2 c7c6 e1g1";
         // e8c8
         let args: Vec<&str> = old_str.split_whitespace().collect();
-        position_parse_board(&args[1..]).unwrap();
+        position_parse_board(&args[1..], false).unwrap();
+    }
+
+    #[test]
+    fn chess960_board_parse() {
+        let b_str = "position fen bqnnrkrb/pppppppp/8/8/8/8/PPPPPPPP/BQNNRKRB w GEge - 0 1 moves f1g1";
+        let args: Vec<&str> = b_str.split_whitespace().collect();
+        let board = position_parse_board(&args[1..], true).unwrap();
+        assert_eq!(board.moves_played(), 1);
+        assert!(board.has_castled(Player::White));
+
+        let b_str = "position startpos moves e2e4 e7e5 g1f3 b8c6 f1c4 g8f6 e1h1";
+        let args: Vec<&str> = b_str.split_whitespace().collect();
+        let board = position_parse_board(&args[1..], true).unwrap();
+        assert_eq!(board.moves_played(), 7);
+        assert!(board.has_castled(Player::White));
     }
 }
\ No newline at end of file